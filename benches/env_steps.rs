@@ -0,0 +1,40 @@
+//! Steps per second of the training environment against a computer snake,
+//! which bounds how fast an agent can learn on it.
+//!
+//! ```text
+//! cargo bench --bench env_steps
+//! ```
+
+use bevy::prelude::*;
+use bevy_movment::{
+    ai::ai::Difficulty,
+    sim::env::{Action, Env, EnvConfig},
+};
+use std::{hint::black_box, time::Instant};
+
+const STEPS: u32 = 100_000;
+
+fn main() {
+    let mut env = Env::new(EnvConfig {
+        opponents: vec![Difficulty::Greedy],
+        ..default()
+    });
+    let mut seed = 0;
+    env.reset(seed);
+
+    let start = Instant::now();
+    for step in 0..STEPS {
+        let action = Action::ALL[step as usize % Action::ALL.len()];
+        let (observation, _, done, _) = env.step(action);
+        black_box(observation);
+        if done {
+            seed += 1;
+            env.reset(seed);
+        }
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{STEPS} steps in {elapsed:?}, {:.0} steps per second",
+        STEPS as f64 / elapsed.as_secs_f64()
+    );
+}
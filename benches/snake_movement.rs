@@ -0,0 +1,127 @@
+//! Time per step of snakes of very different lengths. A step only moves the
+//! tail piece, so the numbers should stay flat as the body grows. A growing
+//! snake reuses the shared mesh and materials, so the asset counts printed
+//! after it stay the same too.
+//!
+//! ```text
+//! cargo bench --bench snake_movement
+//! ```
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use bevy_movment::{
+    IsPaused, MyAssets,
+    board::{
+        board::Board,
+        occupancy::{CellContent, Occupancy},
+        portal::Portals,
+    },
+    mode::mode::{FoodEaten, GameMode},
+    player::{
+        food::FoodZones,
+        player::{
+            Direction, GridPosition, PendingGrowth, PlayerId, SNAKE_Z, SnakeHead,
+            SnakeRenderAssets, SnakeSegment, SnakeSegments, cell_to_world, snake_movement_system,
+        },
+    },
+};
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+const LENGTHS: [usize; 3] = [10, 1_000, 10_000];
+const STEPS: u32 = 2_000;
+const GROWTH: u32 = 5_000;
+
+fn main() {
+    for length in LENGTHS {
+        let mut app = snake_app(length);
+        // Let the clock start before timing anything
+        app.update();
+        let start = Instant::now();
+        for _ in 0..STEPS {
+            app.update();
+        }
+        let per_step = start.elapsed() / STEPS;
+        black_box(&app);
+        println!("{length:>6} segments: {per_step:?} per step");
+    }
+
+    let mut app = snake_app(LENGTHS[0]);
+    app.update();
+    let mut snakes = app
+        .world_mut()
+        .query::<(&mut PendingGrowth, &SnakeSegments)>();
+    snakes.single_mut(app.world_mut()).unwrap().0.0 = GROWTH;
+    for grown in 0..=GROWTH {
+        if grown % (GROWTH / 5) == 0 {
+            let length = snakes.single(app.world()).unwrap().1.0.len();
+            let world = app.world();
+            let meshes = world.resource::<Assets<Mesh>>().len();
+            let materials = world.resource::<Assets<ColorMaterial>>().len();
+            println!("{length:>6} segments: {meshes} meshes, {materials} materials");
+        }
+        app.update();
+    }
+}
+
+/// Zen mode on a board just big enough, so the snake never dies however its
+/// head runs over the body.
+fn snake_app(length: usize) -> App {
+    let mode = GameMode::Zen;
+    let step = Duration::from_secs_f32(mode.rules().step_seconds);
+    let board = Board {
+        half_extent: IVec2::splat(50),
+    };
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .insert_resource(TimeUpdateStrategy::ManualDuration(step))
+        .init_resource::<Assets<Mesh>>()
+        .init_resource::<Assets<ColorMaterial>>()
+        .init_resource::<SnakeRenderAssets>()
+        .init_resource::<MyAssets>()
+        .init_resource::<NextState<IsPaused>>()
+        .init_resource::<FoodZones>()
+        .init_resource::<Portals>()
+        .insert_resource(mode)
+        .add_event::<FoodEaten>()
+        .add_systems(Update, snake_movement_system);
+    app.world_mut()
+        .resource_mut::<Time<Virtual>>()
+        .set_max_delta(step);
+
+    let mut occupancy = Occupancy::new(board);
+    let mut cells = board.cells();
+    let head_cell = cells.next().unwrap();
+    let world = app.world_mut();
+    let head = world.spawn_empty().id();
+    let segments = cells
+        .take(length)
+        .map(|cell| {
+            let segment = world
+                .spawn((
+                    SnakeSegment,
+                    GridPosition(cell),
+                    Transform::from_translation(cell_to_world(cell, SNAKE_Z)),
+                ))
+                .id();
+            occupancy.set(cell, segment, CellContent::Body);
+            segment
+        })
+        .collect();
+    world.entity_mut(head).insert((
+        SnakeHead {
+            direction: Direction::Right,
+            next_move_timer: Timer::new(step, TimerMode::Repeating),
+        },
+        PlayerId(0),
+        SnakeSegments(segments),
+        PendingGrowth(0),
+        GridPosition(head_cell),
+        Transform::from_translation(cell_to_world(head_cell, SNAKE_Z)),
+    ));
+    occupancy.set(head_cell, head, CellContent::Head);
+    world.insert_resource(occupancy);
+    app
+}
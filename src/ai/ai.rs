@@ -0,0 +1,273 @@
+use crate::{
+    ai::pathfinding::{NavGrid, Path},
+    player::{
+        controller::{SnakeController, SnakeView},
+        player::Direction,
+    },
+};
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+/// How hard a computer snake plays.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Difficulty {
+    /// Shortest way to the nearest apple, no thought about what comes after.
+    Greedy,
+    /// Only takes an apple when it can still reach its own tail afterwards,
+    /// otherwise chases its tail until the way is clear.
+    #[default]
+    Safe,
+    /// Weighs every move by the room left behind it and keeps away from
+    /// other heads before going for food.
+    LookAhead,
+}
+
+impl Difficulty {
+    pub fn label(self) -> &'static str {
+        match self {
+            Difficulty::Greedy => "GREEDY",
+            Difficulty::Safe => "SAFE",
+            Difficulty::LookAhead => "LOOK-AHEAD",
+        }
+    }
+}
+
+/// Computer opponent joining the modes on the mode select screen, `None`
+/// to play alone.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct Opponent(pub Option<Difficulty>);
+
+impl Opponent {
+    pub fn label(self) -> &'static str {
+        self.0.map_or("OFF", Difficulty::label)
+    }
+
+    pub fn next(self) -> Self {
+        Self(match self.0 {
+            None => Some(Difficulty::Greedy),
+            Some(Difficulty::Greedy) => Some(Difficulty::Safe),
+            Some(Difficulty::Safe) => Some(Difficulty::LookAhead),
+            Some(Difficulty::LookAhead) => None,
+        })
+    }
+}
+
+/// Marks a computer snake. One that crashes leaves the board and the run
+/// goes on.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct AiSnake;
+
+/// Plans every step with the pathfinder, how far ahead depends on the
+/// difficulty.
+pub struct AiController {
+    pub difficulty: Difficulty,
+}
+
+impl SnakeController for AiController {
+    fn decide(&mut self, snake: &SnakeView) -> Option<Direction> {
+        let mut nav = NavGrid::new(snake.occupancy, snake.portals, snake.wrap);
+        // The tail moves on before the head gets there
+        if !snake.growing
+            && snake.body.len() > 1
+            && let Some(&tail) = snake.body.back()
+        {
+            nav.set_blocked(tail, false);
+        }
+
+        // Cells another head could step onto next, a head-on crash takes both out
+        let rival_reach: Vec<IVec2> = snake
+            .rival_heads
+            .iter()
+            .flat_map(|&cell| Direction::ALL.map(|direction| nav.step(cell, direction)))
+            .flatten()
+            .collect();
+
+        let plan = Planner {
+            nav: &nav,
+            body: snake.body,
+            food: snake.food,
+            rival_reach: &rival_reach,
+        };
+        plan.choose(self.difficulty, snake.direction)
+    }
+}
+
+pub struct AiPlugin;
+
+impl Plugin for AiPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Opponent>();
+    }
+}
+
+/// Everything a computer snake knows when it picks a move.
+struct Planner<'a> {
+    nav: &'a NavGrid<'a>,
+    /// Own cells, head first.
+    body: &'a VecDeque<IVec2>,
+    food: &'a [IVec2],
+    rival_reach: &'a [IVec2],
+}
+
+impl Planner<'_> {
+    fn head(&self) -> IVec2 {
+        self.body[0]
+    }
+
+    fn choose(&self, difficulty: Difficulty, current: Direction) -> Option<Direction> {
+        let to_food = self.nav.path(self.head(), |cell| self.food.contains(&cell));
+        match difficulty {
+            Difficulty::Greedy => to_food
+                .map(|path| path[0].0)
+                .or_else(|| self.roomiest_move(current)),
+            Difficulty::Safe => to_food
+                .filter(|path| self.safe_after(path))
+                .or_else(|| self.to_tail())
+                .map(|path| path[0].0)
+                .or_else(|| self.roomiest_move(current)),
+            Difficulty::LookAhead => self.look_ahead(current),
+        }
+    }
+
+    /// Moves that stay on the board onto a free cell, never straight back.
+    fn moves(&self, current: Direction) -> impl Iterator<Item = (Direction, IVec2)> + '_ {
+        Direction::ALL
+            .into_iter()
+            .filter(move |direction| self.body.len() == 1 || *direction != current.opposite())
+            .filter_map(|direction| Some((direction, self.nav.step(self.head(), direction)?)))
+            .filter(|(_, cell)| self.nav.is_free(*cell))
+    }
+
+    /// Free cells left reachable after stepping onto `cell`.
+    fn room_after(&self, cell: IVec2) -> usize {
+        let mut nav = self.nav.clone();
+        nav.set_blocked(cell, true);
+        nav.reachable(cell)
+    }
+
+    fn roomiest_move(&self, current: Direction) -> Option<Direction> {
+        self.moves(current)
+            .max_by_key(|(_, cell)| self.room_after(*cell))
+            .map(|(direction, _)| direction)
+    }
+
+    fn to_tail(&self) -> Option<Path> {
+        let tail = *self.body.back()?;
+        if self.body.len() < 2 {
+            return None;
+        }
+        self.nav.path(self.head(), |cell| cell == tail)
+    }
+
+    /// Plays `path` forward and checks the snake can still reach its own tail
+    /// once it has eaten at the end, which keeps it from sealing itself in.
+    fn safe_after(&self, path: &Path) -> bool {
+        let mut nav = self.nav.clone();
+        let mut body = self.body.clone();
+        for (i, &(_, cell)) in path.iter().enumerate() {
+            body.push_front(cell);
+            nav.set_blocked(cell, true);
+            // Eating on the last cell keeps the tail where it is
+            if i + 1 < path.len()
+                && let Some(tail) = body.pop_back()
+            {
+                nav.set_blocked(tail, false);
+            }
+        }
+
+        let (head, tail) = (body[0], *body.back().unwrap());
+        body.len() < 3 || nav.path(head, |cell| cell == tail).is_some()
+    }
+
+    fn look_ahead(&self, current: Direction) -> Option<Direction> {
+        self.moves(current)
+            .max_by_key(|&(_, cell)| {
+                let room = self.room_after(cell);
+                let fits = room >= self.body.len();
+                let contested = self.rival_reach.contains(&cell);
+
+                // Food seen from the cell after the move, closer is better
+                let mut nav = self.nav.clone();
+                nav.set_blocked(cell, true);
+                let food = nav
+                    .path(cell, |next| self.food.contains(&next))
+                    .map(|path| path.len() + 1);
+                let food = if self.food.contains(&cell) {
+                    Some(0)
+                } else {
+                    food
+                };
+
+                (fits, !contested, food.map(|steps| usize::MAX - steps), room)
+            })
+            .map(|(direction, _)| direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{
+        board::Board,
+        occupancy::{CellContent, Occupancy},
+        portal::Portals,
+    };
+
+    /// Snake of four heading right into a corridor with an apple at its dead
+    /// end. Eating it leaves the snake no way out.
+    ///
+    /// ```text
+    /// . . . . # # #
+    /// T B B H . . *
+    /// . . . . # # #
+    /// ```
+    fn dead_end_choice(difficulty: Difficulty) -> Option<Direction> {
+        let mut occupancy = Occupancy::new(Board {
+            half_extent: IVec2::new(3, 1),
+        });
+        for x in 1..=3 {
+            for y in [-1, 1] {
+                occupancy.set(IVec2::new(x, y), Entity::PLACEHOLDER, CellContent::Wall);
+            }
+        }
+        let body: VecDeque<IVec2> = (0..4).map(|i| IVec2::new(-i, 0)).collect();
+        for (i, &cell) in body.iter().enumerate() {
+            let content = if i == 0 {
+                CellContent::Head
+            } else {
+                CellContent::Body
+            };
+            occupancy.set(cell, Entity::PLACEHOLDER, content);
+        }
+        let food = [IVec2::new(3, 0)];
+        let portals = Portals::default();
+        let view = SnakeView {
+            entity: Entity::PLACEHOLDER,
+            direction: Direction::Right,
+            body: &body,
+            growing: false,
+            food: &food,
+            occupancy: &occupancy,
+            portals: &portals,
+            wrap: false,
+            rival_heads: Vec::new(),
+        };
+        AiController { difficulty }.decide(&view)
+    }
+
+    #[test]
+    fn greedy_walks_into_the_dead_end() {
+        assert_eq!(dead_end_choice(Difficulty::Greedy), Some(Direction::Right));
+    }
+
+    #[test]
+    fn careful_snakes_stay_out_of_the_dead_end() {
+        for difficulty in [Difficulty::Safe, Difficulty::LookAhead] {
+            let choice = dead_end_choice(difficulty);
+            assert!(
+                matches!(choice, Some(Direction::Up | Direction::Down)),
+                "{difficulty:?} chose {choice:?}"
+            );
+        }
+    }
+}
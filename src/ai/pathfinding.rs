@@ -0,0 +1,220 @@
+//! Breadth-first search over the board the way a grid snake moves on it:
+//! walls, obstacles, portals and snake pieces block, edges wrap when the mode
+//! says so and stepping onto a portal comes out of its partner.
+
+use crate::{
+    board::{
+        board::Board,
+        occupancy::{CellContent, Occupancy},
+        portal::Portals,
+    },
+    player::player::Direction,
+    sim::rules::next_cell,
+};
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+/// A route as the moves to make and the cell each one ends on.
+pub type Path = Vec<(Direction, IVec2)>;
+
+/// Snapshot of which cells a snake may enter. Cheap to clone, so a planner
+/// can play a route forward on a copy.
+#[derive(Clone)]
+pub struct NavGrid<'a> {
+    board: Board,
+    wrap: bool,
+    portals: &'a Portals,
+    blocked: Vec<bool>,
+}
+
+impl<'a> NavGrid<'a> {
+    /// Everything but food blocks.
+    pub fn new(occupancy: &Occupancy, portals: &'a Portals, wrap: bool) -> Self {
+        let board = occupancy.board();
+        let blocked = board
+            .cells()
+            .map(|cell| {
+                occupancy
+                    .get(cell)
+                    .is_some_and(|occupant| occupant.content != CellContent::Food)
+            })
+            .collect();
+        Self {
+            board,
+            wrap,
+            portals,
+            blocked,
+        }
+    }
+
+    pub fn is_free(&self, cell: IVec2) -> bool {
+        self.board.index(cell).is_some_and(|i| !self.blocked[i])
+    }
+
+    pub fn set_blocked(&mut self, cell: IVec2, blocked: bool) {
+        if let Some(i) = self.board.index(cell) {
+            self.blocked[i] = blocked;
+        }
+    }
+
+    /// Cell a head on `cell` ends up on after moving in `direction`, `None`
+    /// when that leaves the board. Whether the cell is free is not checked.
+    pub fn step(&self, cell: IVec2, direction: Direction) -> Option<IVec2> {
+        let next = next_cell(self.board, self.portals, self.wrap, cell, direction);
+        self.board.contains(next).then_some(next)
+    }
+
+    /// Shortest route from `start` to the nearest cell matching `goal`. Goal
+    /// cells count as enterable even when blocked, so a snake can aim for its
+    /// own tail.
+    pub fn path(&self, start: IVec2, goal: impl Fn(IVec2) -> bool) -> Option<Path> {
+        let mut came_from: Vec<Option<(Direction, IVec2)>> = vec![None; self.blocked.len()];
+        let mut seen = vec![false; self.blocked.len()];
+        seen[self.board.index(start)?] = true;
+        let mut queue = VecDeque::from([start]);
+
+        while let Some(cell) = queue.pop_front() {
+            for direction in Direction::ALL {
+                let Some(next) = self.step(cell, direction) else {
+                    continue;
+                };
+                let i = self.board.index(next).unwrap();
+                if seen[i] {
+                    continue;
+                }
+                let reached = goal(next);
+                if self.blocked[i] && !reached {
+                    continue;
+                }
+                seen[i] = true;
+                came_from[i] = Some((direction, cell));
+                if reached {
+                    return Some(self.walk_back(&came_from, start, next));
+                }
+                queue.push_back(next);
+            }
+        }
+        None
+    }
+
+    fn walk_back(
+        &self,
+        came_from: &[Option<(Direction, IVec2)>],
+        start: IVec2,
+        end: IVec2,
+    ) -> Path {
+        let mut path = Vec::new();
+        let mut cell = end;
+        while cell != start {
+            let (direction, previous) = came_from[self.board.index(cell).unwrap()].unwrap();
+            path.push((direction, cell));
+            cell = previous;
+        }
+        path.reverse();
+        path
+    }
+
+    /// Number of free cells reachable from `start`, not counting `start`.
+    pub fn reachable(&self, start: IVec2) -> usize {
+        let mut seen = vec![false; self.blocked.len()];
+        if let Some(i) = self.board.index(start) {
+            seen[i] = true;
+        }
+        let mut queue = VecDeque::from([start]);
+        let mut count = 0;
+
+        while let Some(cell) = queue.pop_front() {
+            for direction in Direction::ALL {
+                if let Some(next) = self.step(cell, direction)
+                    && let Some(i) = self.board.index(next)
+                    && !self.blocked[i]
+                    && !seen[i]
+                {
+                    seen[i] = true;
+                    count += 1;
+                    queue.push_back(next);
+                }
+            }
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Seven by three with a wall across the middle column.
+    fn split_board() -> Occupancy {
+        let mut occupancy = Occupancy::new(Board {
+            half_extent: IVec2::new(3, 1),
+        });
+        for y in -1..=1 {
+            occupancy.set(IVec2::new(0, y), Entity::PLACEHOLDER, CellContent::Wall);
+        }
+        occupancy
+    }
+
+    #[test]
+    fn finds_the_way_through_a_portal() {
+        let mut occupancy = split_board();
+        let mut portals = Portals::default();
+        let (a, b) = (IVec2::new(-1, 0), IVec2::new(1, 0));
+        portals.link(a, b);
+        occupancy.set(a, Entity::PLACEHOLDER, CellContent::Portal);
+        occupancy.set(b, Entity::PLACEHOLDER, CellContent::Portal);
+
+        let nav = NavGrid::new(&occupancy, &portals, false);
+        let path = nav.path(IVec2::new(-3, 0), |cell| cell == IVec2::new(3, 0));
+        assert_eq!(
+            path,
+            Some(vec![
+                (Direction::Right, IVec2::new(-2, 0)),
+                (Direction::Right, IVec2::new(2, 0)),
+                (Direction::Right, IVec2::new(3, 0)),
+            ])
+        );
+    }
+
+    #[test]
+    fn finds_the_way_round_a_wrapping_edge() {
+        let occupancy = split_board();
+        let portals = Portals::default();
+        let (start, goal) = (IVec2::new(-1, 0), IVec2::new(1, 0));
+
+        let walled_in = NavGrid::new(&occupancy, &portals, false);
+        assert_eq!(walled_in.path(start, |cell| cell == goal), None);
+        assert_eq!(walled_in.reachable(start), 8);
+
+        let nav = NavGrid::new(&occupancy, &portals, true);
+        let path = nav.path(start, |cell| cell == goal).unwrap();
+        let cells: Vec<IVec2> = path.iter().map(|&(_, cell)| cell).collect();
+        assert_eq!(
+            cells,
+            [
+                IVec2::new(-2, 0),
+                IVec2::new(-3, 0),
+                IVec2::new(3, 0),
+                IVec2::new(2, 0),
+                goal
+            ]
+        );
+        assert_eq!(path[2].0, Direction::Left);
+        assert_eq!(nav.reachable(start), 17);
+    }
+
+    #[test]
+    fn blocked_goals_can_still_be_reached() {
+        let mut occupancy = split_board();
+        let tail = IVec2::new(-2, 0);
+        occupancy.set(tail, Entity::PLACEHOLDER, CellContent::Body);
+        let portals = Portals::default();
+        let nav = NavGrid::new(&occupancy, &portals, false);
+        assert!(!nav.is_free(tail));
+        assert_eq!(
+            nav.path(IVec2::new(-3, 0), |cell| cell == tail),
+            Some(vec![(Direction::Right, tail)])
+        );
+        assert_eq!(nav.step(IVec2::new(-3, 0), Direction::Left), None);
+    }
+}
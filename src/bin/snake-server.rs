@@ -0,0 +1,109 @@
+//! Runs online matches without a window. The server plays every round itself,
+//! checks the turns players send and shows them where the snakes are. Players
+//! start the game with `--server ADDRESS` and pick SERVER on the online
+//! screen.
+//!
+//! ```text
+//! cargo run --release --bin snake-server -- --mode versus --style maze
+//! cargo run --release --bin snake-server -- --bind 127.0.0.1:47475 --rounds 3
+//! ```
+
+use bevy::{app::ScheduleRunnerPlugin, log::LogPlugin, prelude::*};
+use bevy_movment::{
+    board::{board::Board, generator::BoardStyle},
+    mode::mode::GameMode,
+    net::{
+        lockstep::MatchSetup,
+        server::{MAX_SERVER_SIDE, SERVER_PORT, ServerConfig, ServerPlugin},
+    },
+};
+use std::{process::ExitCode, time::Duration};
+
+const USAGE: &str = "\
+usage: snake-server [options]
+
+  --bind ADDRESS   UDP address to listen on (0.0.0.0:47475)
+  --mode NAME      classic, wrap, time-attack, zen or versus (versus)
+  --board WxH      board size in cells (31x23)
+  --style NAME     open, maze, rocks or rooms (open)
+  --seed N         seed of the first round, the rest count up (random)
+  --tick-ms MS     time per step (the mode's own)
+  --rounds N       rounds before the server shuts down (no limit)";
+
+const DEFAULT_BOARD: Board = Board {
+    half_extent: IVec2::new(15, 11),
+};
+
+fn main() -> ExitCode {
+    if std::env::args().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+
+    let config = match parse_args(std::env::args().skip(1)) {
+        Ok(config) => config,
+        Err(message) => {
+            eprintln!("{message}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut app = App::new();
+    // Updates only wait on players and need not spin
+    app.add_plugins((
+        MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_millis(1))),
+        LogPlugin::default(),
+    ))
+    .insert_resource(config)
+    .add_plugins(ServerPlugin);
+
+    match app.run() {
+        AppExit::Success => ExitCode::SUCCESS,
+        AppExit::Error(_) => ExitCode::FAILURE,
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<ServerConfig, String> {
+    let mut setup = MatchSetup {
+        seed: rand::random(),
+        mode: GameMode::Versus,
+        style: BoardStyle::Open,
+        board: DEFAULT_BOARD,
+    };
+    let mut bind = format!("0.0.0.0:{SERVER_PORT}");
+    let mut tick_time = None;
+    let mut rounds = None;
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or_else(|| format!("{flag} needs a value"))?;
+        let bad = || format!("bad value for {flag}: {value}");
+        match flag.as_str() {
+            "--bind" => bind = value,
+            "--mode" => setup.mode = GameMode::from_arg(&value).ok_or_else(bad)?,
+            "--board" => {
+                setup.board = Board::from_arg(&value).map_err(|err| format!("{flag}: {err}"))?;
+                if setup.board.width().max(setup.board.height()) > MAX_SERVER_SIDE {
+                    return Err(format!(
+                        "{flag}: {value} is too big, servers play on at most \
+                         {MAX_SERVER_SIDE}x{MAX_SERVER_SIDE}"
+                    ));
+                }
+            }
+            "--style" => setup.style = BoardStyle::from_arg(&value).ok_or_else(bad)?,
+            "--seed" => setup.seed = value.parse().map_err(|_| bad())?,
+            "--tick-ms" => {
+                let millis: u64 = value.parse().ok().filter(|ms| *ms > 0).ok_or_else(bad)?;
+                tick_time = Some(Duration::from_millis(millis));
+            }
+            "--rounds" => rounds = Some(value.parse().ok().filter(|n| *n > 0).ok_or_else(bad)?),
+            _ => return Err(format!("unknown option {flag}")),
+        }
+    }
+
+    Ok(ServerConfig {
+        bind,
+        setup,
+        tick_time: tick_time
+            .unwrap_or_else(|| Duration::from_secs_f32(setup.mode.rules().step_seconds)),
+        rounds,
+    })
+}
@@ -0,0 +1,388 @@
+//! Plays batches of computer games without a window and prints how they went,
+//! so speed and food rules can be tuned without playing by hand. The
+//! `tournament` command pits controllers against each other instead, and
+//! `lockstep` and `rollback` play networked matches between computer players
+//! to test the netcode.
+//!
+//! ```text
+//! cargo run --release --bin snake-sim -- --games 500 --ai look-ahead --format csv
+//! cargo run --release --bin snake-sim -- tournament --players greedy,safe,bot:127.0.0.1:7000
+//! cargo run --release --bin snake-sim -- lockstep --loss 0.2 --latency 40
+//! cargo run --release --bin snake-sim -- lockstep --bind 127.0.0.1:7100 --peer 127.0.0.1:7101 --player 0
+//! cargo run --release --bin snake-sim -- rollback --latency 60 --tick-ms 50
+//! ```
+
+use bevy::{app::ScheduleRunnerPlugin, prelude::*};
+use bevy_movment::{
+    ai::ai::Difficulty,
+    board::{board::Board, generator::BoardStyle},
+    mode::mode::{GameMode, ModeRules},
+    net::{
+        harness::{NetLink, NetTestConfig, NetTestPlugin, Netcode},
+        lockstep::{DEFAULT_INPUT_DELAY, MatchSetup},
+        rollback::DEFAULT_MAX_ROLLBACK,
+        transport::LinkConditions,
+    },
+    player::{
+        bot::{BotLink, BotTarget, DEFAULT_BOT_TIMEOUT},
+        player::MAX_SNAKES,
+    },
+    sim::{
+        batch::{BatchConfig, BatchPlugin, OutputFormat},
+        tournament::{Entrant, EntrantKind, TournamentConfig, TournamentPlugin},
+    },
+};
+use std::{path::PathBuf, process::ExitCode, time::Duration};
+
+const USAGE: &str = "\
+usage: snake-sim [options]
+       snake-sim tournament [tournament options]
+       snake-sim lockstep [network options]
+       snake-sim rollback [network options]
+
+  --games N        games to play (100)
+  --seed N         seed of the first game, the rest count up (0)
+  --mode NAME      classic, wrap, time-attack, zen or versus (classic)
+  --board WxH      board size in cells (31x23)
+  --style NAME     open, maze, rocks or rooms (open)
+  --snakes N       computer snakes per game, up to 3 (as many as the mode has
+                   players)
+  --step-ms MS     time per step, the clock of timed modes runs down by it
+                   (the mode's own)
+  --time-limit S   seconds on the clock, 0 for none (the mode's own)
+  --bonus S        seconds each apple adds to the clock (the mode's own)
+  --points N       points per apple (the mode's own)
+  --start-length N pieces a snake grows on its first steps (the mode's own)
+  --ai NAME        greedy, safe or look-ahead (safe)
+  --bot ADDRESS    let the bot listening there play the first snake
+  --bot-timeout MS how long the bot gets to answer (100)
+  --max-ticks N    steps before a game is called off (10000)
+  --format NAME    json for a summary, csv for one row per snake (json)
+
+tournament options:
+
+  --players LIST   comma separated entrants, each greedy, safe, look-ahead or
+                   bot:ADDRESS (greedy,safe,look-ahead)
+  --seeds N        boards every pair plays on, once from each side (10)
+  --seed N         seed of the first board (0)
+  --mode NAME      as above (versus)
+  --board WxH      as above (31x23)
+  --style NAME     as above (open)
+  --bot-timeout MS as above (100)
+  --max-ticks N    steps before a match goes to the higher score (10000)
+  --out DIR        where results.csv and the replays go (tournament)
+
+network options, both peers in this process unless --peer is given:
+
+  --bind ADDRESS   local UDP address of this peer (0.0.0.0:0)
+  --peer ADDRESS   UDP address of the other peer
+  --player N       snake this peer plays, 0 or 1 (0)
+  --seed N         seed of the board, the same on both peers (0)
+  --mode NAME      as above (versus)
+  --board WxH      as above (31x23)
+  --style NAME     as above (open)
+  --ai NAME        who plays on each side (safe)
+  --delay N        ticks a turn is sent ahead, computer players only see the
+                   board their turn is played on without one (3)
+  --loss P         share of packets dropped on purpose, 0 to 1 (0)
+  --latency MS     delay added to every packet (0)
+  --jitter MS      up to this much more delay per packet (0)
+  --tick-ms MS     time per step, 0 for as fast as the link allows (0)
+  --max-rollback N steps rollback may guess ahead of the peer (8)
+  --max-ticks N    steps before the match is called off (2000)";
+
+const DEFAULT_BOARD: Board = Board {
+    half_extent: IVec2::new(15, 11),
+};
+
+fn main() -> ExitCode {
+    if std::env::args().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+
+    let mut args = std::env::args().skip(1).peekable();
+    let command =
+        args.next_if(|arg| ["tournament", "lockstep", "rollback"].contains(&arg.as_str()));
+    let mut app = App::new();
+    // Batch updates play a whole game each and need no wait in between, most
+    // network updates only wait on the peer and need not spin
+    let wait = match command.as_deref() {
+        Some("lockstep" | "rollback") => Duration::from_millis(1),
+        _ => Duration::ZERO,
+    };
+    app.add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(wait)));
+
+    let parsed = match command.as_deref() {
+        Some("tournament") => parse_tournament_args(args).map(|config| {
+            app.insert_resource(config).add_plugins(TournamentPlugin);
+        }),
+        Some(command @ ("lockstep" | "rollback")) => {
+            let netcode = match command {
+                "lockstep" => Netcode::Lockstep,
+                _ => Netcode::Rollback {
+                    max_rollback: DEFAULT_MAX_ROLLBACK,
+                },
+            };
+            parse_net_args(args, netcode).map(|config| {
+                app.insert_resource(config).add_plugins(NetTestPlugin);
+            })
+        }
+        _ => parse_args(args).map(|config| {
+            app.insert_resource(config).add_plugins(BatchPlugin);
+        }),
+    };
+    if let Err(message) = parsed {
+        eprintln!("{message}\n\n{USAGE}");
+        return ExitCode::from(2);
+    }
+
+    match app.run() {
+        AppExit::Success => ExitCode::SUCCESS,
+        AppExit::Error(_) => ExitCode::FAILURE,
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<BatchConfig, String> {
+    let mut config = BatchConfig {
+        games: 100,
+        first_seed: 0,
+        mode: GameMode::Classic,
+        style: BoardStyle::Open,
+        board: DEFAULT_BOARD,
+        rules: GameMode::Classic.rules(),
+        snakes: 0,
+        difficulty: Difficulty::Safe,
+        bot: None,
+        max_ticks: 10_000,
+        format: OutputFormat::Json,
+    };
+
+    let mut bot = None;
+    let mut bot_timeout = DEFAULT_BOT_TIMEOUT;
+    // Rule changes go on top of the mode, whichever order the flags come in
+    let mut rules = RuleChanges::default();
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or_else(|| format!("{flag} needs a value"))?;
+        let bad = || format!("bad value for {flag}: {value}");
+        match flag.as_str() {
+            "--games" => config.games = value.parse().map_err(|_| bad())?,
+            "--seed" => config.first_seed = value.parse().map_err(|_| bad())?,
+            "--snakes" => {
+                config.snakes = value
+                    .parse()
+                    .ok()
+                    .filter(|snakes| *snakes <= MAX_SNAKES)
+                    .ok_or_else(bad)?;
+            }
+            "--step-ms" => {
+                let millis: u64 = value.parse().ok().filter(|ms| *ms > 0).ok_or_else(bad)?;
+                rules.step_seconds = Some(millis as f32 / 1000.0);
+            }
+            "--time-limit" => {
+                let seconds: f32 = value.parse().ok().filter(|s| *s >= 0.0).ok_or_else(bad)?;
+                rules.time_limit = Some(Some(seconds).filter(|seconds| *seconds > 0.0));
+            }
+            "--bonus" => rules.bonus_seconds = Some(value.parse().map_err(|_| bad())?),
+            "--points" => rules.points_per_food = Some(value.parse().map_err(|_| bad())?),
+            "--start-length" => rules.start_length = Some(value.parse().map_err(|_| bad())?),
+            "--max-ticks" => config.max_ticks = value.parse().map_err(|_| bad())?,
+            "--mode" => config.mode = GameMode::from_arg(&value).ok_or_else(bad)?,
+            "--board" => {
+                config.board = Board::from_arg(&value).map_err(|err| format!("{flag}: {err}"))?
+            }
+            "--style" => config.style = BoardStyle::from_arg(&value).ok_or_else(bad)?,
+            "--ai" => config.difficulty = parse_difficulty(&value).ok_or_else(bad)?,
+            // The statistics go to stdout, so the bot has to be on a socket
+            "--bot" if value == "stdio" => return Err("--bot needs an address here".to_string()),
+            "--bot" => bot = Some(BotTarget::Socket(value)),
+            "--bot-timeout" => {
+                bot_timeout = Duration::from_millis(value.parse().map_err(|_| bad())?)
+            }
+            "--format" => {
+                config.format = match value.as_str() {
+                    "json" => OutputFormat::Json,
+                    "csv" => OutputFormat::Csv,
+                    _ => return Err(bad()),
+                }
+            }
+            _ => return Err(format!("unknown option {flag}")),
+        }
+    }
+
+    config.bot = bot.map(|target| BotLink::new(target, bot_timeout));
+    config.rules = rules.apply(config.mode.rules());
+    if config.snakes == 0 {
+        config.snakes = config.rules.players;
+    }
+    Ok(config)
+}
+
+/// Rules given on the command line, each replaces the mode's own.
+#[derive(Default)]
+struct RuleChanges {
+    step_seconds: Option<f32>,
+    time_limit: Option<Option<f32>>,
+    bonus_seconds: Option<f32>,
+    points_per_food: Option<u32>,
+    start_length: Option<u32>,
+}
+
+impl RuleChanges {
+    fn apply(&self, rules: ModeRules) -> ModeRules {
+        ModeRules {
+            step_seconds: self.step_seconds.unwrap_or(rules.step_seconds),
+            time_limit: self.time_limit.unwrap_or(rules.time_limit),
+            bonus_seconds: self.bonus_seconds.unwrap_or(rules.bonus_seconds),
+            points_per_food: self.points_per_food.unwrap_or(rules.points_per_food),
+            start_length: self.start_length.unwrap_or(rules.start_length),
+            ..rules
+        }
+    }
+}
+
+fn parse_tournament_args(
+    mut args: impl Iterator<Item = String>,
+) -> Result<TournamentConfig, String> {
+    let mut config = TournamentConfig {
+        entrants: Vec::new(),
+        seeds: 10,
+        first_seed: 0,
+        mode: GameMode::Versus,
+        style: BoardStyle::Open,
+        board: DEFAULT_BOARD,
+        max_ticks: 10_000,
+        out: PathBuf::from("tournament"),
+    };
+
+    let mut players = "greedy,safe,look-ahead".to_string();
+    let mut bot_timeout = DEFAULT_BOT_TIMEOUT;
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or_else(|| format!("{flag} needs a value"))?;
+        let bad = || format!("bad value for {flag}: {value}");
+        match flag.as_str() {
+            "--players" => players = value,
+            "--seeds" => config.seeds = value.parse().map_err(|_| bad())?,
+            "--seed" => config.first_seed = value.parse().map_err(|_| bad())?,
+            "--max-ticks" => config.max_ticks = value.parse().map_err(|_| bad())?,
+            "--mode" => config.mode = GameMode::from_arg(&value).ok_or_else(bad)?,
+            "--board" => {
+                config.board = Board::from_arg(&value).map_err(|err| format!("{flag}: {err}"))?
+            }
+            "--style" => config.style = BoardStyle::from_arg(&value).ok_or_else(bad)?,
+            "--bot-timeout" => {
+                bot_timeout = Duration::from_millis(value.parse().map_err(|_| bad())?)
+            }
+            "--out" => config.out = PathBuf::from(value),
+            _ => return Err(format!("unknown option {flag}")),
+        }
+    }
+
+    for name in players.split(',').map(str::trim) {
+        let kind = match name.strip_prefix("bot:") {
+            Some(address) => EntrantKind::Bot(BotLink::new(
+                BotTarget::Socket(address.to_string()),
+                bot_timeout,
+            )),
+            None => EntrantKind::Ai(
+                parse_difficulty(name).ok_or_else(|| format!("unknown player {name}"))?,
+            ),
+        };
+        // The same controller entered twice gets a number to tell them apart
+        let taken = config
+            .entrants
+            .iter()
+            .filter(|entrant| entrant.name.split('#').next() == Some(name))
+            .count();
+        let name = match taken {
+            0 => name.to_string(),
+            _ => format!("{name}#{}", taken + 1),
+        };
+        config.entrants.push(Entrant { name, kind });
+    }
+    if config.entrants.len() < 2 {
+        return Err("a tournament needs at least two players".to_string());
+    }
+    Ok(config)
+}
+
+fn parse_net_args(
+    mut args: impl Iterator<Item = String>,
+    netcode: Netcode,
+) -> Result<NetTestConfig, String> {
+    let mut config = NetTestConfig {
+        setup: MatchSetup {
+            seed: 0,
+            mode: GameMode::Versus,
+            style: BoardStyle::Open,
+            board: DEFAULT_BOARD,
+        },
+        netcode,
+        difficulty: Difficulty::Safe,
+        input_delay: DEFAULT_INPUT_DELAY,
+        conditions: LinkConditions::default(),
+        max_ticks: 2_000,
+        tick_time: Duration::ZERO,
+        link: NetLink::Loopback,
+    };
+
+    let mut bind = "0.0.0.0:0".to_string();
+    let mut peer = None;
+    let mut player = 0;
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or_else(|| format!("{flag} needs a value"))?;
+        let bad = || format!("bad value for {flag}: {value}");
+        let millis = || value.parse().map(Duration::from_millis).map_err(|_| bad());
+        match flag.as_str() {
+            "--bind" => bind = value,
+            "--peer" => peer = Some(value),
+            "--player" => {
+                player = value.parse().ok().filter(|p| *p < 2).ok_or_else(bad)?;
+            }
+            "--seed" => config.setup.seed = value.parse().map_err(|_| bad())?,
+            "--mode" => config.setup.mode = GameMode::from_arg(&value).ok_or_else(bad)?,
+            "--board" => {
+                config.setup.board =
+                    Board::from_arg(&value).map_err(|err| format!("{flag}: {err}"))?
+            }
+            "--style" => config.setup.style = BoardStyle::from_arg(&value).ok_or_else(bad)?,
+            "--ai" => config.difficulty = parse_difficulty(&value).ok_or_else(bad)?,
+            "--delay" => config.input_delay = value.parse().map_err(|_| bad())?,
+            "--loss" => {
+                config.conditions.loss = value
+                    .parse()
+                    .ok()
+                    .filter(|loss| (0.0..1.0).contains(loss))
+                    .ok_or_else(bad)?;
+            }
+            "--latency" => config.conditions.latency = millis()?,
+            "--jitter" => config.conditions.jitter = millis()?,
+            "--tick-ms" => config.tick_time = millis()?,
+            "--max-rollback" => match &mut config.netcode {
+                Netcode::Rollback { max_rollback } => {
+                    *max_rollback = value.parse().map_err(|_| bad())?
+                }
+                Netcode::Lockstep => return Err("--max-rollback is for rollback".to_string()),
+            },
+            "--max-ticks" => config.max_ticks = value.parse().map_err(|_| bad())?,
+            _ => return Err(format!("unknown option {flag}")),
+        }
+    }
+
+    if config.setup.mode.rules().players < 2 {
+        return Err("a networked match needs a mode for two players".to_string());
+    }
+    if let Some(peer) = peer {
+        config.link = NetLink::Udp { bind, peer, player };
+    }
+    Ok(config)
+}
+
+fn parse_difficulty(value: &str) -> Option<Difficulty> {
+    Some(match value {
+        "greedy" => Difficulty::Greedy,
+        "safe" => Difficulty::Safe,
+        "look-ahead" => Difficulty::LookAhead,
+        _ => return None,
+    })
+}
@@ -0,0 +1,173 @@
+use crate::player::player::GRID_SIZE;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Longest side [`Board::from_arg`] accepts, larger boards take more memory
+/// and time per step than anyone would wait for.
+pub const MAX_SIDE: i32 = 1000;
+
+/// Playable area in grid cells, centred on the world origin so cell `(0, 0)`
+/// is the middle of the screen.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Board {
+    pub half_extent: IVec2,
+}
+
+impl Board {
+    /// Largest board whose cells fit completely inside the window.
+    pub fn from_window(window_w: f32, window_h: f32) -> Self {
+        let half = |len: f32| (((len / GRID_SIZE) - 1.0) / 2.0).floor().max(0.0) as i32;
+        Self {
+            half_extent: IVec2::new(half(window_w), half(window_h)),
+        }
+    }
+
+    /// Board from `WxH` in cells. Boards are centred on a cell, so even sizes
+    /// round down.
+    pub fn from_arg(value: &str) -> Result<Self, String> {
+        let size = value
+            .split_once('x')
+            .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)));
+        let Some((width, height)): Option<(i32, i32)> = size else {
+            return Err(format!("{value} is not a size like 31x23"));
+        };
+        if width < 3 || height < 3 {
+            return Err(format!("{value} is too small, boards are at least 3x3"));
+        }
+        if width > MAX_SIDE || height > MAX_SIDE {
+            return Err(format!(
+                "{value} is too big, boards are at most {MAX_SIDE}x{MAX_SIDE}"
+            ));
+        }
+        Ok(Self {
+            half_extent: IVec2::new((width - 1) / 2, (height - 1) / 2),
+        })
+    }
+
+    pub fn min(&self) -> IVec2 {
+        -self.half_extent
+    }
+
+    pub fn max(&self) -> IVec2 {
+        self.half_extent
+    }
+
+    pub fn width(&self) -> i32 {
+        self.half_extent.x * 2 + 1
+    }
+
+    pub fn height(&self) -> i32 {
+        self.half_extent.y * 2 + 1
+    }
+
+    pub fn contains(&self, cell: IVec2) -> bool {
+        cell.cmpge(self.min()).all() && cell.cmple(self.max()).all()
+    }
+
+    /// Same cell folded back onto the board, as if opposite edges were joined.
+    pub fn wrap(&self, cell: IVec2) -> IVec2 {
+        let size = IVec2::new(self.width(), self.height());
+        self.min() + (cell - self.min()).rem_euclid(size)
+    }
+
+    /// Shortest step from `from` to `to`. On a wrapping board the way round
+    /// through an edge counts too.
+    pub fn delta(&self, from: IVec2, to: IVec2, wrap: bool) -> IVec2 {
+        let delta = to - from;
+        if !wrap {
+            return delta;
+        }
+        let size = IVec2::new(self.width(), self.height());
+        (delta + size / 2).rem_euclid(size) - size / 2
+    }
+
+    /// Manhattan distance between two cells, see [`Board::delta`].
+    pub fn distance(&self, from: IVec2, to: IVec2, wrap: bool) -> i32 {
+        self.delta(from, to, wrap).abs().element_sum()
+    }
+
+    /// Row-major index of a cell, `None` when it lies outside the board.
+    pub fn index(&self, cell: IVec2) -> Option<usize> {
+        if !self.contains(cell) {
+            return None;
+        }
+        let local = cell - self.min();
+        Some((local.y * self.width() + local.x) as usize)
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = IVec2> + use<> {
+        let (min, max) = (self.min(), self.max());
+        (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| IVec2::new(x, y)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_from_the_command_line() {
+        let board = Board::from_arg("31x24").unwrap();
+        assert_eq!((board.width(), board.height()), (31, 23));
+        assert!(Board::from_arg("1000x3").is_ok());
+        for bad in ["31", "31x", "ax9", "2x9", "9x-9", "1001x9", "9x100000"] {
+            assert!(Board::from_arg(bad).is_err(), "{bad}");
+        }
+    }
+    /// Five by three, so rows and columns wrap at different lengths.
+    const SMALL: Board = Board {
+        half_extent: IVec2::new(2, 1),
+    };
+
+    #[test]
+    fn wrapping_folds_cells_past_each_edge_back_on() {
+        for cell in SMALL.cells() {
+            assert_eq!(SMALL.wrap(cell), cell);
+        }
+        assert_eq!(SMALL.wrap(IVec2::new(3, 0)), IVec2::new(-2, 0));
+        assert_eq!(SMALL.wrap(IVec2::new(-3, 0)), IVec2::new(2, 0));
+        assert_eq!(SMALL.wrap(IVec2::new(0, 2)), IVec2::new(0, -1));
+        assert_eq!(SMALL.wrap(IVec2::new(0, -2)), IVec2::new(0, 1));
+        assert_eq!(SMALL.wrap(IVec2::new(3, -2)), IVec2::new(-2, 1));
+        // Several times round
+        assert_eq!(SMALL.wrap(IVec2::new(-8, -4)), IVec2::new(2, -1));
+        assert_eq!(SMALL.wrap(IVec2::new(12, 7)), IVec2::new(2, 1));
+    }
+
+    #[test]
+    fn the_short_way_round_goes_through_the_edge() {
+        let (left, right) = (IVec2::new(-2, 0), IVec2::new(2, 0));
+        assert_eq!(SMALL.delta(right, left, false), IVec2::new(-4, 0));
+        assert_eq!(SMALL.delta(right, left, true), IVec2::new(1, 0));
+        assert_eq!(SMALL.delta(left, right, true), IVec2::new(-1, 0));
+        // Half way across is as far as it gets either way
+        assert_eq!(SMALL.delta(IVec2::ZERO, right, true), IVec2::new(2, 0));
+        assert_eq!(SMALL.delta(IVec2::ZERO, left, true), IVec2::new(-2, 0));
+        assert_eq!(
+            SMALL.delta(IVec2::new(0, 1), IVec2::new(0, -1), true),
+            IVec2::new(0, 1)
+        );
+
+        let (corner, opposite) = (SMALL.max(), SMALL.min());
+        assert_eq!(SMALL.distance(corner, opposite, false), 6);
+        assert_eq!(SMALL.distance(corner, opposite, true), 2);
+        assert_eq!(SMALL.distance(opposite, corner, true), 2);
+    }
+
+    #[test]
+    fn every_delta_leads_to_its_cell() {
+        // An even size asked for rounds down to the odd one below
+        for board in ["6x4", "7x5"].map(|size| Board::from_arg(size).unwrap()) {
+            let half = IVec2::new(board.width(), board.height()) / 2;
+            for from in board.cells() {
+                for to in board.cells() {
+                    assert_eq!(from + board.delta(from, to, false), to);
+                    let delta = board.delta(from, to, true);
+                    assert_eq!(board.wrap(from + delta), to);
+                    assert!(delta.abs().cmple(half).all(), "{from} to {to}: {delta}");
+                    assert!(board.distance(from, to, true) <= board.distance(from, to, false));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,328 @@
+//! Procedural boards. Every generator works on a plain blocked/free grid and
+//! then goes through the same clean-up, so the guarantees hold for all of them:
+//! every free cell can be reached from the spawn point, and the first step the
+//! snake takes is onto a free cell.
+
+use crate::{board::board::Board, level::layout::LevelLayout, player::player::Direction};
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Share of cells the rock generator fills.
+const ROCK_DENSITY: f64 = 0.08;
+
+/// Share of maze dead ends that get opened up, a perfect maze is a death trap
+/// for anything longer than a few pieces.
+const MAZE_BRAIDING: f64 = 0.6;
+
+/// Cells around the middle of the board the rock generator keeps clear.
+const SPAWN_CLEARANCE: i32 = 2;
+
+/// How non-campaign boards are filled, picked on the mode select screen.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Default, Debug, Serialize, Deserialize)]
+pub enum BoardStyle {
+    /// Nothing on the board, the classic game.
+    #[default]
+    Open,
+    /// Recursive backtracker maze with most dead ends knocked out.
+    Maze,
+    /// Scattered single rocks.
+    Rocks,
+    /// Rectangular rooms joined by corridors.
+    Rooms,
+}
+
+impl BoardStyle {
+    pub fn label(self) -> &'static str {
+        match self {
+            BoardStyle::Open => "OPEN",
+            BoardStyle::Maze => "MAZE",
+            BoardStyle::Rocks => "ROCKS",
+            BoardStyle::Rooms => "ROOMS",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            BoardStyle::Open => BoardStyle::Maze,
+            BoardStyle::Maze => BoardStyle::Rocks,
+            BoardStyle::Rocks => BoardStyle::Rooms,
+            BoardStyle::Rooms => BoardStyle::Open,
+        }
+    }
+
+    /// Style by its command line name, e.g. `rocks`.
+    pub fn from_arg(value: &str) -> Option<Self> {
+        Some(match value {
+            "open" => BoardStyle::Open,
+            "maze" => BoardStyle::Maze,
+            "rocks" => BoardStyle::Rocks,
+            "rooms" => BoardStyle::Rooms,
+            _ => return None,
+        })
+    }
+
+    /// Layout for this style on the given board, `None` for an open board.
+    /// The same seed always gives the same layout.
+    pub fn generate(self, board: Board, seed: u64) -> Option<LevelLayout> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut blocked = match self {
+            BoardStyle::Open => return None,
+            BoardStyle::Maze => maze(board, &mut rng),
+            BoardStyle::Rocks => rocks(board, &mut rng),
+            BoardStyle::Rooms => rooms(board, &mut rng),
+        };
+
+        let (spawn, spawn_direction) = pick_spawn(board, &mut blocked);
+
+        // Seal off pockets the snake could never get to, food must not land there
+        let reachable = flood_fill(board, &blocked, spawn);
+        for (cell, reached) in blocked.iter_mut().zip(&reachable) {
+            *cell |= !reached;
+        }
+
+        let cells = board
+            .cells()
+            .filter(|cell| blocked[board.index(*cell).unwrap()]);
+        let (walls, obstacles) = match self {
+            BoardStyle::Rocks => (Vec::new(), cells.collect()),
+            _ => (cells.collect(), Vec::new()),
+        };
+
+        Some(LevelLayout {
+            board,
+            walls,
+            obstacles,
+            spawn,
+            spawn_direction,
+            food_zones: Vec::new(),
+            portals: Vec::new(),
+        })
+    }
+}
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Right,
+    Direction::Up,
+    Direction::Left,
+    Direction::Down,
+];
+
+fn is_free(board: Board, blocked: &[bool], cell: IVec2) -> bool {
+    board.index(cell).is_some_and(|i| !blocked[i])
+}
+
+/// Cells reachable from `start` through free cells, indexed like the board.
+pub fn flood_fill(board: Board, blocked: &[bool], start: IVec2) -> Vec<bool> {
+    let mut reached = vec![false; blocked.len()];
+    let mut queue = VecDeque::new();
+    if is_free(board, blocked, start) {
+        reached[board.index(start).unwrap()] = true;
+        queue.push_back(start);
+    }
+
+    while let Some(cell) = queue.pop_front() {
+        for direction in DIRECTIONS {
+            let next = cell + direction.delta();
+            if let Some(i) = board.index(next)
+                && !blocked[i]
+                && !reached[i]
+            {
+                reached[i] = true;
+                queue.push_back(next);
+            }
+        }
+    }
+    reached
+}
+
+/// Free cell closest to the middle that has a free neighbour, heading down the
+/// longest straight run from there. If nothing qualifies the middle is cleared
+/// together with the cell to its right.
+fn pick_spawn(board: Board, blocked: &mut [bool]) -> (IVec2, Direction) {
+    let run = |blocked: &[bool], cell: IVec2, direction: Direction| {
+        (1..)
+            .take_while(|&step| is_free(board, blocked, cell + direction.delta() * step))
+            .count()
+    };
+
+    let mut candidates: Vec<IVec2> = board
+        .cells()
+        .filter(|cell| is_free(board, blocked, *cell))
+        .collect();
+    candidates.sort_by_key(|cell| (cell.abs().element_sum(), cell.y, cell.x));
+
+    for cell in candidates {
+        if let Some(direction) = DIRECTIONS
+            .into_iter()
+            .filter(|direction| run(blocked, cell, *direction) > 0)
+            .max_by_key(|direction| run(blocked, cell, *direction))
+        {
+            return (cell, direction);
+        }
+    }
+
+    for cell in [IVec2::ZERO, IVec2::X] {
+        if let Some(i) = board.index(cell) {
+            blocked[i] = false;
+        }
+    }
+    (IVec2::ZERO, Direction::Right)
+}
+
+/// Rooms on every even local coordinate, walls in between. The board edge is
+/// already deadly, so the outermost ring stays part of the maze.
+fn maze(board: Board, rng: &mut StdRng) -> Vec<bool> {
+    let mut blocked = vec![true; (board.width() * board.height()) as usize];
+    let is_room = |cell: IVec2| {
+        let local = cell - board.min();
+        local.x % 2 == 0 && local.y % 2 == 0
+    };
+    let open = |blocked: &mut Vec<bool>, cell: IVec2| {
+        if let Some(i) = board.index(cell) {
+            blocked[i] = false;
+        }
+    };
+
+    let start = board.min();
+    let mut visited = vec![false; blocked.len()];
+    visited[board.index(start).unwrap()] = true;
+    open(&mut blocked, start);
+    let mut stack = vec![start];
+
+    while let Some(&cell) = stack.last() {
+        let mut neighbours: Vec<IVec2> = DIRECTIONS
+            .iter()
+            .map(|direction| cell + direction.delta() * 2)
+            .filter(|next| board.index(*next).is_some_and(|i| !visited[i]))
+            .collect();
+        neighbours.shuffle(rng);
+
+        match neighbours.first() {
+            Some(&next) => {
+                visited[board.index(next).unwrap()] = true;
+                open(&mut blocked, (cell + next) / 2);
+                open(&mut blocked, next);
+                stack.push(next);
+            }
+            None => {
+                stack.pop();
+            }
+        }
+    }
+
+    // Knock a wall out of most dead ends so the maze has loops
+    for cell in board.cells().filter(|cell| is_room(*cell)) {
+        let walls: Vec<IVec2> = DIRECTIONS
+            .iter()
+            .map(|direction| cell + direction.delta())
+            .filter(|wall| board.index(*wall).is_some_and(|i| blocked[i]))
+            .collect();
+        if walls.len() == 3 && rng.random_bool(MAZE_BRAIDING) {
+            let wall = walls[rng.random_range(0..walls.len())];
+            open(&mut blocked, wall);
+        }
+    }
+
+    blocked
+}
+
+fn rocks(board: Board, rng: &mut StdRng) -> Vec<bool> {
+    board
+        .cells()
+        .map(|cell| cell.abs().max_element() > SPAWN_CLEARANCE && rng.random_bool(ROCK_DENSITY))
+        .collect()
+}
+
+fn rooms(board: Board, rng: &mut StdRng) -> Vec<bool> {
+    let mut blocked = vec![true; (board.width() * board.height()) as usize];
+    let mut carve = |min: IVec2, max: IVec2| {
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                if let Some(i) = board.index(IVec2::new(x, y)) {
+                    blocked[i] = false;
+                }
+            }
+        }
+    };
+
+    // One room in the middle so the spawn is never squeezed into a corridor
+    let room_count = (board.width() * board.height() / 60).clamp(3, 9);
+    let mut centres = vec![IVec2::ZERO];
+    carve(IVec2::new(-2, -2), IVec2::new(2, 2));
+
+    for _ in 1..room_count {
+        let half_size = IVec2::new(rng.random_range(1..=3), rng.random_range(1..=2));
+        let range = (board.half_extent - half_size).max(IVec2::ZERO);
+        let centre = IVec2::new(
+            rng.random_range(-range.x..=range.x),
+            rng.random_range(-range.y..=range.y),
+        );
+        carve(centre - half_size, centre + half_size);
+
+        // L-shaped corridor from the previous room, horizontal leg first
+        let previous = *centres.last().unwrap();
+        let corner = IVec2::new(centre.x, previous.y);
+        carve(previous.min(corner), previous.max(corner));
+        carve(corner.min(centre), corner.max(centre));
+        centres.push(centre);
+    }
+
+    blocked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STYLES: [BoardStyle; 3] = [BoardStyle::Maze, BoardStyle::Rocks, BoardStyle::Rooms];
+
+    const BOARDS: [IVec2; 4] = [
+        IVec2::new(2, 2),
+        IVec2::new(7, 5),
+        IVec2::new(15, 11),
+        IVec2::new(24, 3),
+    ];
+
+    #[test]
+    fn every_free_cell_is_reachable_with_a_safe_first_move() {
+        for style in STYLES {
+            for half_extent in BOARDS {
+                let board = Board { half_extent };
+                for seed in 0..200 {
+                    let layout = style.generate(board, seed).unwrap();
+                    let mut blocked = vec![false; (board.width() * board.height()) as usize];
+                    for cell in layout.walls.iter().chain(&layout.obstacles) {
+                        blocked[board.index(*cell).unwrap()] = true;
+                    }
+                    let context = format!("{style:?} on {half_extent} with seed {seed}");
+
+                    let first = layout.spawn + layout.spawn_direction.delta();
+                    assert!(is_free(board, &blocked, layout.spawn), "{context}");
+                    assert!(is_free(board, &blocked, first), "{context}");
+
+                    let reached = flood_fill(board, &blocked, layout.spawn);
+                    for (i, (blocked, reached)) in blocked.iter().zip(&reached).enumerate() {
+                        assert!(*blocked || *reached, "{context}: cell {i} is cut off");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_same_layout() {
+        let board = Board {
+            half_extent: IVec2::new(15, 11),
+        };
+        for style in STYLES {
+            let first = style.generate(board, 7).unwrap();
+            let again = style.generate(board, 7).unwrap();
+            assert_eq!(first.walls, again.walls);
+            assert_eq!(first.obstacles, again.obstacles);
+            assert_eq!(first.spawn, again.spawn);
+        }
+        assert!(BoardStyle::Open.generate(board, 7).is_none());
+    }
+}
@@ -0,0 +1,5 @@
+pub mod board;
+pub mod generator;
+pub mod obstacle;
+pub mod occupancy;
+pub mod portal;
@@ -0,0 +1,96 @@
+use crate::{
+    board::occupancy::{CellContent, Occupancy},
+    physics::physics::{GameLayer, grid_collider},
+    player::player::{GRID_SIZE, InGameEntity, cell_to_world},
+};
+use bevy::prelude::*;
+
+pub const OBSTACLE_COLOR: Color = Color::srgb(0.35, 0.3, 0.25);
+
+pub const WALL_COLOR: Color = Color::srgb(0.2, 0.2, 0.25);
+
+/// A rock or wall piece, blocks the snake in every mode.
+#[derive(Component)]
+pub struct Obstacle;
+
+/// Part of a level's walls. Blocks exactly like an [`Obstacle`], it only looks
+/// different and sits on its own physics layer.
+#[derive(Component)]
+pub struct Wall;
+
+/// Mesh and materials shared by every obstacle and wall piece.
+#[derive(Resource)]
+pub struct ObstacleRenderAssets {
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<ColorMaterial>,
+    pub wall_material: Handle<ColorMaterial>,
+}
+
+impl FromWorld for ObstacleRenderAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mesh = world
+            .resource_mut::<Assets<Mesh>>()
+            .add(Rectangle::new(GRID_SIZE, GRID_SIZE));
+        let mut materials = world.resource_mut::<Assets<ColorMaterial>>();
+        let material = materials.add(ColorMaterial::from(OBSTACLE_COLOR));
+        let wall_material = materials.add(ColorMaterial::from(WALL_COLOR));
+        Self {
+            mesh,
+            material,
+            wall_material,
+        }
+    }
+}
+
+/// Spawns an obstacle on a free board cell. Cells that are off the board or
+/// already taken are skipped.
+pub fn spawn_obstacle(
+    commands: &mut Commands,
+    render: &ObstacleRenderAssets,
+    occupancy: &mut Occupancy,
+    cell: IVec2,
+) -> Option<Entity> {
+    if !occupancy.is_free(cell) {
+        return None;
+    }
+
+    let obstacle = commands
+        .spawn((
+            Obstacle,
+            InGameEntity,
+            Mesh2d(render.mesh.clone()),
+            MeshMaterial2d(render.material.clone()),
+            Transform::from_translation(cell_to_world(cell, 1.0)),
+            grid_collider(GameLayer::Obstacle),
+        ))
+        .id();
+    occupancy.set(cell, obstacle, CellContent::Obstacle);
+
+    Some(obstacle)
+}
+
+/// Same as [`spawn_obstacle`] for a wall piece.
+pub fn spawn_wall(
+    commands: &mut Commands,
+    render: &ObstacleRenderAssets,
+    occupancy: &mut Occupancy,
+    cell: IVec2,
+) -> Option<Entity> {
+    if !occupancy.is_free(cell) {
+        return None;
+    }
+
+    let wall = commands
+        .spawn((
+            Wall,
+            InGameEntity,
+            Mesh2d(render.mesh.clone()),
+            MeshMaterial2d(render.wall_material.clone()),
+            Transform::from_translation(cell_to_world(cell, 1.0)),
+            grid_collider(GameLayer::Wall),
+        ))
+        .id();
+    occupancy.set(cell, wall, CellContent::Wall);
+
+    Some(wall)
+}
@@ -0,0 +1,132 @@
+use crate::board::board::Board;
+use bevy::prelude::*;
+
+/// What sits on a grid cell.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CellContent {
+    Head,
+    Body,
+    Food,
+    Obstacle,
+    Wall,
+    Portal,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Occupant {
+    pub entity: Entity,
+    pub content: CellContent,
+}
+
+/// Dense grid of everything on the board. It is kept up to date by whoever
+/// spawns, moves or despawns a grid entity, so lookups never scan the world.
+#[derive(Resource, Clone)]
+pub struct Occupancy {
+    board: Board,
+    cells: Vec<Option<Occupant>>,
+}
+
+impl Occupancy {
+    pub fn new(board: Board) -> Self {
+        Self {
+            board,
+            cells: vec![None; (board.width() * board.height()) as usize],
+        }
+    }
+
+    pub fn board(&self) -> Board {
+        self.board
+    }
+
+    /// Occupant of a cell, `None` when it is empty or off the board.
+    pub fn get(&self, cell: IVec2) -> Option<Occupant> {
+        self.board.index(cell).and_then(|i| self.cells[i])
+    }
+
+    /// True when the cell is on the board and nothing sits on it.
+    pub fn is_free(&self, cell: IVec2) -> bool {
+        self.board
+            .index(cell)
+            .is_some_and(|i| self.cells[i].is_none())
+    }
+
+    pub fn set(&mut self, cell: IVec2, entity: Entity, content: CellContent) {
+        if let Some(i) = self.board.index(cell) {
+            self.cells[i] = Some(Occupant { entity, content });
+        }
+    }
+
+    pub fn clear(&mut self, cell: IVec2) {
+        if let Some(i) = self.board.index(cell) {
+            self.cells[i] = None;
+        }
+    }
+
+    /// Clears the cell only if `entity` is what sits there, so pieces that were
+    /// allowed to overlap do not erase each other.
+    pub fn remove(&mut self, cell: IVec2, entity: Entity) {
+        if self
+            .get(cell)
+            .is_some_and(|occupant| occupant.entity == entity)
+        {
+            self.clear(cell);
+        }
+    }
+
+    pub fn free_cells(&self) -> impl Iterator<Item = IVec2> + '_ {
+        self.board.cells().filter(|&cell| self.is_free(cell))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn occupancy() -> Occupancy {
+        Occupancy::new(Board {
+            half_extent: IVec2::new(1, 1),
+        })
+    }
+
+    #[test]
+    fn removing_leaves_other_occupants_alone() {
+        let mut occupancy = occupancy();
+        let mut world = World::new();
+        let (head, tail) = (world.spawn_empty().id(), world.spawn_empty().id());
+        let cell = IVec2::new(1, 0);
+        occupancy.set(cell, head, CellContent::Head);
+
+        occupancy.remove(cell, tail);
+        assert_eq!(
+            occupancy.get(cell),
+            Some(Occupant {
+                entity: head,
+                content: CellContent::Head
+            })
+        );
+        occupancy.remove(cell, head);
+        assert!(occupancy.is_free(cell));
+        // Off the board nothing happens
+        occupancy.remove(IVec2::new(5, 5), head);
+    }
+
+    #[test]
+    fn free_cells_skip_occupied_ones() {
+        let mut occupancy = occupancy();
+        assert_eq!(occupancy.free_cells().count(), 9);
+        let taken = [IVec2::new(-1, -1), IVec2::ZERO, IVec2::new(1, 1)];
+        for cell in taken {
+            occupancy.set(cell, Entity::PLACEHOLDER, CellContent::Wall);
+        }
+        // Off the board is never free, and never taken either
+        occupancy.set(IVec2::new(2, 0), Entity::PLACEHOLDER, CellContent::Wall);
+        assert!(!occupancy.is_free(IVec2::new(2, 0)));
+
+        let free: Vec<IVec2> = occupancy.free_cells().collect();
+        assert_eq!(free.len(), 6);
+        assert!(taken.iter().all(|cell| !free.contains(cell)));
+
+        occupancy.clear(IVec2::ZERO);
+        assert!(occupancy.free_cells().any(|cell| cell == IVec2::ZERO));
+    }
+}
@@ -0,0 +1,102 @@
+use crate::{
+    IsPaused, MyAssets,
+    board::occupancy::{CellContent, Occupancy},
+    player::player::{Direction, InGameEntity, cell_to_world},
+};
+use bevy::{platform::collections::HashMap, prelude::*};
+
+/// Frames in `portal.png`, one full turn of the swirl.
+const PORTAL_FRAMES: usize = 8;
+
+/// Seconds each frame of the swirl is shown.
+const FRAME_SECONDS: f32 = 0.1;
+
+/// One end of a portal pair. Portal cells are never stood on, a head that
+/// steps in comes out of the partner straight away.
+#[derive(Component)]
+pub struct Portal;
+
+/// Both ends of every portal pair on the current board.
+#[derive(Resource, Default)]
+pub struct Portals(HashMap<IVec2, IVec2>);
+
+impl Portals {
+    pub fn link(&mut self, a: IVec2, b: IVec2) {
+        self.0.insert(a, b);
+        self.0.insert(b, a);
+    }
+
+    pub fn partner(&self, cell: IVec2) -> Option<IVec2> {
+        self.0.get(&cell).copied()
+    }
+
+    /// Every pair once, the end with the smaller coordinates first.
+    pub fn pairs(&self) -> impl Iterator<Item = (IVec2, IVec2)> + '_ {
+        self.0
+            .iter()
+            .filter(|(a, b)| a.to_array() < b.to_array())
+            .map(|(a, b)| (*a, *b))
+    }
+
+    /// Where a head moving in `direction` ends up after stepping onto `cell`:
+    /// one step past the partner, or `None` if `cell` is not a portal.
+    pub fn exit(&self, cell: IVec2, direction: Direction) -> Option<IVec2> {
+        self.partner(cell)
+            .map(|partner| partner + direction.delta())
+    }
+}
+
+pub fn portal_color(id: u8) -> Color {
+    Color::hsl(260.0 + id as f32 * 37.0, 0.7, 0.6)
+}
+
+pub struct PortalPlugin;
+
+impl Plugin for PortalPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Portals>()
+            .add_systems(Update, animate_portals.run_if(in_state(IsPaused::Running)));
+    }
+}
+
+/// Spawns both ends of a portal pair and marks their cells, so food and
+/// other pieces stay off them. Both ends share the pair's tint.
+pub fn spawn_portal_pair(
+    commands: &mut Commands,
+    assets: &MyAssets,
+    occupancy: &mut Occupancy,
+    portals: &mut Portals,
+    id: u8,
+    (a, b): (IVec2, IVec2),
+) {
+    for cell in [a, b] {
+        let portal = commands
+            .spawn((
+                Portal,
+                InGameEntity,
+                Sprite {
+                    color: portal_color(id),
+                    ..Sprite::from_atlas_image(
+                        assets.portal.clone(),
+                        TextureAtlas::from(assets.portal_layout.clone()),
+                    )
+                },
+                Transform::from_translation(cell_to_world(cell, 1.0)),
+            ))
+            .id();
+        occupancy.set(cell, portal, CellContent::Portal);
+    }
+    portals.link(a, b);
+}
+
+/// Every portal shows the same frame, so the whole board swirls in step.
+fn animate_portals(time: Res<Time>, mut query: Query<&mut Sprite, With<Portal>>) {
+    let frame = (time.elapsed_secs() / FRAME_SECONDS) as usize % PORTAL_FRAMES;
+    for mut sprite in &mut query {
+        if let Some(atlas) = &mut sprite.texture_atlas
+            && atlas.index != frame
+        {
+            atlas.index = frame;
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use crate::AppState;
+use bevy::prelude::*;
+
+/// Messages of assets that failed to load, shown on the error screen.
+#[derive(Resource, Default)]
+pub struct LoadErrors(pub Vec<String>);
+
+#[derive(Component, Default)]
+pub struct ErrorScreen;
+
+pub struct ErrorScreenPlugin;
+
+impl Plugin for ErrorScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LoadErrors>()
+            .add_systems(OnEnter(AppState::BootingApp), clear_errors)
+            .add_systems(OnEnter(AppState::ErrorScreen), spawn_error_screen)
+            .add_systems(OnExit(AppState::ErrorScreen), delete_error_screen);
+    }
+}
+
+fn clear_errors(mut errors: ResMut<LoadErrors>) {
+    errors.0.clear();
+}
+
+fn spawn_error_screen(mut commands: Commands, errors: Res<LoadErrors>) {
+    commands
+        .spawn((
+            ErrorScreen,
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(10.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgb(0.15, 0.15, 0.15)),
+        ))
+        .with_children(|p| {
+            p.spawn((
+                Text::new("Could not load the game"),
+                TextFont {
+                    font_size: 32.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(1.0, 1.0, 1.0)),
+            ));
+
+            for error in &errors.0 {
+                p.spawn((
+                    Text::new(error.clone()),
+                    TextFont {
+                        font_size: 18.0,
+                        ..default()
+                    },
+                    TextColor(Color::srgb(1.0, 0.6, 0.6)),
+                ));
+            }
+
+            p.spawn((
+                Text::new("Fix the files above and press F2 to retry"),
+                TextFont {
+                    font_size: 22.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(199.0 / 255.0, 236.0 / 255.0, 250.0 / 255.0)),
+            ));
+        });
+}
+
+fn delete_error_screen(mut commands: Commands, query: Query<Entity, With<ErrorScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
@@ -1 +1,2 @@
-pub mod boot_screen;
\ No newline at end of file
+pub mod boot_screen;
+pub mod error_screen;
\ No newline at end of file
@@ -0,0 +1,170 @@
+use crate::{
+    AppState, IsPaused,
+    campaign::progress::CampaignProgress,
+    mode::mode::{FoodEaten, GameMode},
+    player::player::{InGameEntity, SnakeSegments},
+};
+use bevy::prelude::*;
+
+/// What has to happen for a level to count as cleared.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LevelGoal {
+    EatApples(u32),
+    /// Head plus body pieces.
+    ReachLength(u32),
+    SurviveSeconds(f32),
+}
+
+impl LevelGoal {
+    pub fn describe(self) -> String {
+        match self {
+            LevelGoal::EatApples(apples) => format!("Eat {apples} apples"),
+            LevelGoal::ReachLength(length) => format!("Reach length {length}"),
+            LevelGoal::SurviveSeconds(seconds) => format!("Survive {seconds:.0} seconds"),
+        }
+    }
+}
+
+/// A hand-authored campaign level.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Level {
+    pub name: &'static str,
+    /// Board layout file, relative to `assets/`.
+    pub layout: &'static str,
+    /// Seconds per grid step.
+    pub step_seconds: f32,
+    pub goal: LevelGoal,
+}
+
+/// The campaign in play order.
+pub const LEVELS: &[Level] = &[
+    Level {
+        name: "First Bite",
+        layout: "levels/01_first_bite.level",
+        step_seconds: 0.4,
+        goal: LevelGoal::EatApples(5),
+    },
+    Level {
+        name: "Pillars",
+        layout: "levels/02_pillars.level",
+        step_seconds: 0.35,
+        goal: LevelGoal::EatApples(8),
+    },
+    Level {
+        name: "Corridor",
+        layout: "levels/03_corridor.level",
+        step_seconds: 0.3,
+        goal: LevelGoal::ReachLength(12),
+    },
+    Level {
+        name: "Crossroads",
+        layout: "levels/04_crossroads.level",
+        step_seconds: 0.3,
+        goal: LevelGoal::SurviveSeconds(60.0),
+    },
+    Level {
+        name: "Gauntlet",
+        layout: "levels/05_gauntlet.level",
+        step_seconds: 0.25,
+        goal: LevelGoal::EatApples(15),
+    },
+];
+
+/// Progress towards the goal of the level being played.
+#[derive(Resource, Default)]
+pub struct LevelRun {
+    pub apples: u32,
+    pub elapsed: f32,
+}
+
+#[derive(Component)]
+pub struct GoalText;
+
+pub struct CampaignPlugin;
+
+impl Plugin for CampaignPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(CampaignProgress::load())
+            .init_resource::<LevelRun>()
+            .add_systems(OnEnter(AppState::InGame), start_level.run_if(in_campaign))
+            .add_systems(OnEnter(IsPaused::LevelComplete), unlock_next_level)
+            .add_systems(
+                Update,
+                check_goal.run_if(in_state(IsPaused::Running).and(in_campaign)),
+            );
+    }
+}
+
+pub fn in_campaign(mode: Res<GameMode>) -> bool {
+    matches!(*mode, GameMode::Campaign(_))
+}
+
+fn start_level(mut commands: Commands, mut run: ResMut<LevelRun>, mode: Res<GameMode>) {
+    *run = LevelRun::default();
+
+    if let Some(level) = mode.level() {
+        commands.spawn((
+            InGameEntity,
+            GoalText,
+            Text::new(level.goal.describe()),
+            TextFont {
+                font_size: 24.0,
+                ..default()
+            },
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(10.0),
+                left: Val::Px(10.0),
+                ..default()
+            },
+        ));
+    }
+}
+
+fn check_goal(
+    mut run: ResMut<LevelRun>,
+    mut food_eaten: EventReader<FoodEaten>,
+    mut goal_text: Query<&mut Text, With<GoalText>>,
+    mut next_state: ResMut<NextState<IsPaused>>,
+    snakes: Query<&SnakeSegments>,
+    mode: Res<GameMode>,
+    time: Res<Time>,
+) {
+    let Some(level) = mode.level() else {
+        return;
+    };
+
+    run.apples += food_eaten.read().count() as u32;
+    run.elapsed += time.delta_secs();
+
+    let length = snakes
+        .iter()
+        .map(|segments| segments.0.len() as u32 + 1)
+        .max()
+        .unwrap_or(1);
+    let (done, status) = match level.goal {
+        LevelGoal::EatApples(apples) => (run.apples >= apples, format!("{}/{apples}", run.apples)),
+        LevelGoal::ReachLength(target) => (length >= target, format!("{length}/{target}")),
+        LevelGoal::SurviveSeconds(seconds) => (
+            run.elapsed >= seconds,
+            format!("{:.0}/{seconds:.0}s", run.elapsed),
+        ),
+    };
+
+    for mut text in &mut goal_text {
+        text.0 = format!("{} ({status})", level.goal.describe());
+    }
+
+    if done {
+        next_state.set(IsPaused::LevelComplete);
+    }
+}
+
+pub fn unlock_next_level(mut progress: ResMut<CampaignProgress>, mode: Res<GameMode>) {
+    if let GameMode::Campaign(level) = *mode
+        && progress.unlock_after(level)
+        && let Err(error) = progress.save()
+    {
+        warn!("Could not save campaign progress: {error}");
+    }
+}
@@ -0,0 +1,56 @@
+use crate::{campaign::campaign::LEVELS, read_save, write_save};
+use bevy::prelude::*;
+use std::io;
+
+/// File unlocked campaign progress is kept in between sessions.
+const PROGRESS_FILE: &str = "campaign_progress.txt";
+
+/// How far the player got in the campaign, saved to disk after every cleared level.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CampaignProgress {
+    /// Number of levels that can be played, the first one is always open.
+    pub unlocked: usize,
+}
+
+impl Default for CampaignProgress {
+    fn default() -> Self {
+        Self { unlocked: 1 }
+    }
+}
+
+impl CampaignProgress {
+    /// Reads saved progress, starting over when there is none or it cannot be read.
+    pub fn load() -> Self {
+        let Some(contents) = read_save(PROGRESS_FILE) else {
+            return Self::default();
+        };
+
+        contents
+            .lines()
+            .find_map(|line| line.strip_prefix("unlocked = "))
+            .and_then(|value| value.trim().parse().ok())
+            .map(|unlocked: usize| Self {
+                unlocked: unlocked.clamp(1, LEVELS.len()),
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        write_save(PROGRESS_FILE, format!("unlocked = {}\n", self.unlocked))
+    }
+
+    pub fn is_unlocked(&self, level: usize) -> bool {
+        level < self.unlocked
+    }
+
+    /// Opens the level after `cleared`, returns true when that changed anything.
+    pub fn unlock_after(&mut self, cleared: usize) -> bool {
+        let unlocked = (cleared + 2).min(LEVELS.len());
+        if unlocked > self.unlocked {
+            self.unlocked = unlocked;
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -0,0 +1,494 @@
+use crate::{
+    AppState, MyAssets,
+    board::{
+        obstacle::{OBSTACLE_COLOR, WALL_COLOR},
+        portal::portal_color,
+    },
+    editor::{grid::EditorGrid, history::EditHistory},
+    gameui::button::{ButtonSize, create_button},
+    level::{
+        layout::{LevelLayout, Tile},
+        loader::PlaytestLayout,
+    },
+    mode::mode::GameMode,
+    player::player::{Direction, GRID_SIZE, MovementMode, SNAKE_COLOR, cell_to_world},
+};
+use bevy::prelude::*;
+use std::{fs, path::Path};
+
+/// Board size of a new layout, in half extents.
+const NEW_HALF_EXTENT: IVec2 = IVec2::new(10, 7);
+
+const FOOD_ZONE_COLOR: Color = Color::srgba(0.9, 0.3, 0.3, 0.5);
+
+/// What the left mouse button paints.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Brush {
+    #[default]
+    Wall,
+    Obstacle,
+    FoodZone,
+    Spawn,
+    Portal,
+    Erase,
+}
+
+impl Brush {
+    pub const ALL: [Brush; 6] = [
+        Brush::Wall,
+        Brush::Obstacle,
+        Brush::FoodZone,
+        Brush::Spawn,
+        Brush::Portal,
+        Brush::Erase,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Brush::Wall => "Wall",
+            Brush::Obstacle => "Rock",
+            Brush::FoodZone => "Food",
+            Brush::Spawn => "Spawn",
+            Brush::Portal => "Portal",
+            Brush::Erase => "Erase",
+        }
+    }
+}
+
+/// The layout open in the editor and where it gets saved. It outlives the
+/// editor screen, so a playtest comes back to the same work.
+#[derive(Resource)]
+pub struct EditorDocument {
+    /// Path relative to `assets/`, same keys as `MyAssets::levels`.
+    pub path: String,
+    pub grid: EditorGrid,
+}
+
+impl Default for EditorDocument {
+    fn default() -> Self {
+        Self {
+            path: "levels/custom.level".to_string(),
+            grid: EditorGrid::new(NEW_HALF_EXTENT),
+        }
+    }
+}
+
+/// Everything the toolbar buttons and shortcuts can ask for.
+#[derive(Event, Clone, Copy, PartialEq, Debug)]
+pub enum EditorAction {
+    SetBrush(Brush),
+    Undo,
+    Redo,
+    Resize(IVec2),
+    RotateSpawn,
+    New,
+    OpenNext,
+    Save,
+    Playtest,
+    Exit,
+}
+
+/// Last thing the editor wants to tell the designer, e.g. why a save failed.
+#[derive(Resource, Default)]
+pub struct EditorMessage(pub String);
+
+#[derive(Component)]
+pub struct EditorScreen;
+
+/// Sprites showing the grid, rebuilt whenever the document changes.
+#[derive(Component)]
+pub struct EditorTile;
+
+#[derive(Component)]
+pub struct EditorStatusText;
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EditorDocument>()
+            .init_resource::<EditHistory>()
+            .init_resource::<Brush>()
+            .init_resource::<EditorMessage>()
+            .add_event::<EditorAction>()
+            .add_systems(OnEnter(AppState::Editor), (spawn_editor, draw_grid))
+            .add_systems(OnExit(AppState::Editor), delete_editor)
+            .add_systems(
+                Update,
+                (
+                    editor_shortcuts,
+                    apply_editor_actions,
+                    paint,
+                    draw_grid.run_if(resource_changed::<EditorDocument>),
+                    update_status_text,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::Editor)),
+            );
+    }
+}
+
+fn spawn_editor(mut commands: Commands) {
+    commands.spawn((
+        EditorScreen,
+        EditorStatusText,
+        Text::new(""),
+        TextFont {
+            font_size: 18.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+
+    commands
+        .spawn((
+            EditorScreen,
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(10.0),
+                width: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                column_gap: Val::Px(6.0),
+                ..default()
+            },
+        ))
+        .with_children(|p| {
+            for brush in Brush::ALL {
+                spawn_action_button(p, brush.label(), EditorAction::SetBrush(brush));
+            }
+            spawn_action_button(p, "Undo", EditorAction::Undo);
+            spawn_action_button(p, "Redo", EditorAction::Redo);
+            spawn_action_button(p, "New", EditorAction::New);
+            spawn_action_button(p, "Open", EditorAction::OpenNext);
+            spawn_action_button(p, "Save", EditorAction::Save);
+            spawn_action_button(p, "Play", EditorAction::Playtest);
+            spawn_action_button(p, "Menu", EditorAction::Exit);
+        });
+}
+
+fn spawn_action_button(p: &mut ChildSpawnerCommands, text: &str, action: EditorAction) {
+    p.spawn(create_button(text, ButtonSize::TOOL)).observe(
+        move |mut trigger: Trigger<Pointer<Released>>, mut actions: EventWriter<EditorAction>| {
+            trigger.propagate(false);
+            actions.write(action);
+        },
+    );
+}
+
+fn delete_editor(mut commands: Commands, query: Query<Entity, With<EditorScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn editor_shortcuts(keys: Res<ButtonInput<KeyCode>>, mut actions: EventWriter<EditorAction>) {
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    let brush_keys = [
+        KeyCode::Digit1,
+        KeyCode::Digit2,
+        KeyCode::Digit3,
+        KeyCode::Digit4,
+        KeyCode::Digit5,
+        KeyCode::Digit6,
+    ];
+    for (key, brush) in brush_keys.into_iter().zip(Brush::ALL) {
+        if keys.just_pressed(key) {
+            actions.write(EditorAction::SetBrush(brush));
+        }
+    }
+
+    if ctrl && keys.just_pressed(KeyCode::KeyZ) {
+        actions.write(if shift {
+            EditorAction::Redo
+        } else {
+            EditorAction::Undo
+        });
+    }
+    if ctrl && keys.just_pressed(KeyCode::KeyY) {
+        actions.write(EditorAction::Redo);
+    }
+    if ctrl && keys.just_pressed(KeyCode::KeyS) {
+        actions.write(EditorAction::Save);
+    }
+    if keys.just_pressed(KeyCode::KeyR) {
+        actions.write(EditorAction::RotateSpawn);
+    }
+    if keys.just_pressed(KeyCode::F5) {
+        actions.write(EditorAction::Playtest);
+    }
+    if keys.just_pressed(KeyCode::Escape) {
+        actions.write(EditorAction::Exit);
+    }
+
+    // Arrows grow and shrink the board by a column or row on each side
+    let resizes = [
+        (KeyCode::ArrowRight, IVec2::X),
+        (KeyCode::ArrowLeft, IVec2::NEG_X),
+        (KeyCode::ArrowUp, IVec2::Y),
+        (KeyCode::ArrowDown, IVec2::NEG_Y),
+    ];
+    for (key, delta) in resizes {
+        if keys.just_pressed(key) {
+            actions.write(EditorAction::Resize(delta));
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn apply_editor_actions(
+    mut actions: EventReader<EditorAction>,
+    mut document: ResMut<EditorDocument>,
+    mut history: ResMut<EditHistory>,
+    mut brush: ResMut<Brush>,
+    mut message: ResMut<EditorMessage>,
+    mut layouts: ResMut<Assets<LevelLayout>>,
+    mut playtest: ResMut<PlaytestLayout>,
+    mut mode: ResMut<GameMode>,
+    mut movement: ResMut<MovementMode>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut assets: ResMut<MyAssets>,
+    asset_server: Res<AssetServer>,
+) {
+    for action in actions.read() {
+        match *action {
+            EditorAction::SetBrush(selected) => *brush = selected,
+            EditorAction::Undo => {
+                if !history.undo(&mut document.grid) {
+                    message.0 = "Nothing to undo".to_string();
+                }
+            }
+            EditorAction::Redo => {
+                if !history.redo(&mut document.grid) {
+                    message.0 = "Nothing to redo".to_string();
+                }
+            }
+            EditorAction::Resize(delta) => {
+                let mut grid = document.grid.clone();
+                if grid.resize(delta) {
+                    history.record(&document.grid);
+                    document.grid = grid;
+                }
+            }
+            EditorAction::RotateSpawn => {
+                let mut grid = document.grid.clone();
+                if grid.rotate_spawn() {
+                    history.record(&document.grid);
+                    document.grid = grid;
+                }
+            }
+            EditorAction::New => {
+                *document = EditorDocument {
+                    path: free_level_path(&assets),
+                    grid: EditorGrid::new(NEW_HALF_EXTENT),
+                };
+                history.clear();
+                message.0 = format!("New layout {}", document.path);
+            }
+            EditorAction::OpenNext => {
+                // Cycle through every layout loaded at boot, in file name order
+                let mut paths: Vec<&String> = assets.levels.keys().collect();
+                paths.sort();
+                let next = paths
+                    .iter()
+                    .position(|path| **path == document.path)
+                    .map_or(0, |i| (i + 1) % paths.len().max(1));
+                let Some(layout) = paths
+                    .get(next)
+                    .and_then(|path| layouts.get(&assets.levels[*path]))
+                else {
+                    message.0 = "No layouts to open".to_string();
+                    continue;
+                };
+                *document = EditorDocument {
+                    path: paths[next].clone(),
+                    grid: EditorGrid::from_layout(layout),
+                };
+                history.clear();
+                message.0 = format!("Opened {}", document.path);
+            }
+            EditorAction::Save => {
+                // Saving something the loader rejects would break the next boot
+                let text = document.grid.to_text();
+                message.0 = match LevelLayout::parse(&text) {
+                    Err(error) => format!("Not saved, {error}"),
+                    Ok(_) => match fs::write(Path::new("assets").join(&document.path), text) {
+                        Ok(()) => {
+                            // Load what was written, so a new layout is listed
+                            // right away and an old one shows the changes
+                            if assets.levels.contains_key(&document.path) {
+                                asset_server.reload(&document.path);
+                            } else {
+                                let handle = asset_server.load(&document.path);
+                                assets.levels.insert(document.path.clone(), handle);
+                            }
+                            format!("Saved {}", document.path)
+                        }
+                        Err(error) => format!("Could not save {}: {error}", document.path),
+                    },
+                };
+            }
+            EditorAction::Playtest => match LevelLayout::parse(&document.grid.to_text()) {
+                Err(error) => message.0 = format!("Can't play, {error}"),
+                Ok(layout) => {
+                    playtest.0 = layouts.add(layout);
+                    *mode = GameMode::Playtest;
+                    *movement = MovementMode::Grid;
+                    next_state.set(AppState::InGameLoading);
+                }
+            },
+            EditorAction::Exit => next_state.set(AppState::MainMenu),
+        }
+    }
+}
+
+/// First `levels/custom_N.level` that is neither loaded nor on disk.
+fn free_level_path(assets: &MyAssets) -> String {
+    (1..)
+        .map(|n| format!("levels/custom_{n}.level"))
+        .find(|path| !assets.levels.contains_key(path) && !Path::new("assets").join(path).exists())
+        .unwrap_or_default()
+}
+
+/// Left button paints the current brush, right button erases. Each press to
+/// release is one undo step.
+fn paint(
+    mouse: Res<ButtonInput<MouseButton>>,
+    window: Single<&Window>,
+    camera: Single<(&Camera, &GlobalTransform)>,
+    brush: Res<Brush>,
+    mut document: ResMut<EditorDocument>,
+    mut history: ResMut<EditHistory>,
+    mut recorded: Local<bool>,
+) {
+    if mouse.any_just_pressed([MouseButton::Left, MouseButton::Right]) {
+        *recorded = false;
+    }
+
+    let erase = mouse.pressed(MouseButton::Right);
+    if !erase && !mouse.pressed(MouseButton::Left) {
+        return;
+    }
+
+    let (camera, camera_transform) = *camera;
+    let Some(world) = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor).ok())
+    else {
+        return;
+    };
+    let cell = (world / GRID_SIZE).round().as_ivec2();
+    // Clicks on the toolbar land outside the board and are ignored here
+    if !document.grid.board.contains(cell) {
+        return;
+    }
+
+    let mut grid = document.grid.clone();
+    let changed = match (*brush, erase) {
+        (_, true) | (Brush::Erase, _) => grid.set(cell, Tile::Floor),
+        (Brush::Wall, _) => grid.set(cell, Tile::Wall),
+        (Brush::Obstacle, _) => grid.set(cell, Tile::Obstacle),
+        (Brush::FoodZone, _) => grid.set(cell, Tile::FoodZone),
+        (Brush::Spawn, _) => grid.place_spawn(cell),
+        // A portal per click, dragging would drop a new pair on every cell
+        (Brush::Portal, _) => {
+            mouse.just_pressed(MouseButton::Left)
+                && grid
+                    .next_portal_id()
+                    .is_some_and(|id| grid.set(cell, Tile::Portal(id)))
+        }
+    };
+
+    if changed {
+        if !*recorded {
+            history.record(&document.grid);
+            *recorded = true;
+        }
+        document.grid = grid;
+    }
+}
+
+fn draw_grid(
+    mut commands: Commands,
+    tiles: Query<Entity, With<EditorTile>>,
+    document: Res<EditorDocument>,
+    assets: Res<MyAssets>,
+) {
+    for entity in &tiles {
+        commands.entity(entity).despawn();
+    }
+
+    let grid = &document.grid;
+    for cell in grid.board.cells() {
+        commands.spawn((
+            EditorScreen,
+            EditorTile,
+            Sprite::from_image(assets.ground.clone()),
+            Transform::from_translation(cell_to_world(cell, 0.0)),
+        ));
+    }
+
+    for (cell, tile) in grid.tiles() {
+        let (color, label) = match tile {
+            Tile::Floor => continue,
+            Tile::Wall => (WALL_COLOR, None),
+            Tile::Obstacle => (OBSTACLE_COLOR, None),
+            Tile::FoodZone => (FOOD_ZONE_COLOR, None),
+            Tile::Portal(id) => (portal_color(id), Some(id.to_string())),
+            Tile::Spawn(direction) => (
+                SNAKE_COLOR,
+                Some(
+                    match direction {
+                        Direction::Right => ">",
+                        Direction::Left => "<",
+                        Direction::Up => "^",
+                        Direction::Down => "v",
+                    }
+                    .to_string(),
+                ),
+            ),
+        };
+
+        let mut tile_entity = commands.spawn((
+            EditorScreen,
+            EditorTile,
+            Sprite::from_color(color, Vec2::splat(GRID_SIZE)),
+            Transform::from_translation(cell_to_world(cell, 1.0)),
+        ));
+        if let Some(label) = label {
+            tile_entity.with_child((
+                Text2d::new(label),
+                TextFont {
+                    font_size: 20.0,
+                    ..default()
+                },
+                Transform::from_xyz(0.0, 0.0, 1.0),
+            ));
+        }
+    }
+}
+
+fn update_status_text(
+    document: Res<EditorDocument>,
+    brush: Res<Brush>,
+    message: Res<EditorMessage>,
+    mut query: Query<&mut Text, With<EditorStatusText>>,
+) {
+    let board = document.grid.board;
+    for mut text in &mut query {
+        text.0 = format!(
+            "{}  {}x{}  brush: {}  (1-6 brush, R rotate spawn, arrows resize, Ctrl+Z/Y undo/redo, Ctrl+S save, F5 play)\n{}",
+            document.path,
+            board.width(),
+            board.height(),
+            brush.label(),
+            message.0,
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{
+    board::board::Board,
+    level::layout::{LevelLayout, Tile},
+    player::player::Direction,
+};
+use bevy::{platform::collections::HashMap, prelude::*};
+
+/// Smallest and largest board the editor lets you resize to, in half extents.
+const MIN_HALF_EXTENT: IVec2 = IVec2::new(2, 2);
+const MAX_HALF_EXTENT: IVec2 = IVec2::new(20, 15);
+
+/// Layout being edited. Unlike [`LevelLayout`] it can be invalid for a while,
+/// a second spawn point or a portal without its partner only get reported
+/// once the grid is turned into a level again.
+#[derive(Clone, PartialEq, Debug)]
+pub struct EditorGrid {
+    pub board: Board,
+    /// Everything that is not plain floor.
+    tiles: HashMap<IVec2, Tile>,
+}
+
+impl EditorGrid {
+    /// Empty board with the snake starting in the middle.
+    pub fn new(half_extent: IVec2) -> Self {
+        let mut grid = Self {
+            board: Board { half_extent },
+            tiles: HashMap::default(),
+        };
+        grid.set(IVec2::ZERO, Tile::Spawn(Direction::Right));
+        grid
+    }
+
+    pub fn from_layout(layout: &LevelLayout) -> Self {
+        let mut grid = Self {
+            board: layout.board,
+            tiles: HashMap::default(),
+        };
+        for &cell in &layout.walls {
+            grid.set(cell, Tile::Wall);
+        }
+        for &cell in &layout.obstacles {
+            grid.set(cell, Tile::Obstacle);
+        }
+        for &cell in &layout.food_zones {
+            grid.set(cell, Tile::FoodZone);
+        }
+        for (id, &(a, b)) in layout.portals.iter().enumerate() {
+            grid.set(a, Tile::Portal(id as u8 + 1));
+            grid.set(b, Tile::Portal(id as u8 + 1));
+        }
+        grid.set(layout.spawn, Tile::Spawn(layout.spawn_direction));
+        grid
+    }
+
+    pub fn get(&self, cell: IVec2) -> Tile {
+        self.tiles.get(&cell).copied().unwrap_or(Tile::Floor)
+    }
+
+    pub fn tiles(&self) -> impl Iterator<Item = (IVec2, Tile)> + '_ {
+        self.tiles.iter().map(|(&cell, &tile)| (cell, tile))
+    }
+
+    /// Paints one cell, returns false when nothing changed.
+    pub fn set(&mut self, cell: IVec2, tile: Tile) -> bool {
+        if !self.board.contains(cell) || self.get(cell) == tile {
+            return false;
+        }
+        if tile == Tile::Floor {
+            self.tiles.remove(&cell);
+        } else {
+            self.tiles.insert(cell, tile);
+        }
+        true
+    }
+
+    pub fn spawn(&self) -> Option<(IVec2, Direction)> {
+        self.tiles().find_map(|(cell, tile)| match tile {
+            Tile::Spawn(direction) => Some((cell, direction)),
+            _ => None,
+        })
+    }
+
+    /// Moves the spawn point, there is only ever one.
+    pub fn place_spawn(&mut self, cell: IVec2) -> bool {
+        let direction = self
+            .spawn()
+            .map_or(Direction::Right, |(_, direction)| direction);
+        if !self.board.contains(cell) || self.get(cell) == Tile::Spawn(direction) {
+            return false;
+        }
+        self.tiles.retain(|_, tile| !matches!(tile, Tile::Spawn(_)));
+        self.set(cell, Tile::Spawn(direction))
+    }
+
+    /// Turns the spawn point a quarter to the right.
+    pub fn rotate_spawn(&mut self) -> bool {
+        let Some((cell, direction)) = self.spawn() else {
+            return false;
+        };
+        let turned = match direction {
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+            Direction::Up => Direction::Right,
+        };
+        self.set(cell, Tile::Spawn(turned))
+    }
+
+    /// Portal id the next portal tile should get: the first pair with a
+    /// missing end, or a new pair. `None` once all nine are complete.
+    pub fn next_portal_id(&self) -> Option<u8> {
+        let mut ends = [0; 9];
+        for (_, tile) in self.tiles() {
+            if let Tile::Portal(id) = tile {
+                ends[id as usize - 1] += 1;
+            }
+        }
+        let open = ends.iter().position(|&count| count == 1);
+        let unused = ends.iter().position(|&count| count == 0);
+        open.or(unused).map(|i| i as u8 + 1)
+    }
+
+    /// Grows or shrinks the board around its middle. Tiles that end up outside
+    /// are dropped.
+    pub fn resize(&mut self, delta: IVec2) -> bool {
+        let half_extent = (self.board.half_extent + delta).clamp(MIN_HALF_EXTENT, MAX_HALF_EXTENT);
+        if half_extent == self.board.half_extent {
+            return false;
+        }
+        self.board.half_extent = half_extent;
+        let board = self.board;
+        self.tiles.retain(|cell, _| board.contains(*cell));
+        true
+    }
+
+    /// The grid in the `.level` text format, see `level/layout.rs`.
+    pub fn to_text(&self) -> String {
+        let (min, max) = (self.board.min(), self.board.max());
+        let mut text = String::new();
+        for y in (min.y..=max.y).rev() {
+            for x in min.x..=max.x {
+                text.push(self.get(IVec2::new(x, y)).to_char());
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
@@ -0,0 +1,45 @@
+use crate::editor::grid::EditorGrid;
+use bevy::prelude::*;
+
+/// Undo steps kept before the oldest ones are dropped.
+const MAX_STEPS: usize = 100;
+
+/// Undo and redo stacks of whole grids. Layouts are a few hundred cells, so a
+/// snapshot per edit is cheaper than tracking individual changes.
+#[derive(Resource, Default)]
+pub struct EditHistory {
+    undo: Vec<EditorGrid>,
+    redo: Vec<EditorGrid>,
+}
+
+impl EditHistory {
+    /// Remembers the grid as it was before an edit. Any redo steps are lost.
+    pub fn record(&mut self, before: &EditorGrid) {
+        if self.undo.len() == MAX_STEPS {
+            self.undo.remove(0);
+        }
+        self.undo.push(before.clone());
+        self.redo.clear();
+    }
+
+    pub fn undo(&mut self, grid: &mut EditorGrid) -> bool {
+        let Some(previous) = self.undo.pop() else {
+            return false;
+        };
+        self.redo.push(std::mem::replace(grid, previous));
+        true
+    }
+
+    pub fn redo(&mut self, grid: &mut EditorGrid) -> bool {
+        let Some(next) = self.redo.pop() else {
+            return false;
+        };
+        self.undo.push(std::mem::replace(grid, next));
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
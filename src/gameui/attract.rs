@@ -0,0 +1,186 @@
+//! Computer snakes playing on a small board behind the main menu, arcade
+//! style. The demo runs on its own [`Sim`], so it never touches the real game
+//! state, scores or leaderboard.
+
+use crate::{
+    AppState, MyAssets,
+    ai::ai::{AiController, Difficulty},
+    board::{board::Board, generator::BoardStyle},
+    gameui::{
+        menu::MenuBackground,
+        sim_view::{spawn_sim_board, spawn_sim_pieces},
+    },
+    mode::mode::GameMode,
+    player::{
+        controller::SnakeController,
+        player::{AI_COLOR, RIVAL_COLOR},
+    },
+    sim::sim::Sim,
+};
+use bevy::{input::mouse::MouseMotion, prelude::*};
+
+/// Seconds without input before the demo starts.
+const IDLE_SECONDS: f32 = 5.0;
+
+/// Seconds per demo step, a little quicker than a real game.
+const STEP_SECONDS: f32 = 0.15;
+
+/// Steps before a demo that is still going gets a fresh board.
+const MAX_STEPS: u64 = 600;
+
+/// Small enough to stay clear of the menu panel on the right.
+const DEMO_BOARD: Board = Board {
+    half_extent: IVec2::new(7, 5),
+};
+
+#[derive(Resource)]
+pub struct AttractMode {
+    idle: Timer,
+    step: Timer,
+    demo: Option<Sim>,
+}
+
+impl Default for AttractMode {
+    fn default() -> Self {
+        Self {
+            idle: Timer::from_seconds(IDLE_SECONDS, TimerMode::Once),
+            step: Timer::from_seconds(STEP_SECONDS, TimerMode::Repeating),
+            demo: None,
+        }
+    }
+}
+
+/// Board tiles of the demo, spawned once per board.
+#[derive(Component, Clone)]
+pub struct AttractBoard;
+
+/// Snakes and apples of the demo, redrawn every step.
+#[derive(Component, Clone)]
+pub struct AttractPiece;
+
+pub struct AttractPlugin;
+
+impl Plugin for AttractPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AttractMode>()
+            .add_systems(OnEnter(AppState::MainMenu), reset_attract)
+            .add_systems(OnExit(AppState::MainMenu), stop_attract)
+            .add_systems(Update, run_attract.run_if(in_state(AppState::MainMenu)));
+    }
+}
+
+fn reset_attract(mut attract: ResMut<AttractMode>) {
+    *attract = AttractMode::default();
+}
+
+/// Clears the demo and its pieces and brings the menu background back.
+#[allow(clippy::type_complexity)]
+fn stop_attract(
+    mut commands: Commands,
+    mut attract: ResMut<AttractMode>,
+    pieces: Query<Entity, Or<(With<AttractBoard>, With<AttractPiece>)>>,
+    mut background: Query<&mut Visibility, With<MenuBackground>>,
+) {
+    *attract = AttractMode::default();
+    for entity in &pieces {
+        commands.entity(entity).despawn();
+    }
+    for mut visibility in &mut background {
+        *visibility = Visibility::Inherited;
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn run_attract(
+    mut commands: Commands,
+    mut attract: ResMut<AttractMode>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut motion: EventReader<MouseMotion>,
+    assets: Res<MyAssets>,
+    time: Res<Time>,
+    board_pieces: Query<Entity, With<AttractBoard>>,
+    pieces: Query<Entity, With<AttractPiece>>,
+    mut background: Query<&mut Visibility, With<MenuBackground>>,
+) {
+    // Any input hands the screen back to the menu
+    let touched = keys.get_just_pressed().next().is_some()
+        || mouse.get_just_pressed().next().is_some()
+        || motion.read().count() > 0;
+    if touched {
+        if attract.demo.is_some() {
+            *attract = AttractMode::default();
+            for entity in board_pieces.iter().chain(&pieces) {
+                commands.entity(entity).despawn();
+            }
+            for mut visibility in &mut background {
+                *visibility = Visibility::Inherited;
+            }
+        }
+        attract.idle.reset();
+        return;
+    }
+
+    if attract.demo.is_none() {
+        if !attract.idle.tick(time.delta()).finished() {
+            return;
+        }
+        for mut visibility in &mut background {
+            *visibility = Visibility::Hidden;
+        }
+    }
+
+    let restart = attract
+        .demo
+        .as_ref()
+        .is_none_or(|demo| demo.is_over() || demo.ticks >= MAX_STEPS);
+    if restart {
+        for entity in &board_pieces {
+            commands.entity(entity).despawn();
+        }
+        let demo = new_demo(rand::random());
+        spawn_sim_board(&mut commands, &assets, &demo, AttractBoard);
+        attract.demo = Some(demo);
+    } else if !attract.step.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let Some(demo) = attract.demo.as_mut() else {
+        return;
+    };
+    if !restart {
+        demo.step();
+    }
+    for entity in &pieces {
+        commands.entity(entity).despawn();
+    }
+    spawn_sim_pieces(
+        &mut commands,
+        &assets,
+        demo,
+        &[AI_COLOR, RIVAL_COLOR],
+        AttractPiece,
+    );
+}
+
+/// Two computer snakes on a generated board, playing versus rules so a
+/// round ends by itself.
+fn new_demo(seed: u64) -> Sim {
+    let styles = [BoardStyle::Open, BoardStyle::Rocks, BoardStyle::Rooms];
+    let layout = styles[(seed % styles.len() as u64) as usize].generate(DEMO_BOARD, seed);
+    let controllers: Vec<Box<dyn SnakeController>> = vec![
+        Box::new(AiController {
+            difficulty: Difficulty::LookAhead,
+        }),
+        Box::new(AiController {
+            difficulty: Difficulty::Safe,
+        }),
+    ];
+    Sim::new(
+        DEMO_BOARD,
+        layout.as_ref(),
+        GameMode::Versus.rules(),
+        seed,
+        controllers,
+    )
+}
@@ -0,0 +1,66 @@
+//! The outlined button the game screens, the lobby and the editor share.
+
+use bevy::prelude::*;
+
+/// How big a [`create_button`] is drawn.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ButtonSize {
+    pub width: f32,
+    pub height: f32,
+    pub border: f32,
+    pub font_size: f32,
+}
+
+impl ButtonSize {
+    /// Pause, game over and the other in-game screens.
+    pub const MENU: Self = Self {
+        width: 150.0,
+        height: 65.0,
+        border: 5.0,
+        font_size: 18.0,
+    };
+
+    /// Room for longer labels, as in the level list and the lobby.
+    pub const WIDE: Self = Self {
+        width: 220.0,
+        height: 56.0,
+        ..Self::MENU
+    };
+
+    /// The editor toolbar.
+    pub const TOOL: Self = Self {
+        width: 64.0,
+        height: 40.0,
+        border: 3.0,
+        font_size: 14.0,
+    };
+}
+
+/// Button of the size the in-game screens use.
+pub fn create_menu_button(text: &str) -> impl Bundle {
+    create_button(text, ButtonSize::MENU)
+}
+
+pub fn create_button(text: &str, size: ButtonSize) -> impl Bundle {
+    (
+        Node {
+            width: Val::Px(size.width),
+            height: Val::Px(size.height),
+            border: UiRect::all(Val::Px(size.border)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        BorderColor(Color::WHITE),
+        BorderRadius::all(Val::Percent(10.0)),
+        BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+        Button,
+        children![(
+            Text::new(text),
+            TextFont {
+                font_size: size.font_size,
+                ..default()
+            },
+        )],
+    )
+}
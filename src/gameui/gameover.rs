@@ -1,4 +1,12 @@
-use crate::{AppState, IsPaused, player::player::Score};
+use crate::{
+    AppState, IsPaused,
+    gameui::button::create_menu_button,
+    mode::{
+        leaderboard::Leaderboard,
+        mode::{GameMode, MatchOutcome, ModeTimer},
+    },
+    player::player::{PlayerId, Score},
+};
 use bevy::prelude::*;
 
 pub struct GameOverPlugin;
@@ -9,7 +17,22 @@ impl Plugin for GameOverPlugin {
     }
 }
 
-pub fn setup_game_over_screen(mut commands: Commands, mut query: Query<&Score>) {
+pub fn setup_game_over_screen(
+    mut commands: Commands,
+    query: Query<(&Score, &PlayerId)>,
+    mode: Res<GameMode>,
+    leaderboard: Res<Leaderboard>,
+    timer: Option<Res<ModeTimer>>,
+    outcome: Option<Res<MatchOutcome>>,
+) {
+    let out_of_time = timer.is_some_and(|timer| timer.0.finished());
+    let title = match outcome.as_deref() {
+        Some(MatchOutcome::Winner(player)) => format!("{} Wins", player.name()),
+        Some(MatchOutcome::Draw) => "Draw".to_string(),
+        None if out_of_time => "Time's Up".to_string(),
+        None => "Game Over".to_string(),
+    };
+
     commands
         .spawn((
             StateScoped(IsPaused::GameOver),
@@ -45,7 +68,7 @@ pub fn setup_game_over_screen(mut commands: Commands, mut query: Query<&Score>)
                 };
 
                 p.spawn((
-                    Text::new("Game Over"),
+                    Text::new(title),
                     gameover_font,
                     TextColor(Color::srgb(1.0, 1.0, 1.0)),
                 ));
@@ -55,17 +78,38 @@ pub fn setup_game_over_screen(mut commands: Commands, mut query: Query<&Score>)
                     ..default()
                 };
 
-                let mut bestscore = 0;
-                for score in &mut query {
-                    if score.0 > bestscore {
-                        bestscore = score.0;
+                if outcome.is_some() {
+                    // One line per player after a match
+                    let mut scores: Vec<_> = query.iter().collect();
+                    scores.sort_by_key(|(_, player)| player.0);
+                    for (score, player) in scores {
+                        p.spawn((
+                            Text::new(format!("{}: {}", player.name(), score.0)),
+                            score_font.clone(),
+                            TextColor(Color::srgb(199.0 / 255.0, 236.0 / 255.0, 250.0 / 255.0)),
+                        ));
+                    }
+                } else {
+                    let mut bestscore = 0;
+                    for (score, _) in &query {
+                        if score.0 > bestscore {
+                            bestscore = score.0;
+                        }
                     }
+                    p.spawn((
+                        Text::new(format!("Score: {}", bestscore)),
+                        score_font.clone(),
+                        TextColor(Color::srgb(199.0 / 255.0, 236.0 / 255.0, 250.0 / 255.0)),
+                    ));
+                }
+
+                if let Some(best) = leaderboard.top(*mode).first() {
+                    p.spawn((
+                        Text::new(format!("Best {}: {}", mode.label(), best)),
+                        score_font,
+                        TextColor(Color::srgb(199.0 / 255.0, 236.0 / 255.0, 250.0 / 255.0)),
+                    ));
                 }
-                p.spawn((
-                    Text::new(format!("Score: {}", bestscore)),
-                    score_font,
-                    TextColor(Color::srgb(199.0 / 255.0, 236.0 / 255.0, 250.0 / 255.0)),
-                ));
 
                 // Restart the Game
                 p.spawn(create_menu_button("Restart")).observe(
@@ -76,6 +120,17 @@ pub fn setup_game_over_screen(mut commands: Commands, mut query: Query<&Score>)
                     },
                 );
 
+                // Back to the level editor after a playtest
+                if *mode == GameMode::Playtest {
+                    p.spawn(create_menu_button("Editor")).observe(
+                        |mut trigger: Trigger<Pointer<Released>>,
+                         mut next: ResMut<NextState<AppState>>| {
+                            trigger.propagate(false);
+                            next.set(AppState::Editor)
+                        },
+                    );
+                }
+
                 // Go to Main Menu
                 p.spawn(create_menu_button("Menu")).observe(
                     |mut trigger: Trigger<Pointer<Released>>,
@@ -95,29 +150,3 @@ pub fn setup_game_over_screen(mut commands: Commands, mut query: Query<&Score>)
             });
         });
 }
-
-// Helper function to create button
-fn create_menu_button(text: &str) -> impl Bundle {
-    (
-        // MainMenuButton,
-        Node {
-            width: Val::Px(150.0),
-            height: Val::Px(65.0),
-            border: UiRect::all(Val::Px(5.0)),
-            justify_content: JustifyContent::Center,
-            align_items: AlignItems::Center,
-            ..default()
-        },
-        BorderColor(Color::WHITE),
-        BorderRadius::all(Val::Percent(10.0)),
-        BackgroundColor(Color::srgb(0.2, 0.2, 0.2).into()),
-        Button,
-        children![(
-            Text::new(text),
-            TextFont {
-                font_size: 18.0,
-                ..default()
-            },
-        )],
-    )
-}
@@ -0,0 +1,107 @@
+use crate::{
+    AppState, IsPaused,
+    campaign::campaign::{LEVELS, unlock_next_level},
+    gameui::button::create_menu_button,
+    mode::mode::GameMode,
+};
+use bevy::prelude::*;
+
+pub struct LevelCompletePlugin;
+
+impl Plugin for LevelCompletePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(IsPaused::LevelComplete),
+            setup_level_complete_screen.after(unlock_next_level),
+        );
+    }
+}
+
+fn setup_level_complete_screen(mut commands: Commands, mode: Res<GameMode>) {
+    let next_level = match *mode {
+        GameMode::Campaign(level) if level + 1 < LEVELS.len() => Some(level + 1),
+        _ => None,
+    };
+
+    commands
+        .spawn((
+            StateScoped(IsPaused::LevelComplete),
+            Node {
+                // center button
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(10.),
+                ..default()
+            },
+        ))
+        .with_children(|p| {
+            p.spawn((
+                Node {
+                    width: Val::Px(400.),
+                    height: Val::Px(400.),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(10.0),
+                    ..default()
+                },
+                BackgroundColor(Color::srgb(0.15, 0.15, 0.15)),
+            ))
+            .with_children(|p| {
+                p.spawn((
+                    Text::new(if next_level.is_some() {
+                        "Level Complete"
+                    } else {
+                        "Campaign Complete"
+                    }),
+                    TextFont {
+                        font_size: 32.0,
+                        ..default()
+                    },
+                    TextColor(Color::srgb(1.0, 1.0, 1.0)),
+                ));
+
+                p.spawn((
+                    Text::new(mode.label()),
+                    TextFont {
+                        font_size: 24.0,
+                        ..default()
+                    },
+                    TextColor(Color::srgb(199.0 / 255.0, 236.0 / 255.0, 250.0 / 255.0)),
+                ));
+
+                if let Some(next_level) = next_level {
+                    p.spawn(create_menu_button("Next Level")).observe(
+                        move |mut trigger: Trigger<Pointer<Released>>,
+                              mut mode: ResMut<GameMode>,
+                              mut next: ResMut<NextState<AppState>>| {
+                            trigger.propagate(false);
+                            *mode = GameMode::Campaign(next_level);
+                            next.set(AppState::InGameLoading)
+                        },
+                    );
+                }
+
+                p.spawn(create_menu_button("Levels")).observe(
+                    |mut trigger: Trigger<Pointer<Released>>,
+                     mut next: ResMut<NextState<AppState>>| {
+                        trigger.propagate(false);
+                        next.set(AppState::LevelSelect)
+                    },
+                );
+
+                // Go to Main Menu
+                p.spawn(create_menu_button("Menu")).observe(
+                    |mut trigger: Trigger<Pointer<Released>>,
+                     mut next: ResMut<NextState<AppState>>| {
+                        trigger.propagate(false);
+                        next.set(AppState::MainMenu)
+                    },
+                );
+            });
+        });
+}
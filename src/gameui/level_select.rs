@@ -0,0 +1,102 @@
+use crate::{
+    AppState,
+    campaign::{campaign::LEVELS, progress::CampaignProgress},
+    gameui::button::{ButtonSize, create_button},
+    mode::mode::GameMode,
+    player::player::MovementMode,
+};
+use bevy::prelude::*;
+
+#[derive(Component, Default)]
+pub struct LevelSelectScreen;
+
+pub struct LevelSelectPlugin;
+
+impl Plugin for LevelSelectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::LevelSelect), spawn_level_select)
+            .add_systems(OnExit(AppState::LevelSelect), delete_level_select);
+    }
+}
+
+fn spawn_level_select(mut commands: Commands, progress: Res<CampaignProgress>) {
+    commands
+        .spawn((
+            LevelSelectScreen,
+            Node {
+                // center button
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(10.),
+                ..default()
+            },
+        ))
+        .with_children(|p| {
+            p.spawn((
+                Node {
+                    width: Val::Px(400.),
+                    height: Val::Px(560.),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(10.0),
+                    ..default()
+                },
+                BackgroundColor(Color::srgb(0.15, 0.15, 0.15)),
+            ))
+            .with_children(|p| {
+                p.spawn((
+                    Text::new("Campaign"),
+                    TextFont {
+                        font_size: 32.0,
+                        ..default()
+                    },
+                    TextColor(Color::srgb(1.0, 1.0, 1.0)),
+                ));
+
+                for (index, level) in LEVELS.iter().enumerate() {
+                    let label = format!("{}. {}", index + 1, level.name);
+
+                    // Locked levels are listed greyed out and ignore clicks
+                    if !progress.is_unlocked(index) {
+                        p.spawn(create_button(&label, ButtonSize::WIDE)).insert((
+                            BackgroundColor(Color::srgb(0.1, 0.1, 0.1)),
+                            BorderColor(Color::srgb(0.4, 0.4, 0.4)),
+                        ));
+                        continue;
+                    }
+
+                    p.spawn(create_button(&label, ButtonSize::WIDE)).observe(
+                        move |mut trigger: Trigger<Pointer<Released>>,
+                              mut selected: ResMut<GameMode>,
+                              mut movement: ResMut<MovementMode>,
+                              mut next: ResMut<NextState<AppState>>| {
+                            trigger.propagate(false);
+                            *selected = GameMode::Campaign(index);
+                            *movement = MovementMode::Grid;
+                            next.set(AppState::InGameLoading)
+                        },
+                    );
+                }
+
+                // Back to Main Menu
+                p.spawn(create_button("Back", ButtonSize::WIDE)).observe(
+                    |mut trigger: Trigger<Pointer<Released>>,
+                     mut next: ResMut<NextState<AppState>>| {
+                        trigger.propagate(false);
+                        next.set(AppState::MainMenu)
+                    },
+                );
+            });
+        });
+}
+
+fn delete_level_select(mut commands: Commands, query: Query<Entity, With<LevelSelectScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
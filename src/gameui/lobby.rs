@@ -0,0 +1,514 @@
+//! Screens for setting up an online match: the list of hosts found on the
+//! local network, and the lobby itself once hosting or joined. Whatever the
+//! screen shows is gathered into a [`LobbyModel`] every frame and the
+//! contents are built again whenever it changes.
+
+use crate::{
+    AppState,
+    gameui::{
+        button::{ButtonSize, create_button},
+        online::{OnlineStart, SEAT_COLORS},
+    },
+    net::{
+        client::ServerAddress,
+        lobby::{
+            BOARD_SIZES, Discovery, GuestLobby, HostLobby, ONLINE_MODES, PlayerName, Seat,
+            default_setup,
+        },
+        lockstep::MatchSetup,
+    },
+};
+use bevy::prelude::*;
+use std::net::SocketAddr;
+
+/// A little lower than the level list, the lobby has more rows.
+const LOBBY_BUTTON: ButtonSize = ButtonSize {
+    height: 50.0,
+    ..ButtonSize::WIDE
+};
+
+#[derive(Component, Default)]
+pub struct LobbyScreen;
+
+/// Parent of everything that is built again on changes.
+#[derive(Component)]
+struct LobbyContent;
+
+enum LobbyPhase {
+    Browsing(Discovery),
+    Hosting(HostLobby),
+    Joined(GuestLobby),
+}
+
+#[derive(Resource, Default)]
+pub struct OnlineLobby {
+    phase: Option<LobbyPhase>,
+    /// Why the last step did not work out, shown until the next one.
+    notice: Option<String>,
+}
+
+impl OnlineLobby {
+    /// Back to the list of hosts.
+    fn browse(&mut self) {
+        self.phase = match Discovery::new() {
+            Ok(discovery) => Some(LobbyPhase::Browsing(discovery)),
+            Err(err) => {
+                self.notice = Some(format!("NO NETWORK: {err}"));
+                None
+            }
+        };
+    }
+
+    /// Tells the other side this player is gone.
+    fn leave(&mut self) {
+        match self.phase.take() {
+            Some(LobbyPhase::Hosting(host)) => host.leave(),
+            Some(LobbyPhase::Joined(guest)) => guest.leave(),
+            _ => {}
+        }
+    }
+}
+
+/// Everything the screen shows.
+#[derive(Clone, PartialEq, Debug)]
+enum LobbyModel {
+    Offline,
+    Browsing {
+        /// Address, name and whether there is a free seat.
+        hosts: Vec<(SocketAddr, String, bool)>,
+    },
+    Room {
+        hosting: bool,
+        /// Still waiting for the host's first answer.
+        joining: Option<SocketAddr>,
+        seats: Vec<Seat>,
+        /// Without the seed, which changes when the countdown starts.
+        setup: Option<MatchSetup>,
+        /// Whole seconds left, rounded up.
+        countdown: Option<u64>,
+    },
+}
+
+impl LobbyModel {
+    fn of(lobby: &OnlineLobby) -> Self {
+        let countdown = |left: Option<std::time::Duration>| {
+            left.map(|left| left.as_millis().div_ceil(1000) as u64)
+        };
+        match &lobby.phase {
+            None => LobbyModel::Offline,
+            Some(LobbyPhase::Browsing(discovery)) => LobbyModel::Browsing {
+                hosts: discovery
+                    .hosts()
+                    .iter()
+                    .map(|host| (host.address, host.name.clone(), host.open))
+                    .collect(),
+            },
+            Some(LobbyPhase::Hosting(host)) => LobbyModel::Room {
+                hosting: true,
+                joining: None,
+                seats: host.view().seats,
+                setup: Some(MatchSetup {
+                    seed: 0,
+                    ..host.setup
+                }),
+                countdown: countdown(host.countdown_left()),
+            },
+            Some(LobbyPhase::Joined(guest)) => LobbyModel::Room {
+                hosting: false,
+                joining: guest.view.is_none().then_some(guest.host),
+                seats: guest
+                    .view
+                    .as_ref()
+                    .map_or_else(Vec::new, |view| view.seats.clone()),
+                setup: guest.view.as_ref().map(|view| MatchSetup {
+                    seed: 0,
+                    ..view.setup
+                }),
+                countdown: countdown(guest.countdown_left()),
+            },
+        }
+    }
+}
+
+pub struct LobbyPlugin;
+
+impl Plugin for LobbyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OnlineLobby>()
+            .add_systems(OnEnter(AppState::Lobby), open_lobby)
+            .add_systems(OnExit(AppState::Lobby), close_lobby)
+            .add_systems(
+                Update,
+                (update_lobby, draw_lobby)
+                    .chain()
+                    .run_if(in_state(AppState::Lobby)),
+            );
+    }
+}
+
+fn open_lobby(mut commands: Commands, mut lobby: ResMut<OnlineLobby>) {
+    lobby.notice = None;
+    lobby.browse();
+
+    commands
+        .spawn((
+            LobbyScreen,
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+        ))
+        .with_children(|p| {
+            p.spawn((
+                LobbyContent,
+                Node {
+                    width: Val::Px(460.),
+                    min_height: Val::Px(540.),
+                    padding: UiRect::all(Val::Px(20.)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(10.0),
+                    ..default()
+                },
+                BackgroundColor(Color::srgb(0.15, 0.15, 0.15)),
+            ));
+        });
+}
+
+fn close_lobby(
+    mut commands: Commands,
+    mut lobby: ResMut<OnlineLobby>,
+    query: Query<Entity, With<LobbyScreen>>,
+) {
+    lobby.leave();
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Talks to the network and moves on to the match once the countdown is over.
+fn update_lobby(
+    mut lobby: ResMut<OnlineLobby>,
+    mut start: ResMut<OnlineStart>,
+    mut next: ResMut<NextState<AppState>>,
+) {
+    let lobby = &mut *lobby;
+    let ready = match &mut lobby.phase {
+        Some(LobbyPhase::Browsing(discovery)) => {
+            discovery.update();
+            None
+        }
+        Some(LobbyPhase::Hosting(host)) => {
+            host.update();
+            host.start()
+        }
+        Some(LobbyPhase::Joined(guest)) => {
+            guest.update();
+            if guest.is_lost() {
+                lobby.notice = Some("THE HOST IS GONE".to_string());
+                lobby.browse();
+                return;
+            }
+            guest.start()
+        }
+        None => None,
+    };
+
+    if let Some(ready) = ready {
+        // The match has its own copy of the socket, the lobby is done
+        lobby.phase = None;
+        start.0 = Some(ready);
+        next.set(AppState::Online);
+    }
+}
+
+/// Builds the contents again when the model changed.
+fn draw_lobby(
+    mut commands: Commands,
+    lobby: Res<OnlineLobby>,
+    server: Option<Res<ServerAddress>>,
+    content: Single<Entity, With<LobbyContent>>,
+    mut shown: Local<Option<(Entity, LobbyModel, Option<String>)>>,
+) {
+    // The content is new on every visit, and empty until drawn
+    let model = (*content, LobbyModel::of(&lobby), lobby.notice.clone());
+    if shown.as_ref() == Some(&model) {
+        return;
+    }
+    let (_, view, notice) = &model;
+
+    let mut content = commands.entity(*content);
+    content.despawn_related::<Children>();
+    content.with_children(|p| {
+        match view {
+            LobbyModel::Offline => {
+                p.spawn(title("ONLINE"));
+            }
+            LobbyModel::Browsing { hosts } => {
+                spawn_browser(p, hosts, server.as_ref().map(|server| server.0.as_str()))
+            }
+            LobbyModel::Room {
+                hosting,
+                joining,
+                seats,
+                setup,
+                countdown,
+            } => {
+                if let Some(host) = joining {
+                    p.spawn(title(&format!("JOINING {host}")));
+                } else {
+                    spawn_room(p, *hosting, seats, *setup, *countdown);
+                }
+            }
+        }
+
+        if let Some(notice) = notice {
+            p.spawn((
+                Text::new(notice.clone()),
+                TextFont {
+                    font_size: 16.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.95, 0.45, 0.4)),
+            ));
+        }
+
+        let back = match view {
+            LobbyModel::Room { .. } => "Leave",
+            _ => "Back",
+        };
+        p.spawn(create_button(back, LOBBY_BUTTON)).observe(
+            |mut trigger: Trigger<Pointer<Released>>,
+             mut lobby: ResMut<OnlineLobby>,
+             mut next: ResMut<NextState<AppState>>| {
+                trigger.propagate(false);
+                lobby.notice = None;
+                match lobby.phase {
+                    Some(LobbyPhase::Hosting(_) | LobbyPhase::Joined(_)) => {
+                        lobby.leave();
+                        lobby.browse();
+                    }
+                    _ => next.set(AppState::MainMenu),
+                }
+            },
+        );
+    });
+    *shown = Some(model);
+}
+
+fn spawn_browser(
+    p: &mut ChildSpawnerCommands,
+    hosts: &[(SocketAddr, String, bool)],
+    server: Option<&str>,
+) {
+    p.spawn(title("ONLINE"));
+
+    if let Some(server) = server {
+        p.spawn(create_button(&format!("SERVER {server}"), LOBBY_BUTTON))
+            .observe(
+                |mut trigger: Trigger<Pointer<Released>>, mut next: ResMut<NextState<AppState>>| {
+                    trigger.propagate(false);
+                    next.set(AppState::ServerMatch);
+                },
+            );
+    }
+
+    p.spawn(create_button("Host", LOBBY_BUTTON)).observe(
+        |mut trigger: Trigger<Pointer<Released>>,
+         mut lobby: ResMut<OnlineLobby>,
+         name: Res<PlayerName>| {
+            trigger.propagate(false);
+            lobby.notice = None;
+            match HostLobby::new(&name, default_setup()) {
+                Ok(host) => lobby.phase = Some(LobbyPhase::Hosting(host)),
+                Err(err) => lobby.notice = Some(format!("COULD NOT HOST: {err}")),
+            }
+        },
+    );
+
+    p.spawn(label(match hosts.is_empty() {
+        true => "LOOKING FOR GAMES...",
+        false => "GAMES NEARBY",
+    }));
+    for (address, host, open) in hosts {
+        if !open {
+            p.spawn(label(&format!("{host} (FULL)")));
+            continue;
+        }
+        let address = *address;
+        p.spawn(create_button(&format!("JOIN {host}"), LOBBY_BUTTON))
+            .observe(
+                move |mut trigger: Trigger<Pointer<Released>>,
+                      mut lobby: ResMut<OnlineLobby>,
+                      name: Res<PlayerName>| {
+                    trigger.propagate(false);
+                    lobby.notice = None;
+                    match GuestLobby::new(&name, address) {
+                        Ok(guest) => lobby.phase = Some(LobbyPhase::Joined(guest)),
+                        Err(err) => lobby.notice = Some(format!("COULD NOT JOIN: {err}")),
+                    }
+                },
+            );
+    }
+}
+
+fn spawn_room(
+    p: &mut ChildSpawnerCommands,
+    hosting: bool,
+    seats: &[Seat],
+    setup: Option<MatchSetup>,
+    countdown: Option<u64>,
+) {
+    p.spawn(title("LOBBY"));
+
+    for (seat, color) in SEAT_COLORS.iter().enumerate() {
+        let (name, state) = match seats.get(seat) {
+            Some(seat) => (
+                seat.name.clone(),
+                match seat.ready {
+                    true => "READY",
+                    false => "NOT READY",
+                },
+            ),
+            None => ("WAITING FOR A PLAYER".to_string(), ""),
+        };
+        p.spawn(Node {
+            width: Val::Percent(100.),
+            align_items: AlignItems::Center,
+            column_gap: Val::Px(12.0),
+            ..default()
+        })
+        .with_children(|p| {
+            p.spawn((
+                Node {
+                    width: Val::Px(20.),
+                    height: Val::Px(20.),
+                    ..default()
+                },
+                BackgroundColor(*color),
+            ));
+            p.spawn((
+                label(&name),
+                Node {
+                    flex_grow: 1.0,
+                    ..default()
+                },
+            ));
+            p.spawn(label(state));
+        });
+    }
+
+    if let Some(setup) = setup {
+        // Only the host picks the match, the guest gets to see it
+        if hosting {
+            spawn_setting(p, &mode_label(setup), |setup| MatchSetup {
+                mode: ONLINE_MODES[(mode_index(setup) + 1) % ONLINE_MODES.len()],
+                ..setup
+            });
+            spawn_setting(p, &style_label(setup), |setup| MatchSetup {
+                style: setup.style.next(),
+                ..setup
+            });
+            spawn_setting(p, &size_label(setup), |setup| MatchSetup {
+                board: BOARD_SIZES[(size_index(setup) + 1) % BOARD_SIZES.len()].1,
+                ..setup
+            });
+        } else {
+            for text in [mode_label(setup), style_label(setup), size_label(setup)] {
+                p.spawn(label(&text));
+            }
+        }
+    }
+
+    let ready = seats
+        .get(usize::from(!hosting))
+        .is_some_and(|seat| seat.ready);
+    p.spawn(create_button(
+        match ready {
+            true => "Not ready",
+            false => "Ready",
+        },
+        LOBBY_BUTTON,
+    ))
+    .observe(
+        |mut trigger: Trigger<Pointer<Released>>, mut lobby: ResMut<OnlineLobby>| {
+            trigger.propagate(false);
+            match &mut lobby.phase {
+                Some(LobbyPhase::Hosting(host)) => host.set_ready(!host.seat.ready),
+                Some(LobbyPhase::Joined(guest)) => guest.set_ready(!guest.seat.ready),
+                _ => {}
+            }
+        },
+    );
+
+    if let Some(seconds) = countdown {
+        p.spawn(title(&format!("STARTING IN {seconds}")));
+    }
+}
+
+/// Button that changes the host's match with `change`.
+fn spawn_setting(
+    p: &mut ChildSpawnerCommands,
+    text: &str,
+    change: impl Fn(MatchSetup) -> MatchSetup + Send + Sync + 'static,
+) {
+    p.spawn(create_button(text, LOBBY_BUTTON)).observe(
+        move |mut trigger: Trigger<Pointer<Released>>, mut lobby: ResMut<OnlineLobby>| {
+            trigger.propagate(false);
+            if let Some(LobbyPhase::Hosting(host)) = &mut lobby.phase {
+                host.set_setup(change(host.setup));
+            }
+        },
+    );
+}
+
+fn mode_index(setup: MatchSetup) -> usize {
+    ONLINE_MODES
+        .iter()
+        .position(|mode| *mode == setup.mode)
+        .unwrap_or(0)
+}
+
+fn size_index(setup: MatchSetup) -> usize {
+    BOARD_SIZES
+        .iter()
+        .position(|(_, board)| *board == setup.board)
+        .unwrap_or(0)
+}
+
+fn mode_label(setup: MatchSetup) -> String {
+    format!("MODE: {}", setup.mode.label())
+}
+
+fn style_label(setup: MatchSetup) -> String {
+    format!("BOARD: {}", setup.style.label())
+}
+
+fn size_label(setup: MatchSetup) -> String {
+    format!("SIZE: {}", BOARD_SIZES[size_index(setup)].0)
+}
+
+fn title(text: &str) -> impl Bundle {
+    (
+        Text::new(text),
+        TextFont {
+            font_size: 32.0,
+            ..default()
+        },
+        TextColor(Color::srgb(1.0, 1.0, 1.0)),
+    )
+}
+
+fn label(text: &str) -> impl Bundle {
+    (
+        Text::new(text),
+        TextFont {
+            font_size: 18.0,
+            ..default()
+        },
+    )
+}
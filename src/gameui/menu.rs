@@ -1,10 +1,14 @@
-use crate::{AppState, MyAssets};
+use crate::{AppState, MyAssets, player::player::MovementMode};
 use bevy::prelude::*;
 use bevy_simple_subsecond_system::hot;
 
 #[derive(Component, Default)]
 pub struct MainMenuScreen;
 
+/// Fullscreen backdrop, hidden while the attract-mode demo plays.
+#[derive(Component)]
+pub struct MenuBackground;
+
 pub struct MainMenuPlugin;
 
 impl Plugin for MainMenuPlugin {
@@ -30,6 +34,7 @@ fn spawn_menu(mut commands: Commands, assets: Res<MyAssets>) {
             },
             BackgroundColor(Color::BLACK), // fallback color if image fails
             MainMenuScreen,
+            MenuBackground,
         ))
         .with_children(|parent| {
             parent.spawn((
@@ -66,7 +71,12 @@ fn spawn_menu(mut commands: Commands, assets: Res<MyAssets>) {
                 ..default()
             },
             // rgb(244, 144, 183)
-            BackgroundColor(Color::srgb(244.0 / 255.0, 144.0 / 255.0, 183.0 / 255.0)), // fallback color if image fails
+            BackgroundColor(Color::srgba(
+                244.0 / 255.0,
+                144.0 / 255.0,
+                183.0 / 255.0,
+                0.85,
+            )), // translucent over the attract demo
             MainMenuScreen,
         ))
         .with_children(|parent| {
@@ -99,33 +109,76 @@ fn spawn_menu(mut commands: Commands, assets: Res<MyAssets>) {
                                 ..default()
                             },
                             // rgb(255, 255, 255)
-                            TextColor(Color::srgb(255.0 / 255.0, 255.0 / 255.0, 255.0 / 255.0)),
+                            TextColor(Color::srgb(1.0, 1.0, 1.0)),
                         )],
                     ));
-                    child_parent.spawn((MainMenuScreen, play_button())).observe(
-                        |mut trigger: Trigger<Pointer<Released>>,
-                         mut state: ResMut<NextState<AppState>>| {
-                            trigger.propagate(false);
-                            // let event = trigger.event();
-                            // let target = event.target;
-                            state.set(AppState::InGameLoading);
-                        },
-                    );
-                    child_parent.spawn((MainMenuScreen, exit_button())).observe(
-                        |mut trigger: Trigger<Pointer<Released>>| {
+                    child_parent
+                        .spawn((MainMenuScreen, menu_button("PLAY")))
+                        .observe(
+                            |mut trigger: Trigger<Pointer<Released>>,
+                             mut mode: ResMut<MovementMode>,
+                             mut state: ResMut<NextState<AppState>>| {
+                                trigger.propagate(false);
+                                // let event = trigger.event();
+                                // let target = event.target;
+                                *mode = MovementMode::Grid;
+                                state.set(AppState::ModeSelect);
+                            },
+                        );
+                    #[cfg(feature = "physics")]
+                    child_parent
+                        .spawn((MainMenuScreen, menu_button("SLITHER")))
+                        .observe(
+                            |mut trigger: Trigger<Pointer<Released>>,
+                             mut mode: ResMut<MovementMode>,
+                             mut state: ResMut<NextState<AppState>>| {
+                                trigger.propagate(false);
+                                *mode = MovementMode::Slither;
+                                state.set(AppState::ModeSelect);
+                            },
+                        );
+                    child_parent
+                        .spawn((MainMenuScreen, menu_button("CAMPAIGN")))
+                        .observe(
+                            |mut trigger: Trigger<Pointer<Released>>,
+                             mut state: ResMut<NextState<AppState>>| {
+                                trigger.propagate(false);
+                                state.set(AppState::LevelSelect);
+                            },
+                        );
+                    child_parent
+                        .spawn((MainMenuScreen, menu_button("ONLINE")))
+                        .observe(
+                            |mut trigger: Trigger<Pointer<Released>>,
+                             mut state: ResMut<NextState<AppState>>| {
+                                trigger.propagate(false);
+                                state.set(AppState::Lobby);
+                            },
+                        );
+                    child_parent
+                        .spawn((MainMenuScreen, menu_button("EDITOR")))
+                        .observe(
+                            |mut trigger: Trigger<Pointer<Released>>,
+                             mut state: ResMut<NextState<AppState>>| {
+                                trigger.propagate(false);
+                                state.set(AppState::Editor);
+                            },
+                        );
+                    child_parent
+                        .spawn((MainMenuScreen, menu_button("EXIT")))
+                        .observe(|mut trigger: Trigger<Pointer<Released>>| {
                             trigger.propagate(false);
 
                             // exit the bevy app
                             std::process::exit(0);
-                        },
-                    );
+                        });
                 });
         });
 
     //
 }
 
-fn play_button() -> impl Bundle + use<> {
+fn menu_button(text: &str) -> impl Bundle + use<> {
     (
         Node {
             width: Val::Percent(100.0),
@@ -150,43 +203,7 @@ fn play_button() -> impl Bundle + use<> {
             // rgb(199, 236, 250)
             BackgroundColor(Color::srgb(199.0 / 255.0, 236.0 / 255.0, 250.0 / 255.0)),
             children![(
-                Text::new("PLAY"),
-                TextFont {
-                    font_size: 22.0,
-                    ..default()
-                },
-                // rgb(29, 45, 60)
-                TextColor(Color::srgb(29.0 / 255.0, 45.0 / 255.0, 60.0 / 255.0)),
-            )]
-        )],
-    )
-}
-
-fn exit_button() -> impl Bundle + use<> {
-    (
-        Node {
-            width: Val::Percent(100.0),
-            height: Val::Percent(100.0),
-            align_items: AlignItems::Center,
-            justify_content: JustifyContent::Center,
-            ..default()
-        },
-        children![(
-            Button,
-            Node {
-                width: Val::Px(160.0),
-                height: Val::Px(54.0),
-                // border: UiRect::all(Val::Px(5.0)),
-                // horizontally center child text
-                justify_content: JustifyContent::Center,
-                // vertically center child text
-                align_items: AlignItems::Center,
-                ..default()
-            },
-            // rgb(199, 236, 250)
-            BackgroundColor(Color::srgb(199.0 / 255.0, 236.0 / 255.0, 250.0 / 255.0)),
-            children![(
-                Text::new("EXIT"),
+                Text::new(text.to_string()),
                 TextFont {
                     font_size: 22.0,
                     ..default()
@@ -1,3 +1,12 @@
 pub mod menu;
+pub mod mode_select;
 pub mod pause;
-pub mod gameover;
\ No newline at end of file
+pub mod gameover;
+pub mod level_complete;
+pub mod level_select;
+pub mod attract;
+pub mod sim_view;
+pub mod online;
+pub mod lobby;
+pub mod server_match;
+pub mod button;
@@ -0,0 +1,138 @@
+use crate::{
+    AppState, ai::ai::Opponent, board::generator::BoardStyle, gameui::button::create_menu_button,
+    mode::mode::GameMode, player::player::MovementMode,
+};
+use bevy::prelude::*;
+
+#[derive(Component, Default)]
+pub struct ModeSelectScreen;
+
+pub struct ModeSelectPlugin;
+
+impl Plugin for ModeSelectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::ModeSelect), spawn_mode_select)
+            .add_systems(OnExit(AppState::ModeSelect), delete_mode_select);
+    }
+}
+
+fn spawn_mode_select(
+    mut commands: Commands,
+    style: Res<BoardStyle>,
+    opponent: Res<Opponent>,
+    movement: Res<MovementMode>,
+) {
+    commands
+        .spawn((
+            ModeSelectScreen,
+            Node {
+                // center button
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(10.),
+                ..default()
+            },
+        ))
+        .with_children(|p| {
+            p.spawn((
+                Node {
+                    width: Val::Px(400.),
+                    height: Val::Px(730.),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(10.0),
+                    ..default()
+                },
+                BackgroundColor(Color::srgb(0.15, 0.15, 0.15)),
+            ))
+            .with_children(|p| {
+                p.spawn((
+                    Text::new("Select Mode"),
+                    TextFont {
+                        font_size: 32.0,
+                        ..default()
+                    },
+                    TextColor(Color::srgb(1.0, 1.0, 1.0)),
+                ));
+
+                // Slither mode only ever has a single snake
+                for mode in GameMode::ALL
+                    .into_iter()
+                    .filter(|mode| *movement == MovementMode::Grid || mode.rules().players == 1)
+                {
+                    p.spawn(create_menu_button(mode.label())).observe(
+                        move |mut trigger: Trigger<Pointer<Released>>,
+                              mut selected: ResMut<GameMode>,
+                              mut next: ResMut<NextState<AppState>>| {
+                            trigger.propagate(false);
+                            *selected = mode;
+                            next.set(AppState::InGameLoading)
+                        },
+                    );
+                }
+
+                // Cycles through the board generators, the label shows the current one
+                p.spawn(create_menu_button(&board_label(*style))).observe(
+                    |mut trigger: Trigger<Pointer<Released>>,
+                     mut style: ResMut<BoardStyle>,
+                     children: Query<&Children>,
+                     mut texts: Query<&mut Text>| {
+                        trigger.propagate(false);
+                        *style = style.next();
+                        for child in children.iter_descendants(trigger.target()) {
+                            if let Ok(mut text) = texts.get_mut(child) {
+                                text.0 = board_label(*style);
+                            }
+                        }
+                    },
+                );
+
+                // Computer opponent, only grid snakes know how to play against one
+                if *movement == MovementMode::Grid {
+                    p.spawn(create_menu_button(&rival_label(*opponent)))
+                        .observe(
+                            |mut trigger: Trigger<Pointer<Released>>,
+                             mut opponent: ResMut<Opponent>,
+                             children: Query<&Children>,
+                             mut texts: Query<&mut Text>| {
+                                trigger.propagate(false);
+                                *opponent = opponent.next();
+                                for child in children.iter_descendants(trigger.target()) {
+                                    if let Ok(mut text) = texts.get_mut(child) {
+                                        text.0 = rival_label(*opponent);
+                                    }
+                                }
+                            },
+                        );
+                }
+
+                // Back to Main Menu
+                p.spawn(create_menu_button("Back")).observe(
+                    |mut trigger: Trigger<Pointer<Released>>,
+                     mut next: ResMut<NextState<AppState>>| {
+                        trigger.propagate(false);
+                        next.set(AppState::MainMenu)
+                    },
+                );
+            });
+        });
+}
+
+fn board_label(style: BoardStyle) -> String {
+    format!("BOARD: {}", style.label())
+}
+
+fn rival_label(opponent: Opponent) -> String {
+    format!("RIVAL: {}", opponent.label())
+}
+
+fn delete_mode_select(mut commands: Commands, query: Query<Entity, With<ModeSelectScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
@@ -0,0 +1,227 @@
+//! A lockstep match against the player met in the lobby. Both sides run the
+//! same [`Sim`](crate::sim::sim::Sim) and only trade turns, the snakes are
+//! drawn from it the same way as the attract-mode demo.
+
+use crate::{
+    AppState, MyAssets,
+    gameui::sim_view::{spawn_sim_board, spawn_sim_pieces},
+    net::{
+        lobby::LobbyStart,
+        lockstep::{DEFAULT_INPUT_DELAY, LockstepMatch, LockstepSession},
+    },
+    player::{
+        controller::{AnyOf, ControlInput, GamepadController, KeyboardController, SnakeController},
+        player::{KeyBindings, RIVAL_COLOR, SNAKE_COLOR},
+    },
+};
+use bevy::prelude::*;
+use std::time::Duration;
+
+/// Snake colours by seat, the host plays the first snake.
+pub const SEAT_COLORS: [Color; 2] = [SNAKE_COLOR, RIVAL_COLOR];
+
+/// Silence after which the peer is shown as missing.
+const WAITING_AFTER: Duration = Duration::from_millis(500);
+
+/// Silence after which the match is given up.
+const LOST_AFTER: Duration = Duration::from_secs(5);
+
+/// Ticks that can pile up while waiting on the peer. Anything beyond is
+/// dropped, so the game picks up its pace again instead of racing ahead.
+const MAX_DUE: u32 = 2;
+
+/// Filled by the lobby when its countdown runs out.
+#[derive(Resource, Default)]
+pub struct OnlineStart(pub Option<LobbyStart>);
+
+#[derive(Resource)]
+struct OnlineMatch {
+    game: LockstepMatch,
+    names: [String; 2],
+    /// This side's snake, both keyboard layouts and the first gamepad.
+    local: AnyOf,
+    step: Timer,
+    /// Ticks whose time has come but that have not been played yet.
+    due: u32,
+}
+
+impl OnlineMatch {
+    fn is_lost(&self) -> bool {
+        self.game
+            .session
+            .turns
+            .silence()
+            .is_some_and(|silence| silence >= LOST_AFTER)
+    }
+}
+
+#[derive(Component, Default)]
+pub struct OnlineScreen;
+
+#[derive(Component, Clone)]
+pub struct OnlineBoard;
+
+#[derive(Component, Clone)]
+pub struct OnlinePiece;
+
+#[derive(Component)]
+struct OnlineStatus;
+
+pub struct OnlinePlugin;
+
+impl Plugin for OnlinePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OnlineStart>()
+            .add_systems(OnEnter(AppState::Online), start_online)
+            .add_systems(OnExit(AppState::Online), stop_online)
+            .add_systems(Update, run_online.run_if(in_state(AppState::Online)));
+    }
+}
+
+fn start_online(
+    mut commands: Commands,
+    assets: Res<MyAssets>,
+    mut start: ResMut<OnlineStart>,
+    mut next: ResMut<NextState<AppState>>,
+) {
+    let Some(start) = start.0.take() else {
+        next.set(AppState::MainMenu);
+        return;
+    };
+    let session = LockstepSession::new(start.transport, start.player, DEFAULT_INPUT_DELAY);
+    let game = LockstepMatch::new(&start.setup, session);
+    spawn_sim_board(&mut commands, &assets, &game.sim, OnlineBoard);
+    spawn_sim_pieces(&mut commands, &assets, &game.sim, &SEAT_COLORS, OnlinePiece);
+
+    commands.spawn((
+        OnlineScreen,
+        Node {
+            width: Val::Percent(100.0),
+            padding: UiRect::all(Val::Px(12.0)),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        children![(
+            OnlineStatus,
+            Text::new(""),
+            TextFont {
+                font_size: 24.0,
+                ..default()
+            },
+            TextLayout::new_with_justify(JustifyText::Center),
+        )],
+    ));
+
+    let step_seconds = start.setup.mode.rules().step_seconds;
+    commands.insert_resource(OnlineMatch {
+        game,
+        names: start.names,
+        local: AnyOf(vec![
+            Box::new(KeyboardController::new(KeyBindings::ARROWS)),
+            Box::new(KeyboardController::new(KeyBindings::WASD)),
+            Box::new(GamepadController::new(0)),
+        ]),
+        step: Timer::from_seconds(step_seconds, TimerMode::Repeating),
+        due: 0,
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_online(
+    mut commands: Commands,
+    assets: Res<MyAssets>,
+    online: Option<ResMut<OnlineMatch>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<(Entity, &Gamepad)>,
+    time: Res<Time>,
+    pieces: Query<Entity, With<OnlinePiece>>,
+    mut status: Query<&mut Text, With<OnlineStatus>>,
+    mut next: ResMut<NextState<AppState>>,
+) {
+    let Some(mut online) = online else {
+        return;
+    };
+    let online = &mut *online;
+    let ended = online.game.is_over() || online.is_lost();
+    if keyboard.just_pressed(KeyCode::Escape) || (ended && keyboard.just_pressed(KeyCode::Enter)) {
+        next.set(AppState::MainMenu);
+        return;
+    }
+
+    let mut gamepads: Vec<_> = gamepads.iter().collect();
+    gamepads.sort_by_key(|(entity, _)| *entity);
+    online.local.observe(&ControlInput {
+        keyboard: &keyboard,
+        gamepads: gamepads.into_iter().map(|(_, gamepad)| gamepad).collect(),
+    });
+
+    // A finished side keeps answering, the peer may still miss its last turns
+    online.step.tick(time.delta());
+    online.due = (online.due + online.step.times_finished_this_tick()).min(MAX_DUE);
+    let mut stepped = false;
+    while online.due > 0 && !ended && online.game.advance(&mut online.local) {
+        online.due -= 1;
+        stepped = true;
+    }
+    if !stepped {
+        online.game.session.update();
+    }
+
+    if stepped {
+        for entity in &pieces {
+            commands.entity(entity).despawn();
+        }
+        spawn_sim_pieces(
+            &mut commands,
+            &assets,
+            &online.game.sim,
+            &SEAT_COLORS,
+            OnlinePiece,
+        );
+    }
+
+    if let Ok(mut text) = status.single_mut() {
+        let line = status_line(online);
+        if text.0 != line {
+            text.0 = line;
+        }
+    }
+}
+
+/// Scores by seat, then whatever is holding the game up or how it ended.
+fn status_line(online: &OnlineMatch) -> String {
+    let snakes = &online.game.sim.snakes;
+    let scores = format!(
+        "{} {}  -  {} {}",
+        online.names[0], snakes[0].score, snakes[1].score, online.names[1]
+    );
+    let peer = &online.names[1 - online.game.session.player];
+    let silence = online.game.session.turns.silence();
+
+    let state = if online.game.is_over() {
+        // The last snake standing wins, when the clock ran out the score
+        let result = match online.game.sim.leader() {
+            Some(winner) => format!("{} WINS", online.names[winner]),
+            None => "DRAW".to_string(),
+        };
+        format!("{result}\nENTER FOR THE MENU")
+    } else if online.is_lost() {
+        format!("LOST {peer}\nENTER FOR THE MENU")
+    } else if silence.is_none_or(|silence| silence >= WAITING_AFTER) {
+        format!("WAITING FOR {peer}")
+    } else {
+        return scores;
+    };
+    format!("{scores}\n{state}")
+}
+
+#[allow(clippy::type_complexity)]
+fn stop_online(
+    mut commands: Commands,
+    query: Query<Entity, Or<(With<OnlineScreen>, With<OnlineBoard>, With<OnlinePiece>)>>,
+) {
+    for entity in &query {
+        commands.entity(entity).despawn();
+    }
+    commands.remove_resource::<OnlineMatch>();
+}
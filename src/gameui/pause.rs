@@ -1,4 +1,4 @@
-use crate::{AppState, IsPaused};
+use crate::{AppState, IsPaused, gameui::button::create_menu_button, mode::mode::GameMode};
 use bevy::prelude::*;
 
 pub struct PauseMenuPlugin;
@@ -20,11 +20,12 @@ fn toggle_pause(
             IsPaused::Running => IsPaused::Paused,
             IsPaused::Paused => IsPaused::Running,
             IsPaused::GameOver => IsPaused::GameOver, // Keep GameOver state unchanged
+            IsPaused::LevelComplete => IsPaused::LevelComplete,
         });
     }
 }
 
-pub fn setup_paused_screen(mut commands: Commands) {
+pub fn setup_paused_screen(mut commands: Commands, mode: Res<GameMode>) {
     commands
         .spawn((
             StateScoped(IsPaused::Paused),
@@ -71,6 +72,17 @@ pub fn setup_paused_screen(mut commands: Commands) {
                     },
                 );
 
+                // Back to the level editor after a playtest
+                if *mode == GameMode::Playtest {
+                    p.spawn(create_menu_button("Editor")).observe(
+                        |mut trigger: Trigger<Pointer<Released>>,
+                         mut next: ResMut<NextState<AppState>>| {
+                            trigger.propagate(false);
+                            next.set(AppState::Editor)
+                        },
+                    );
+                }
+
                 // Go to Main Menu
                 p.spawn(create_menu_button("Menu")).observe(
                     |mut trigger: Trigger<Pointer<Released>>,
@@ -90,29 +102,3 @@ pub fn setup_paused_screen(mut commands: Commands) {
             });
         });
 }
-
-// Helper function to create button
-fn create_menu_button(text: &str) -> impl Bundle {
-    (
-        // MainMenuButton,
-        Node {
-            width: Val::Px(150.0),
-            height: Val::Px(65.0),
-            border: UiRect::all(Val::Px(5.0)),
-            justify_content: JustifyContent::Center,
-            align_items: AlignItems::Center,
-            ..default()
-        },
-        BorderColor(Color::WHITE),
-        BorderRadius::all(Val::Percent(10.0)),
-        BackgroundColor(Color::srgb(0.2, 0.2, 0.2).into()),
-        Button,
-        children![(
-            Text::new(text),
-            TextFont {
-                font_size: 18.0,
-                ..default()
-            },
-        )],
-    )
-}
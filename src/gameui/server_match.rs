@@ -0,0 +1,309 @@
+//! A match on a dedicated server. Nothing is simulated here, every snapshot
+//! the server sends is put into a copy of the round's [`Sim`]. Snakes and
+//! apples look as they do in a local game, and their entities are moved to
+//! where each snapshot has them rather than spawned again.
+
+use crate::{
+    AppState, MyAssets,
+    gameui::sim_view::spawn_sim_board,
+    net::{
+        client::{ServerAddress, ServerLink},
+        lobby::PlayerName,
+        server::{RoundPhase, Snapshot, round_sim},
+    },
+    player::{
+        controller::{AnyOf, ControlInput, GamepadController, KeyboardController, SnakeController},
+        food::food_sprite,
+        player::{KeyBindings, PlayerId, SnakeRenderAssets, cell_to_world, snake_piece},
+    },
+    sim::sim::Sim,
+};
+use bevy::prelude::*;
+
+#[derive(Resource)]
+struct ServerMatch {
+    link: ServerLink,
+    /// The round as last drawn, with the number of the round.
+    shown: Option<(u64, Sim)>,
+    /// Sequence number of the snapshot last drawn.
+    drawn: u64,
+    /// Newest snapshot that fits the round, the one the status shows.
+    snapshot: Option<Snapshot>,
+    /// Pieces of every snake by snake index, head first.
+    snakes: Vec<Vec<Entity>>,
+    apples: Vec<Entity>,
+    /// This player's snake, both keyboard layouts and the first gamepad.
+    local: AnyOf,
+}
+
+#[derive(Component, Default)]
+pub struct ServerMatchScreen;
+
+#[derive(Component, Clone)]
+pub struct ServerMatchBoard;
+
+#[derive(Component, Clone)]
+pub struct ServerMatchPiece;
+
+#[derive(Component)]
+struct ServerMatchStatus;
+
+pub struct ServerMatchPlugin;
+
+impl Plugin for ServerMatchPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::ServerMatch), start_server_match)
+            .add_systems(OnExit(AppState::ServerMatch), stop_server_match)
+            .add_systems(
+                Update,
+                run_server_match.run_if(in_state(AppState::ServerMatch)),
+            );
+    }
+}
+
+fn start_server_match(
+    mut commands: Commands,
+    address: Option<Res<ServerAddress>>,
+    name: Res<PlayerName>,
+    mut next: ResMut<NextState<AppState>>,
+) {
+    let Some(address) = address else {
+        next.set(AppState::MainMenu);
+        return;
+    };
+    let link = match ServerLink::connect(address.0.as_str(), &name) {
+        Ok(link) => link,
+        Err(err) => {
+            warn!("Could not reach {}: {err}", address.0);
+            next.set(AppState::MainMenu);
+            return;
+        }
+    };
+
+    commands.spawn((
+        ServerMatchScreen,
+        Node {
+            width: Val::Percent(100.0),
+            padding: UiRect::all(Val::Px(12.0)),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        children![(
+            ServerMatchStatus,
+            Text::new(""),
+            TextFont {
+                font_size: 24.0,
+                ..default()
+            },
+            TextLayout::new_with_justify(JustifyText::Center),
+        )],
+    ));
+
+    commands.insert_resource(ServerMatch {
+        link,
+        shown: None,
+        drawn: 0,
+        snapshot: None,
+        snakes: Vec::new(),
+        apples: Vec::new(),
+        local: AnyOf(vec![
+            Box::new(KeyboardController::new(KeyBindings::ARROWS)),
+            Box::new(KeyboardController::new(KeyBindings::WASD)),
+            Box::new(GamepadController::new(0)),
+        ]),
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_server_match(
+    mut commands: Commands,
+    assets: Res<MyAssets>,
+    render: Res<SnakeRenderAssets>,
+    game: Option<ResMut<ServerMatch>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<(Entity, &Gamepad)>,
+    board: Query<Entity, With<ServerMatchBoard>>,
+    mut pieces: Query<&mut Transform, With<ServerMatchPiece>>,
+    mut status: Query<&mut Text, With<ServerMatchStatus>>,
+    mut next: ResMut<NextState<AppState>>,
+) {
+    let Some(mut game) = game else {
+        return;
+    };
+    let game = &mut *game;
+    if keyboard.just_pressed(KeyCode::Escape) {
+        next.set(AppState::MainMenu);
+        return;
+    }
+
+    let mut gamepads: Vec<_> = gamepads.iter().collect();
+    gamepads.sort_by_key(|(entity, _)| *entity);
+    game.local.observe(&ControlInput {
+        keyboard: &keyboard,
+        gamepads: gamepads.into_iter().map(|(_, gamepad)| gamepad).collect(),
+    });
+    // The controllers only look at keys and sticks, the snake they are shown
+    // is as far as the last snapshot
+    if let (Some((_, sim)), Some(player)) = (&game.shown, game.link.player)
+        && player < sim.snakes.len()
+    {
+        while let Some(turn) = game.local.decide(&sim.view(player)) {
+            game.link.push_turn(turn);
+        }
+    }
+    game.link.update();
+
+    if let Some(snapshot) = &game.link.snapshot
+        && snapshot.seq != game.drawn
+    {
+        game.drawn = snapshot.seq;
+        // Every round has a board of its own
+        let fresh = game
+            .shown
+            .as_ref()
+            .is_none_or(|(round, _)| *round != snapshot.round)
+            .then(|| round_sim(&snapshot.setup).0);
+        let sim = fresh.as_ref().or(game.shown.as_ref().map(|(_, sim)| sim));
+        if sim.is_some_and(|sim| snapshot.fits(sim)) {
+            if let Some(sim) = fresh {
+                for entity in &board {
+                    commands.entity(entity).despawn();
+                }
+                spawn_sim_board(&mut commands, &assets, &sim, ServerMatchBoard);
+                game.shown = Some((snapshot.round, sim));
+            }
+            if let Some((_, sim)) = &mut game.shown {
+                snapshot.apply(sim);
+                game.snakes.resize_with(sim.snakes.len(), Vec::new);
+                for (i, (snake, entities)) in sim.snakes.iter().zip(&mut game.snakes).enumerate() {
+                    // Dead snakes are no longer drawn
+                    let body = snake.body.iter().filter(|_| snake.alive());
+                    let material = render.material(PlayerId(i));
+                    place_pieces(&mut commands, &mut pieces, entities, body, |cell| {
+                        snake_piece(&render, material.clone(), cell)
+                    });
+                }
+                place_pieces(
+                    &mut commands,
+                    &mut pieces,
+                    &mut game.apples,
+                    sim.food.iter(),
+                    |cell| food_sprite(&assets, cell),
+                );
+            }
+            game.snapshot = Some(snapshot.clone());
+        } else {
+            warn!(
+                "Ignoring a snapshot that does not fit round {}",
+                snapshot.round
+            );
+        }
+    }
+
+    if let Ok(mut text) = status.single_mut() {
+        let line = status_line(&game.link, game.snapshot.as_ref());
+        if text.0 != line {
+            text.0 = line;
+        }
+    }
+}
+
+/// Moves `entities` onto `cells`, spawning pieces that are missing and
+/// despawning the ones left over.
+fn place_pieces<'a, B: Bundle>(
+    commands: &mut Commands,
+    pieces: &mut Query<&mut Transform, With<ServerMatchPiece>>,
+    entities: &mut Vec<Entity>,
+    cells: impl Iterator<Item = &'a IVec2>,
+    spawn: impl Fn(IVec2) -> B,
+) {
+    let mut placed = 0;
+    for &cell in cells {
+        match entities.get(placed) {
+            Some(&entity) => {
+                if let Ok(mut transform) = pieces.get_mut(entity) {
+                    transform.translation = cell_to_world(cell, transform.translation.z);
+                }
+            }
+            None => entities.push(commands.spawn((ServerMatchPiece, spawn(cell))).id()),
+        }
+        placed += 1;
+    }
+    for entity in entities.drain(placed..) {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Scores by seat, then whatever is holding the round up or how it ended.
+fn status_line(link: &ServerLink, snapshot: Option<&Snapshot>) -> String {
+    if link.closed {
+        return "THE SERVER CLOSED\nESC FOR THE MENU".to_string();
+    }
+    if link.full {
+        return "THE SERVER IS FULL\nESC FOR THE MENU".to_string();
+    }
+    let Some(snapshot) = snapshot else {
+        return format!("CONNECTING TO {}", link.server);
+    };
+
+    let name = |snake: usize| match snapshot.snakes[snake].name.as_str() {
+        "" => "OPEN SEAT".to_string(),
+        name => name.to_string(),
+    };
+    let scores = (0..snapshot.snakes.len())
+        .map(|snake| format!("{} {}", name(snake), snapshot.snakes[snake].score))
+        .collect::<Vec<_>>()
+        .join("  -  ");
+
+    let state = if !link.is_connected() {
+        "LOST THE SERVER, RECONNECTING".to_string()
+    } else {
+        match snapshot.phase {
+            RoundPhase::Waiting => "WAITING FOR PLAYERS".to_string(),
+            RoundPhase::Countdown { ms } => format!("STARTING IN {}", ms.div_ceil(1000)),
+            RoundPhase::Playing => return scores,
+            RoundPhase::Paused => format!("WAITING FOR {}", missing(snapshot)),
+            RoundPhase::Over { winner } => {
+                let result = match winner {
+                    Some(winner) => format!("{} WINS", name(winner)),
+                    None => "DRAW".to_string(),
+                };
+                format!("{result}\nNEXT ROUND SOON")
+            }
+        }
+    };
+    format!("{scores}\n{state}")
+}
+
+/// Names of the players the round is waiting for.
+fn missing(snapshot: &Snapshot) -> String {
+    snapshot
+        .snakes
+        .iter()
+        .filter(|snake| !snake.connected && !snake.name.is_empty())
+        .map(|snake| snake.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[allow(clippy::type_complexity)]
+fn stop_server_match(
+    mut commands: Commands,
+    game: Option<Res<ServerMatch>>,
+    query: Query<
+        Entity,
+        Or<(
+            With<ServerMatchScreen>,
+            With<ServerMatchBoard>,
+            With<ServerMatchPiece>,
+        )>,
+    >,
+) {
+    if let Some(game) = game {
+        game.link.leave();
+    }
+    for entity in &query {
+        commands.entity(entity).despawn();
+    }
+    commands.remove_resource::<ServerMatch>();
+}
@@ -0,0 +1,74 @@
+//! Sprites for a [`Sim`], which has no entities of its own. The board is
+//! spawned once, the snakes and apples are despawned and spawned again after
+//! every step, they are few enough for that to be cheap.
+
+use crate::{
+    MyAssets,
+    board::{
+        obstacle::{OBSTACLE_COLOR, WALL_COLOR},
+        occupancy::CellContent,
+        portal::portal_color,
+    },
+    player::player::{GRID_SIZE, cell_to_world},
+    sim::sim::Sim,
+};
+use bevy::prelude::*;
+
+/// Ground, walls, obstacles and portals, each spawned with `marker`.
+pub fn spawn_sim_board(
+    commands: &mut Commands,
+    assets: &MyAssets,
+    sim: &Sim,
+    marker: impl Bundle + Clone,
+) {
+    for cell in sim.board().cells() {
+        commands.spawn((
+            marker.clone(),
+            Sprite::from_image(assets.ground.clone()),
+            Transform::from_translation(cell_to_world(cell, 0.0)),
+        ));
+
+        let color = match sim.occupancy.get(cell).map(|occupant| occupant.content) {
+            Some(CellContent::Wall) => WALL_COLOR,
+            Some(CellContent::Obstacle) => OBSTACLE_COLOR,
+            Some(CellContent::Portal) => portal_color(1),
+            _ => continue,
+        };
+        commands.spawn((
+            marker.clone(),
+            Sprite::from_color(color, Vec2::splat(GRID_SIZE)),
+            Transform::from_translation(cell_to_world(cell, 1.0)),
+        ));
+    }
+}
+
+/// Apples and living snakes, the snakes in `colors` by snake index, each
+/// spawned with `marker`.
+pub fn spawn_sim_pieces(
+    commands: &mut Commands,
+    assets: &MyAssets,
+    sim: &Sim,
+    colors: &[Color],
+    marker: impl Bundle + Clone,
+) {
+    for &cell in &sim.food {
+        commands.spawn((
+            marker.clone(),
+            Sprite::from_image(assets.apple.clone()),
+            Transform::from_translation(cell_to_world(cell, 2.0)),
+        ));
+    }
+
+    for (snake, &color) in sim.snakes.iter().zip(colors) {
+        if !snake.alive() {
+            continue;
+        }
+        for &cell in &snake.body {
+            commands.spawn((
+                marker.clone(),
+                Sprite::from_color(color, Vec2::splat(GRID_SIZE)),
+                Transform::from_translation(cell_to_world(cell, 3.0)),
+            ));
+        }
+    }
+}
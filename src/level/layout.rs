@@ -0,0 +1,355 @@
+//! Text format for board layouts, one character per cell:
+//!
+//! | char            | cell                                              |
+//! |-----------------|---------------------------------------------------|
+//! | `.`             | empty floor                                       |
+//! | `#`             | wall                                              |
+//! | `o`             | obstacle                                          |
+//! | `*`             | floor where food may spawn                        |
+//! | `>` `<` `^` `v` | snake spawn, pointing the way it starts moving    |
+//! | `1`..`9`        | portal, each digit appears exactly twice          |
+//!
+//! Lines starting with `;` are comments. Rows must all be the same length and
+//! both dimensions odd, so the middle cell lands on `(0, 0)` like every other
+//! board. Without any `*` cell food can spawn on any free cell.
+
+use crate::{board::board::Board, player::player::Direction};
+use bevy::prelude::*;
+use std::{fmt, io};
+
+/// One character of a layout file.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Tile {
+    Floor,
+    Wall,
+    Obstacle,
+    FoodZone,
+    Spawn(Direction),
+    /// Portal pair `1..=9`.
+    Portal(u8),
+}
+
+impl Tile {
+    pub fn from_char(tile: char) -> Option<Self> {
+        Some(match tile {
+            '.' => Tile::Floor,
+            '#' => Tile::Wall,
+            'o' => Tile::Obstacle,
+            '*' => Tile::FoodZone,
+            '>' => Tile::Spawn(Direction::Right),
+            '<' => Tile::Spawn(Direction::Left),
+            '^' => Tile::Spawn(Direction::Up),
+            'v' => Tile::Spawn(Direction::Down),
+            '1'..='9' => Tile::Portal(tile as u8 - b'0'),
+            _ => return None,
+        })
+    }
+
+    pub fn to_char(self) -> char {
+        match self {
+            Tile::Floor => '.',
+            Tile::Wall => '#',
+            Tile::Obstacle => 'o',
+            Tile::FoodZone => '*',
+            Tile::Spawn(Direction::Right) => '>',
+            Tile::Spawn(Direction::Left) => '<',
+            Tile::Spawn(Direction::Up) => '^',
+            Tile::Spawn(Direction::Down) => 'v',
+            Tile::Portal(id) => (b'0' + id) as char,
+        }
+    }
+}
+
+#[derive(Asset, TypePath, Clone, PartialEq, Debug)]
+pub struct LevelLayout {
+    pub board: Board,
+    pub walls: Vec<IVec2>,
+    pub obstacles: Vec<IVec2>,
+    pub spawn: IVec2,
+    pub spawn_direction: Direction,
+    /// Cells food is limited to, empty for anywhere.
+    pub food_zones: Vec<IVec2>,
+    /// Portal pairs, numbered by their position in the list plus one.
+    pub portals: Vec<(IVec2, IVec2)>,
+}
+
+/// Why a layout file was rejected. Lines and columns are 1-based and count
+/// comment lines too, so they match what a text editor shows.
+#[derive(Debug)]
+pub enum LevelError {
+    Io(io::Error),
+    NotUtf8,
+    Empty,
+    RaggedRow {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    EvenSize {
+        width: usize,
+        height: usize,
+    },
+    UnknownTile {
+        line: usize,
+        column: usize,
+        tile: char,
+    },
+    MissingSpawn,
+    ExtraSpawn {
+        line: usize,
+        column: usize,
+    },
+    UnpairedPortal {
+        id: char,
+        count: usize,
+    },
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelError::Io(error) => write!(f, "could not read level: {error}"),
+            LevelError::NotUtf8 => write!(f, "level is not valid UTF-8"),
+            LevelError::Empty => write!(f, "level has no rows"),
+            LevelError::RaggedRow {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {line}: row is {found} cells wide, expected {expected}"
+            ),
+            LevelError::EvenSize { width, height } => write!(
+                f,
+                "board is {width}x{height}, both sides must be odd to have a middle cell"
+            ),
+            LevelError::UnknownTile { line, column, tile } => {
+                write!(f, "line {line}, column {column}: unknown tile '{tile}'")
+            }
+            LevelError::MissingSpawn => write!(f, "no spawn point, add one of > < ^ v"),
+            LevelError::ExtraSpawn { line, column } => {
+                write!(f, "line {line}, column {column}: second spawn point")
+            }
+            LevelError::UnpairedPortal { id, count } => {
+                write!(f, "portal {id} appears {count} times, expected 2")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+impl From<io::Error> for LevelError {
+    fn from(error: io::Error) -> Self {
+        LevelError::Io(error)
+    }
+}
+
+impl LevelLayout {
+    pub fn parse(text: &str) -> Result<Self, LevelError> {
+        let rows: Vec<(usize, Vec<char>)> = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim_end()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with(';'))
+            .map(|(line, row)| (line, row.chars().collect()))
+            .collect();
+
+        let Some((_, first)) = rows.first() else {
+            return Err(LevelError::Empty);
+        };
+        let (width, height) = (first.len(), rows.len());
+        if let Some((line, row)) = rows.iter().find(|(_, row)| row.len() != width) {
+            return Err(LevelError::RaggedRow {
+                line: *line,
+                expected: width,
+                found: row.len(),
+            });
+        }
+        if width % 2 == 0 || height % 2 == 0 {
+            return Err(LevelError::EvenSize { width, height });
+        }
+
+        let board = Board {
+            half_extent: IVec2::new(width as i32 / 2, height as i32 / 2),
+        };
+        let mut walls = Vec::new();
+        let mut obstacles = Vec::new();
+        let mut food_zones = Vec::new();
+        let mut spawn = None;
+        let mut portal_ends: [Vec<IVec2>; 9] = Default::default();
+
+        for (row, (line, tiles)) in rows.iter().enumerate() {
+            for (column, &tile) in tiles.iter().enumerate() {
+                // First row is the top of the board
+                let cell = IVec2::new(
+                    column as i32 - board.half_extent.x,
+                    board.half_extent.y - row as i32,
+                );
+                let direction = match Tile::from_char(tile) {
+                    Some(Tile::Floor) => continue,
+                    Some(Tile::Wall) => {
+                        walls.push(cell);
+                        continue;
+                    }
+                    Some(Tile::Obstacle) => {
+                        obstacles.push(cell);
+                        continue;
+                    }
+                    Some(Tile::FoodZone) => {
+                        food_zones.push(cell);
+                        continue;
+                    }
+                    Some(Tile::Portal(id)) => {
+                        portal_ends[id as usize - 1].push(cell);
+                        continue;
+                    }
+                    Some(Tile::Spawn(direction)) => direction,
+                    None => {
+                        return Err(LevelError::UnknownTile {
+                            line: *line,
+                            column: column + 1,
+                            tile,
+                        });
+                    }
+                };
+                if spawn.replace((cell, direction)).is_some() {
+                    return Err(LevelError::ExtraSpawn {
+                        line: *line,
+                        column: column + 1,
+                    });
+                }
+            }
+        }
+
+        let (spawn, spawn_direction) = spawn.ok_or(LevelError::MissingSpawn)?;
+
+        let mut portals = Vec::new();
+        for (id, ends) in ('1'..='9').zip(&portal_ends) {
+            match ends.as_slice() {
+                [] => {}
+                [a, b] => portals.push((*a, *b)),
+                _ => {
+                    return Err(LevelError::UnpairedPortal {
+                        id,
+                        count: ends.len(),
+                    });
+                }
+            }
+        }
+
+        Ok(Self {
+            board,
+            walls,
+            obstacles,
+            spawn,
+            spawn_direction,
+            food_zones,
+            portals,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_walls_spawn_and_portals() {
+        let layout = LevelLayout::parse(
+            "; a small room\n\
+             #####\n\
+             #1*o#\n\
+             #.>.#\n\
+             #..1#\n\
+             #####\n",
+        )
+        .unwrap();
+        assert_eq!(layout.board.half_extent, IVec2::new(2, 2));
+        assert_eq!(layout.walls.len(), 16);
+        assert!(layout.walls.contains(&IVec2::new(-2, 2)));
+        assert_eq!(layout.obstacles, [IVec2::new(1, 1)]);
+        assert_eq!(layout.food_zones, [IVec2::new(0, 1)]);
+        assert_eq!(layout.spawn, IVec2::ZERO);
+        assert_eq!(layout.spawn_direction, Direction::Right);
+        assert_eq!(layout.portals, [(IVec2::new(-1, 1), IVec2::new(1, -1))]);
+    }
+
+    #[test]
+    fn rejects_an_empty_level() {
+        assert!(matches!(
+            LevelLayout::parse("; only a comment\n\n"),
+            Err(LevelError::Empty)
+        ));
+    }
+
+    #[test]
+    fn rejects_ragged_rows() {
+        assert!(matches!(
+            LevelLayout::parse("...\n.>\n...\n"),
+            Err(LevelError::RaggedRow {
+                line: 2,
+                expected: 3,
+                found: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_even_sizes() {
+        assert!(matches!(
+            LevelLayout::parse("..\n>.\n..\n"),
+            Err(LevelError::EvenSize {
+                width: 2,
+                height: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_unknown_tiles() {
+        assert!(matches!(
+            LevelLayout::parse(";\n...\n.>x\n...\n"),
+            Err(LevelError::UnknownTile {
+                line: 3,
+                column: 3,
+                tile: 'x'
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_a_missing_spawn() {
+        assert!(matches!(
+            LevelLayout::parse("...\n...\n...\n"),
+            Err(LevelError::MissingSpawn)
+        ));
+    }
+
+    #[test]
+    fn rejects_a_second_spawn() {
+        assert!(matches!(
+            LevelLayout::parse("...\n>.<\n...\n"),
+            Err(LevelError::ExtraSpawn { line: 2, column: 3 })
+        ));
+    }
+
+    #[test]
+    fn rejects_unpaired_portals() {
+        assert!(matches!(
+            LevelLayout::parse("2..\n.>.\n...\n"),
+            Err(LevelError::UnpairedPortal { id: '2', count: 1 })
+        ));
+        assert!(matches!(
+            LevelLayout::parse("3.3\n.>.\n..3\n"),
+            Err(LevelError::UnpairedPortal { id: '3', count: 3 })
+        ));
+    }
+
+    #[test]
+    fn read_errors_name_their_cause() {
+        let error: LevelError = io::Error::other("gone").into();
+        assert!(matches!(error, LevelError::Io(_)));
+        assert_eq!(error.to_string(), "could not read level: gone");
+    }
+}
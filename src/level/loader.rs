@@ -0,0 +1,104 @@
+use crate::{
+    AppState, MyAssets,
+    booting::error_screen::LoadErrors,
+    level::layout::{LevelError, LevelLayout},
+    mode::mode::GameMode,
+};
+use bevy::{
+    asset::{AssetLoadFailedEvent, AssetLoader, LoadContext, io::Reader},
+    prelude::*,
+};
+
+/// Reads `.level` files from `assets/levels/` into [`LevelLayout`]s.
+#[derive(Default)]
+pub struct LevelLoader;
+
+impl AssetLoader for LevelLoader {
+    type Asset = LevelLayout;
+    type Settings = ();
+    type Error = LevelError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<LevelLayout, LevelError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let text = std::str::from_utf8(&bytes).map_err(|_| LevelError::NotUtf8)?;
+        LevelLayout::parse(text)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level"]
+    }
+}
+
+/// Layout handed over by the level editor for a playtest run.
+#[derive(Resource, Default)]
+pub struct PlaytestLayout(pub Handle<LevelLayout>);
+
+/// Layout of the level being played, `None` when the board just fills the window.
+pub fn active_layout<'a>(
+    mode: GameMode,
+    assets: &'a MyAssets,
+    playtest: &'a PlaytestLayout,
+) -> Option<&'a Handle<LevelLayout>> {
+    match mode {
+        GameMode::Playtest => Some(&playtest.0),
+        _ => mode
+            .level()
+            .and_then(|level| assets.levels.get(level.layout)),
+    }
+}
+
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<LevelLayout>()
+            .init_resource::<PlaytestLayout>()
+            .init_asset_loader::<LevelLoader>()
+            .add_systems(Update, report_load_failures)
+            .add_systems(
+                Update,
+                reload_changed_level.run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+/// Broken files during boot end up on the error screen. A file that breaks
+/// while playing only logs, the previous version of the level stays loaded.
+fn report_load_failures(
+    mut failures: EventReader<AssetLoadFailedEvent<LevelLayout>>,
+    mut errors: ResMut<LoadErrors>,
+    state: Res<State<AppState>>,
+) {
+    for failure in failures.read() {
+        warn!("{}", failure.error);
+        if *state.get() != AppState::InGame {
+            errors.0.push(failure.error.to_string());
+        }
+    }
+}
+
+/// Restarts the level when its file changes on disk, so layout edits show up
+/// without going back through the menus.
+fn reload_changed_level(
+    mut events: EventReader<AssetEvent<LevelLayout>>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mode: Res<GameMode>,
+    assets: Res<MyAssets>,
+    playtest: Res<PlaytestLayout>,
+) {
+    let Some(active) = active_layout(*mode, &assets, &playtest) else {
+        events.clear();
+        return;
+    };
+
+    if events.read().any(|event| event.is_modified(active.id())) {
+        info!("Level file changed, restarting");
+        next_state.set(AppState::InGameLoading);
+    }
+}
@@ -0,0 +1,105 @@
+//! The snake game as a library, shared by the game itself and the headless
+//! `snake-sim` runner.
+
+// Every area keeps its main file under its own name, e.g. `board::board`
+#![allow(clippy::module_inception)]
+
+use crate::level::layout::LevelLayout;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use bevy_asset_loader::prelude::*;
+use std::{env, fs, io, path::PathBuf};
+
+pub mod ai;
+pub mod board;
+pub mod booting;
+pub mod camera;
+pub mod campaign;
+pub mod editor;
+pub mod gameui;
+pub mod level;
+pub mod mode;
+pub mod net;
+pub mod physics;
+pub mod player;
+pub mod sim;
+pub mod window;
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Default, States)]
+pub enum AppState {
+    #[default]
+    Restarting,
+    BootingApp,
+    ErrorScreen,
+    MainMenu,
+    ModeSelect,
+    LevelSelect,
+    Editor,
+    InGameLoading,
+    InGame,
+    Paused,
+    Lobby,
+    Online,
+    ServerMatch,
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, SubStates)]
+#[source(AppState = AppState::InGame)]
+#[states(scoped_entities)]
+pub enum IsPaused {
+    #[default]
+    Running,
+    Paused,
+    GameOver,
+    LevelComplete,
+}
+
+#[derive(Resource, Default, AssetCollection)]
+pub struct MyAssets {
+    #[asset(path = "background.png")]
+    background: Handle<Image>,
+    #[asset(path = "ground.png")]
+    ground: Handle<Image>,
+    #[asset(path = "apple.png")]
+    apple: Handle<Image>,
+    /// Portal animation frames in one row, white so each pair can tint them.
+    #[asset(path = "portal.png")]
+    portal: Handle<Image>,
+    #[asset(texture_atlas_layout(tile_size_x = 32, tile_size_y = 32, columns = 8, rows = 1))]
+    portal_layout: Handle<TextureAtlasLayout>,
+    /// Board layouts keyed by path, e.g. `levels/01_first_bite.level`.
+    #[asset(path = "levels", collection(typed, mapped))]
+    levels: HashMap<String, Handle<LevelLayout>>,
+}
+
+/// Where a file the game keeps between sessions goes, in this game's folder
+/// under the platform's data directory. `None` when there is no home to put
+/// it in.
+fn save_path(file: &str) -> Option<PathBuf> {
+    let var = |name| env::var_os(name).filter(|value| !value.is_empty());
+    let data = if cfg!(windows) {
+        PathBuf::from(var("APPDATA")?)
+    } else if cfg!(target_os = "macos") {
+        PathBuf::from(var("HOME")?).join("Library/Application Support")
+    } else {
+        match var("XDG_DATA_HOME") {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(var("HOME")?).join(".local/share"),
+        }
+    };
+    Some(data.join(env!("CARGO_PKG_NAME")).join(file))
+}
+
+/// Contents of a saved file, `None` when it was never saved or cannot be read.
+pub fn read_save(file: &str) -> Option<String> {
+    fs::read_to_string(save_path(file)?).ok()
+}
+
+pub fn write_save(file: &str, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let path = save_path(file)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, contents)
+}
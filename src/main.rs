@@ -1,10 +1,27 @@
-use crate::booting::boot_screen::BootPlugin;
-use crate::gameui::gameover::GameOverPlugin;
-use crate::gameui::menu::MainMenuPlugin;
-use crate::gameui::pause::PauseMenuPlugin;
-use crate::player::player::PlayerPlugin;
-use crate::window::window::CustomWindowPlugin;
-use avian2d::prelude::*;
+use bevy_movment::ai::ai::AiPlugin;
+use bevy_movment::board::portal::PortalPlugin;
+use bevy_movment::booting::boot_screen::BootPlugin;
+use bevy_movment::booting::error_screen::ErrorScreenPlugin;
+use bevy_movment::campaign::campaign::CampaignPlugin;
+use bevy_movment::editor::editor::EditorPlugin;
+use bevy_movment::gameui::attract::AttractPlugin;
+use bevy_movment::gameui::gameover::GameOverPlugin;
+use bevy_movment::gameui::level_complete::LevelCompletePlugin;
+use bevy_movment::gameui::level_select::LevelSelectPlugin;
+use bevy_movment::gameui::lobby::LobbyPlugin;
+use bevy_movment::gameui::menu::MainMenuPlugin;
+use bevy_movment::gameui::mode_select::ModeSelectPlugin;
+use bevy_movment::gameui::online::OnlinePlugin;
+use bevy_movment::gameui::pause::PauseMenuPlugin;
+use bevy_movment::gameui::server_match::ServerMatchPlugin;
+use bevy_movment::level::loader::LevelPlugin;
+use bevy_movment::mode::leaderboard::LeaderboardPlugin;
+use bevy_movment::mode::mode::GameModePlugin;
+use bevy_movment::net::client::ServerAddress;
+use bevy_movment::net::lobby::PlayerName;
+use bevy_movment::physics::physics::GamePhysicsPlugin;
+use bevy_movment::player::player::PlayerPlugin;
+use bevy_movment::window::window::CustomWindowPlugin;
 use bevy::winit::WinitSettings;
 use bevy::{
     dev_tools::fps_overlay::{FpsOverlayConfig, FpsOverlayPlugin},
@@ -12,47 +29,20 @@ use bevy::{
     text::FontSmoothing,
 };
 use bevy_asset_loader::prelude::*;
+use bevy_movment::player::bot::BotLink;
+use bevy_movment::{AppState, IsPaused, MyAssets};
 use bevy_simple_subsecond_system::prelude::*;
 
-mod booting;
-mod camera;
-mod gameui;
-mod player;
-mod window;
-
-#[derive(Debug, Clone, Eq, PartialEq, Hash, Default, States)]
-pub enum AppState {
-    #[default]
-    Restarting,
-    BootingApp,
-    ErrorScreen,
-    MainMenu,
-    InGameLoading,
-    InGame,
-    Paused,
-}
-
-#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, SubStates)]
-#[source(AppState = AppState::InGame)]
-#[states(scoped_entities)]
-pub enum IsPaused {
-    #[default]
-    Running,
-    Paused,
-    GameOver
-}
-
-#[derive(Resource, Default, AssetCollection)]
-pub struct MyAssets {
-    #[asset(path = "background.png")]
-    background: Handle<Image>,
-    #[asset(path = "ground.png")]
-    ground: Handle<Image>,
-    #[asset(path = "apple.png")]
-    apple: Handle<Image>,
-}
-
 fn main() {
+    // `--bot stdio` or `--bot <address>` hands the first snake to an outside program
+    let bot = match BotLink::from_args(std::env::args().skip(1)) {
+        Ok(bot) => bot,
+        Err(message) => {
+            eprintln!("{message}");
+            std::process::exit(2);
+        }
+    };
+
     let mut app = App::new();
     app.add_plugins((
         DefaultPlugins.set(WindowPlugin {
@@ -83,7 +73,8 @@ fn main() {
         },
     ))
     .add_plugins(SimpleSubsecondPlugin::default())
-    .add_plugins((PhysicsPlugins::default(), PhysicsDebugPlugin::default()))
+    .add_plugins(GamePhysicsPlugin)
+    .add_plugins(LevelPlugin)
     // Only run the app when there is user input. This will significantly reduce CPU/GPU use.
     .insert_resource(WinitSettings::game())
     .init_state::<AppState>()
@@ -97,12 +88,36 @@ fn main() {
     .add_systems(OnEnter(AppState::Restarting), go_to_running)
     .add_systems(PreUpdate, detect_restart_key)
     .add_plugins(BootPlugin)
+    .add_plugins(ErrorScreenPlugin)
     .add_plugins(CustomWindowPlugin)
     .add_plugins(MainMenuPlugin)
+    .add_plugins(AttractPlugin)
+    .add_plugins(ModeSelectPlugin)
+    .add_plugins(LevelSelectPlugin)
+    .add_plugins(LobbyPlugin)
+    .add_plugins(OnlinePlugin)
+    .add_plugins(ServerMatchPlugin)
+    .add_plugins(EditorPlugin)
+    .add_plugins(GameModePlugin)
+    .add_plugins(LeaderboardPlugin)
+    .add_plugins(CampaignPlugin)
     .add_plugins(PlayerPlugin)
+    .add_plugins(PortalPlugin)
+    .add_plugins(AiPlugin)
     .add_plugins(GameOverPlugin)
+    .add_plugins(LevelCompletePlugin)
     .add_plugins(PauseMenuPlugin);
 
+    // `--name` is what other players see in an online lobby
+    app.insert_resource(PlayerName::from_args(std::env::args().skip(1)));
+    // `--server <address>` offers a match on a dedicated server there
+    if let Some(server) = ServerAddress::from_args(std::env::args().skip(1)) {
+        app.insert_resource(server);
+    }
+    if let Some(bot) = bot {
+        app.insert_resource(bot);
+    }
+
     app.run();
 }
 
@@ -0,0 +1,76 @@
+use crate::{
+    IsPaused, gameui::gameover::setup_game_over_screen, mode::mode::GameMode,
+    player::player::Score, read_save, write_save,
+};
+use bevy::{platform::collections::HashMap, prelude::*};
+use std::io;
+
+/// Scores kept per category.
+const MAX_ENTRIES: usize = 10;
+
+/// File the best scores are kept in between sessions, next to the campaign progress.
+const LEADERBOARD_FILE: &str = "leaderboard.json";
+
+/// Best scores so far, one category per game mode so a time attack run is
+/// never ranked against an endless one.
+#[derive(Resource, Default)]
+pub struct Leaderboard(HashMap<GameMode, Vec<u32>>);
+
+impl Leaderboard {
+    /// Reads saved scores, starting empty when there are none or they cannot be read.
+    pub fn load() -> Self {
+        read_save(LEADERBOARD_FILE)
+            .and_then(|contents| serde_json::from_str::<Vec<(GameMode, Vec<u32>)>>(&contents).ok())
+            .map(|categories| {
+                let mut leaderboard = Self::default();
+                for (mode, scores) in categories {
+                    for score in scores {
+                        leaderboard.record(mode, score);
+                    }
+                }
+                leaderboard
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        // Campaign modes carry a level, so the categories go out as a list, not a map
+        let categories: Vec<_> = self.0.iter().collect();
+        write_save(LEADERBOARD_FILE, serde_json::to_string(&categories)?)
+    }
+
+    pub fn record(&mut self, mode: GameMode, score: u32) {
+        let entries = self.0.entry(mode).or_default();
+        let at = entries.partition_point(|&best| best >= score);
+        entries.insert(at, score);
+        entries.truncate(MAX_ENTRIES);
+    }
+
+    /// Scores for a mode, best first.
+    pub fn top(&self, mode: GameMode) -> &[u32] {
+        self.0.get(&mode).map_or(&[], Vec::as_slice)
+    }
+}
+
+pub struct LeaderboardPlugin;
+
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Leaderboard::load()).add_systems(
+            OnEnter(IsPaused::GameOver),
+            record_score.before(setup_game_over_screen),
+        );
+    }
+}
+
+fn record_score(mut leaderboard: ResMut<Leaderboard>, mode: Res<GameMode>, query: Query<&Score>) {
+    if query.is_empty() {
+        return;
+    }
+    for score in &query {
+        leaderboard.record(*mode, score.0);
+    }
+    if let Err(error) = leaderboard.save() {
+        warn!("Could not save the leaderboard: {error}");
+    }
+}
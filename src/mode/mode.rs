@@ -0,0 +1,306 @@
+use crate::{
+    AppState, IsPaused,
+    campaign::campaign::{LEVELS, Level},
+    player::player::{InGameEntity, PlayerId, Score, ScoreText},
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Ruleset for a run, picked on the mode select screen.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Hash, Default, Debug, Serialize, Deserialize)]
+pub enum GameMode {
+    /// Die on the border or on your own body.
+    #[default]
+    Classic,
+    /// Edges lead to the opposite side, only your own body kills.
+    WrapAround,
+    /// Classic rules against a countdown, apples buy extra seconds.
+    TimeAttack,
+    /// No way to die and no clock, just eat.
+    Zen,
+    /// Two snakes on one keyboard, the first to crash loses.
+    Versus,
+    /// Classic rules on the campaign level with this index.
+    Campaign(usize),
+    /// Classic rules on the layout open in the level editor.
+    Playtest,
+}
+
+/// Everything a mode decides about a run.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ModeRules {
+    /// Leaving the board re-enters on the opposite edge instead of ending the run.
+    pub wrap_edges: bool,
+    /// Running into a snake body ends the run.
+    pub body_collision: bool,
+    /// Seconds per grid step.
+    pub step_seconds: f32,
+    /// Body pieces the snake grows on its first steps.
+    pub start_length: u32,
+    pub points_per_food: u32,
+    /// Length of the run in seconds, `None` for no clock.
+    pub time_limit: Option<f32>,
+    /// Seconds added to the clock for every apple.
+    pub bonus_seconds: f32,
+    /// Snakes on the board, each steered by its own player.
+    pub players: usize,
+}
+
+impl GameMode {
+    /// Modes offered on the mode select screen, campaign levels have their own.
+    pub const ALL: [GameMode; 5] = [
+        GameMode::Classic,
+        GameMode::WrapAround,
+        GameMode::TimeAttack,
+        GameMode::Zen,
+        GameMode::Versus,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GameMode::Classic => "CLASSIC",
+            GameMode::WrapAround => "WRAP",
+            GameMode::TimeAttack => "TIME ATTACK",
+            GameMode::Zen => "ZEN",
+            GameMode::Versus => "VERSUS",
+            GameMode::Playtest => "PLAYTEST",
+            GameMode::Campaign(level) => LEVELS.get(level).map_or("CAMPAIGN", |level| level.name),
+        }
+    }
+
+    /// Mode by its command line name, e.g. `time-attack`.
+    pub fn from_arg(value: &str) -> Option<Self> {
+        Some(match value {
+            "classic" => GameMode::Classic,
+            "wrap" => GameMode::WrapAround,
+            "time-attack" => GameMode::TimeAttack,
+            "zen" => GameMode::Zen,
+            "versus" => GameMode::Versus,
+            _ => return None,
+        })
+    }
+
+    pub fn level(self) -> Option<&'static Level> {
+        match self {
+            GameMode::Campaign(level) => LEVELS.get(level),
+            _ => None,
+        }
+    }
+
+    pub fn rules(self) -> ModeRules {
+        let classic = ModeRules {
+            wrap_edges: false,
+            body_collision: true,
+            step_seconds: 0.5,
+            start_length: 0,
+            points_per_food: 1,
+            time_limit: None,
+            bonus_seconds: 0.0,
+            players: 1,
+        };
+
+        match self {
+            GameMode::Classic | GameMode::Playtest => classic,
+            GameMode::WrapAround => ModeRules {
+                wrap_edges: true,
+                ..classic
+            },
+            GameMode::TimeAttack => ModeRules {
+                step_seconds: 0.3,
+                start_length: 2,
+                points_per_food: 10,
+                time_limit: Some(90.0),
+                bonus_seconds: 3.0,
+                ..classic
+            },
+            GameMode::Zen => ModeRules {
+                wrap_edges: true,
+                body_collision: false,
+                step_seconds: 0.6,
+                ..classic
+            },
+            GameMode::Versus => ModeRules {
+                step_seconds: 0.3,
+                start_length: 2,
+                players: 2,
+                ..classic
+            },
+            GameMode::Campaign(_) => ModeRules {
+                step_seconds: self
+                    .level()
+                    .map_or(classic.step_seconds, |level| level.step_seconds),
+                ..classic
+            },
+        }
+    }
+}
+
+/// Seed everything random about a run is derived from, rolled when a run is
+/// loaded so a restart gets a fresh board.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct RunSeed(pub u64);
+
+/// Sent by the movement systems whenever a snake eats an apple, with the
+/// head of the snake that ate it.
+#[derive(Event)]
+pub struct FoodEaten(pub Entity);
+
+/// How a match between several snakes ended, set when the first one crashes.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MatchOutcome {
+    Winner(PlayerId),
+    /// Every snake crashed on the same step.
+    Draw,
+}
+
+/// Countdown for modes with a time limit, only ticks while the game is running.
+#[derive(Resource)]
+pub struct ModeTimer(pub Timer);
+
+#[derive(Component)]
+pub struct TimerText;
+
+pub struct GameModePlugin;
+
+impl Plugin for GameModePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameMode>()
+            .init_resource::<RunSeed>()
+            .add_event::<FoodEaten>()
+            .add_systems(OnEnter(AppState::InGameLoading), roll_run_seed)
+            .add_systems(OnEnter(AppState::InGame), start_run)
+            .add_systems(OnExit(AppState::InGame), end_run)
+            .add_systems(
+                Update,
+                (score_food, tick_mode_timer, update_timer_text)
+                    .chain()
+                    .run_if(in_state(IsPaused::Running)),
+            );
+    }
+}
+
+fn roll_run_seed(mut seed: ResMut<RunSeed>) {
+    seed.0 = rand::random();
+}
+
+fn start_run(mut commands: Commands, mode: Res<GameMode>) {
+    if let Some(limit) = mode.rules().time_limit {
+        commands.insert_resource(ModeTimer(Timer::from_seconds(limit, TimerMode::Once)));
+        commands.spawn((
+            InGameEntity,
+            TimerText,
+            Text::new(format_time(limit)),
+            TextFont {
+                font_size: 28.0,
+                ..default()
+            },
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(44.0),
+                right: Val::Px(10.0),
+                ..default()
+            },
+        ));
+    }
+
+    // Player one in the top right corner, a second player opposite
+    let players = mode.rules().players;
+    for i in 0..players {
+        let player = PlayerId(i);
+        let mut node = Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            ..default()
+        };
+        if i == 0 {
+            node.right = Val::Px(10.0);
+        } else {
+            node.left = Val::Px(10.0);
+        }
+        commands.spawn((
+            InGameEntity,
+            ScoreText(player),
+            Text::new(score_label(player, 0, players)),
+            TextFont {
+                font_size: 28.0,
+                ..default()
+            },
+            node,
+        ));
+    }
+}
+
+fn end_run(mut commands: Commands) {
+    commands.remove_resource::<ModeTimer>();
+    commands.remove_resource::<MatchOutcome>();
+}
+
+fn score_label(player: PlayerId, score: u32, players: usize) -> String {
+    if players > 1 {
+        format!("{}: {score}", player.name())
+    } else {
+        format!("Score: {score}")
+    }
+}
+
+fn score_food(
+    mut food_eaten: EventReader<FoodEaten>,
+    mode: Res<GameMode>,
+    timer: Option<ResMut<ModeTimer>>,
+    mut scores: Query<(&mut Score, &PlayerId)>,
+    mut score_text: Query<(&mut Text, &ScoreText)>,
+) {
+    let rules = mode.rules();
+    let mut eaten = 0;
+    for FoodEaten(snake) in food_eaten.read() {
+        // Apples a computer snake eats neither score nor buy time
+        let Ok((mut score, player)) = scores.get_mut(*snake) else {
+            continue;
+        };
+        eaten += 1;
+        score.0 += rules.points_per_food;
+        for (mut text, ScoreText(owner)) in &mut score_text {
+            if owner == player {
+                text.0 = score_label(*player, score.0, rules.players);
+            }
+        }
+    }
+    if eaten == 0 {
+        return;
+    }
+
+    // Bonus time can push the clock past its starting length
+    if let Some(mut timer) = timer {
+        let remaining = timer.0.remaining_secs() + eaten as f32 * rules.bonus_seconds;
+        let duration = timer.0.duration().as_secs_f32().max(remaining);
+        timer.0.set_duration(Duration::from_secs_f32(duration));
+        timer
+            .0
+            .set_elapsed(Duration::from_secs_f32(duration - remaining));
+    }
+}
+
+fn tick_mode_timer(
+    time: Res<Time>,
+    timer: Option<ResMut<ModeTimer>>,
+    mut next_state: ResMut<NextState<IsPaused>>,
+) {
+    if let Some(mut timer) = timer
+        && timer.0.tick(time.delta()).just_finished()
+    {
+        next_state.set(IsPaused::GameOver);
+    }
+}
+
+fn update_timer_text(timer: Option<Res<ModeTimer>>, mut query: Query<&mut Text, With<TimerText>>) {
+    if let Some(timer) = timer {
+        for mut text in &mut query {
+            text.0 = format_time(timer.0.remaining_secs());
+        }
+    }
+}
+
+fn format_time(seconds: f32) -> String {
+    format!("Time: {seconds:.1}")
+}
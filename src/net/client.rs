@@ -0,0 +1,206 @@
+//! The player's end of a match on a dedicated server, see
+//! [`server`](crate::net::server). It asks for a seat, sends turns until the
+//! server has them and keeps the newest snapshot, once all of its chunks are
+//! in. When the server goes quiet
+//! it asks for its seat back, with the token from the first welcome.
+
+use crate::{
+    net::{
+        lobby::PlayerName,
+        server::{ClientMessage, HEARTBEAT, SERVER_PORT, ServerMessage, Snapshot, TIMEOUT},
+        transport::{MAX_PACKET, Reassembly, receive_message, send_message},
+    },
+    player::player::Direction,
+};
+use bevy::prelude::*;
+use std::{
+    collections::VecDeque,
+    io,
+    net::{Ipv4Addr, SocketAddr, ToSocketAddrs, UdpSocket},
+    time::{Duration, Instant},
+};
+
+/// How often unacknowledged turns go out again.
+const RESEND_INTERVAL: Duration = Duration::from_millis(30);
+
+/// Turns that may wait for the server, anything beyond is dropped.
+const MAX_PENDING: usize = 8;
+
+/// Server to play on, from `--server`. The port can be left out.
+#[derive(Resource, Clone, PartialEq, Eq, Debug)]
+pub struct ServerAddress(pub String);
+
+impl ServerAddress {
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Option<Self> {
+        let address = args
+            .into_iter()
+            .skip_while(|arg| arg != "--server")
+            .nth(1)?;
+        Some(match address.contains(':') {
+            true => Self(address),
+            false => Self(format!("{address}:{SERVER_PORT}")),
+        })
+    }
+}
+
+pub struct ServerLink {
+    socket: UdpSocket,
+    pub server: SocketAddr,
+    name: String,
+    /// Snake of this player, from the last welcome.
+    pub player: Option<usize>,
+    token: Option<u64>,
+    /// Seated and not asking for the seat again.
+    welcomed: bool,
+    /// Newest snapshot that came in.
+    pub snapshot: Option<Snapshot>,
+    /// Chunks of the snapshot on its way.
+    reassembly: Reassembly,
+    /// Turns from `turns_from` on the server has not acknowledged.
+    turns: VecDeque<Direction>,
+    turns_from: u64,
+    /// A turn was added since the last packet went out.
+    dirty: bool,
+    heard: Option<Instant>,
+    last_sent: Option<Instant>,
+    /// The server turned this player away.
+    pub full: bool,
+    /// The server shut down.
+    pub closed: bool,
+    buffer: Vec<u8>,
+}
+
+impl ServerLink {
+    pub fn connect(server: impl ToSocketAddrs, name: &PlayerName) -> io::Result<Self> {
+        let server = server
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no server address"))?;
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            server,
+            name: name.0.clone(),
+            player: None,
+            token: None,
+            welcomed: false,
+            snapshot: None,
+            reassembly: Reassembly::default(),
+            turns: VecDeque::new(),
+            turns_from: 0,
+            dirty: false,
+            heard: None,
+            last_sent: None,
+            full: false,
+            closed: false,
+            buffer: vec![0; MAX_PACKET],
+        })
+    }
+
+    /// Time since anything came from the server, `None` before it answered.
+    pub fn silence(&self) -> Option<Duration> {
+        self.heard.map(|heard| heard.elapsed())
+    }
+
+    /// Seated and hearing from the server.
+    pub fn is_connected(&self) -> bool {
+        self.welcomed && self.silence().is_some_and(|silence| silence < TIMEOUT)
+    }
+
+    /// Sends `turn` for this player's snake, once seated.
+    pub fn push_turn(&mut self, turn: Direction) {
+        if self.welcomed && self.turns.len() < MAX_PENDING {
+            self.turns.push_back(turn);
+            self.dirty = true;
+        }
+    }
+
+    /// Reads what the server sent and sends whatever it is still missing.
+    pub fn update(&mut self) {
+        self.receive();
+        if self.closed {
+            return;
+        }
+        if self.welcomed && !self.is_connected() {
+            // Ask for the seat again, the server may have seen a new address
+            self.welcomed = false;
+        }
+
+        let since_sent = self.last_sent.map(|sent| sent.elapsed());
+        let message = if !self.welcomed {
+            if since_sent.is_some_and(|since| since < HEARTBEAT) {
+                return;
+            }
+            ClientMessage::Hello {
+                name: self.name.clone(),
+                token: self.token,
+            }
+        } else {
+            let interval = match self.turns.is_empty() {
+                true => HEARTBEAT,
+                false => RESEND_INTERVAL,
+            };
+            if !self.dirty && since_sent.is_some_and(|since| since < interval) {
+                return;
+            }
+            ClientMessage::Turns {
+                from: self.turns_from,
+                turns: self.turns.iter().copied().collect(),
+            }
+        };
+        send_message(&self.socket, self.server, &message);
+        self.last_sent = Some(Instant::now());
+        self.dirty = false;
+    }
+
+    fn receive(&mut self) {
+        while let Some((message, address)) = receive_message(&self.socket, &mut self.buffer) {
+            if address != self.server {
+                continue;
+            }
+            self.heard = Some(Instant::now());
+            match message {
+                ServerMessage::Welcome { player, token, ack } if !self.welcomed => {
+                    self.player = Some(player);
+                    self.token = Some(token);
+                    self.welcomed = true;
+                    self.full = false;
+                    // Turns from before are stale by now, and a server that
+                    // started over counts its snapshots from the start again
+                    self.turns.clear();
+                    self.snapshot = None;
+                    self.reassembly = Reassembly::default();
+                    self.turns_from = ack;
+                }
+                ServerMessage::Full if !self.welcomed => self.full = true,
+                ServerMessage::Snapshot(chunk) => {
+                    let Some(snapshot) = self.reassembly.add::<Snapshot>(chunk) else {
+                        continue;
+                    };
+                    if self
+                        .snapshot
+                        .as_ref()
+                        .is_some_and(|newest| newest.seq >= snapshot.seq)
+                    {
+                        continue;
+                    }
+                    if self.welcomed {
+                        let acked = snapshot.ack.saturating_sub(self.turns_from);
+                        let acked = acked.min(self.turns.len() as u64);
+                        self.turns.drain(..acked as usize);
+                        self.turns_from += acked;
+                    }
+                    self.snapshot = Some(snapshot);
+                }
+                ServerMessage::Bye => self.closed = true,
+                _ => {}
+            }
+        }
+    }
+
+    /// Tells the server the seat is free.
+    pub fn leave(&self) {
+        send_message(&self.socket, self.server, &ClientMessage::Bye);
+    }
+}
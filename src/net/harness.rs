@@ -0,0 +1,315 @@
+//! Headless networked matches between computer players, to test the netcode
+//! without anyone at the keyboard. Both peers can live in one process over an
+//! in-memory link, or in two processes talking UDP. Either way the checksums
+//! after every confirmed tick show whether the peers stayed in sync. Run it
+//! with `snake-sim lockstep` or `snake-sim rollback`.
+
+use crate::{
+    ai::ai::{AiController, Difficulty},
+    net::{
+        lockstep::{LockstepMatch, LockstepSession, MatchSetup},
+        rollback::RollbackSession,
+        transport::{LinkConditioner, LinkConditions, MemoryTransport, Transport, UdpTransport},
+        turns::LinkStats,
+    },
+    sim::sim::Sim,
+};
+use bevy::prelude::*;
+use std::time::{Duration, Instant};
+
+/// How long a finished peer keeps resending so the other one can finish too.
+const LINGER: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Netcode {
+    Lockstep,
+    Rollback { max_rollback: u64 },
+}
+
+#[derive(Clone, Debug)]
+pub enum NetLink {
+    /// Both peers in this process over an in-memory link.
+    Loopback,
+    /// This process is one peer, the other is at `peer`.
+    Udp {
+        bind: String,
+        peer: String,
+        player: usize,
+    },
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct NetTestConfig {
+    pub setup: MatchSetup,
+    pub netcode: Netcode,
+    /// Who picks the turns on each side.
+    pub difficulty: Difficulty,
+    pub input_delay: u64,
+    pub conditions: LinkConditions,
+    /// Time per tick, zero to play as fast as the link allows.
+    pub tick_time: Duration,
+    /// Ticks after which the match is called off.
+    pub max_ticks: u64,
+    pub link: NetLink,
+}
+
+enum NetGame {
+    Lockstep(LockstepMatch),
+    Rollback(RollbackSession),
+}
+
+/// One side of a headless run with its computer player.
+struct Peer {
+    game: NetGame,
+    controller: AiController,
+    /// Ticks played, guesses that were played again not counted.
+    played: u64,
+}
+
+impl Peer {
+    fn new(config: &NetTestConfig, transport: impl Transport, player: usize) -> Self {
+        let game = match config.netcode {
+            Netcode::Lockstep => NetGame::Lockstep(LockstepMatch::new(
+                &config.setup,
+                LockstepSession::new(transport, player, config.input_delay),
+            )),
+            Netcode::Rollback { max_rollback } => NetGame::Rollback(RollbackSession::new(
+                &config.setup,
+                transport,
+                player,
+                config.input_delay,
+                max_rollback,
+            )),
+        };
+        Self {
+            game,
+            controller: AiController {
+                difficulty: config.difficulty,
+            },
+            played: 0,
+        }
+    }
+
+    fn finished(&self, config: &NetTestConfig) -> bool {
+        match &self.game {
+            NetGame::Lockstep(game) => game.is_over() || game.session.tick() >= config.max_ticks,
+            NetGame::Rollback(game) => {
+                game.confirmed() == game.tick()
+                    && (game.is_over() || game.tick() >= config.max_ticks)
+            }
+        }
+    }
+
+    /// Plays the next tick if there is one to play.
+    fn advance(&mut self, config: &NetTestConfig) -> bool {
+        let advanced = match &mut self.game {
+            NetGame::Lockstep(game) => game.advance(&mut self.controller),
+            NetGame::Rollback(game) if game.tick() < config.max_ticks => {
+                game.advance(&mut self.controller)
+            }
+            NetGame::Rollback(game) => {
+                game.update();
+                false
+            }
+        };
+        self.played += advanced as u64;
+        advanced
+    }
+
+    /// Only talks to the peer.
+    fn update(&mut self) {
+        match &mut self.game {
+            NetGame::Lockstep(game) => game.session.update(),
+            NetGame::Rollback(game) => game.update(),
+        }
+    }
+
+    fn delivered(&self) -> bool {
+        match &self.game {
+            NetGame::Lockstep(game) => game.session.delivered(),
+            NetGame::Rollback(game) => game.delivered(),
+        }
+    }
+
+    fn player(&self) -> usize {
+        match &self.game {
+            NetGame::Lockstep(game) => game.session.player,
+            NetGame::Rollback(game) => game.player,
+        }
+    }
+
+    fn stats(&self) -> LinkStats {
+        match &self.game {
+            NetGame::Lockstep(game) => game.session.turns.stats,
+            NetGame::Rollback(game) => game.turns.stats,
+        }
+    }
+
+    fn sim(&self) -> &Sim {
+        match &self.game {
+            NetGame::Lockstep(game) => &game.sim,
+            NetGame::Rollback(game) => &game.sim,
+        }
+    }
+
+    fn checksums(&self) -> &[u64] {
+        match &self.game {
+            NetGame::Lockstep(game) => &game.checksums,
+            NetGame::Rollback(game) => &game.checksums,
+        }
+    }
+}
+
+#[derive(Resource)]
+struct NetTestRun {
+    peers: Vec<Peer>,
+    started: Instant,
+    /// When this side finished, it keeps answering the peer for a moment.
+    finished: Option<Instant>,
+}
+
+/// Plays the [`NetTestConfig`] inserted into the app, prints checksums along
+/// the way and exits at the end.
+pub struct NetTestPlugin;
+
+impl Plugin for NetTestPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, start_net_test)
+            .add_systems(Update, run_net_test);
+    }
+}
+
+fn start_net_test(
+    mut commands: Commands,
+    config: Res<NetTestConfig>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let seed = config.setup.seed;
+    let peers = match &config.link {
+        NetLink::Loopback => {
+            let (a, b) = MemoryTransport::pair();
+            vec![
+                Peer::new(&config, LinkConditioner::new(a, config.conditions, seed), 0),
+                Peer::new(
+                    &config,
+                    LinkConditioner::new(b, config.conditions, !seed),
+                    1,
+                ),
+            ]
+        }
+        NetLink::Udp { bind, peer, player } => {
+            match UdpTransport::bind(bind.as_str(), peer.as_str()) {
+                Ok(udp) => {
+                    eprintln!("Player {player} on {bind}, waiting for {peer}");
+                    let transport =
+                        LinkConditioner::new(udp, config.conditions, seed ^ *player as u64);
+                    vec![Peer::new(&config, transport, *player)]
+                }
+                Err(err) => {
+                    eprintln!("Could not open {bind}: {err}");
+                    exit.write(AppExit::error());
+                    return;
+                }
+            }
+        }
+    };
+    commands.insert_resource(NetTestRun {
+        peers,
+        started: Instant::now(),
+        finished: None,
+    });
+}
+
+fn run_net_test(
+    config: Res<NetTestConfig>,
+    run: Option<ResMut<NetTestRun>>,
+    mut exit: EventWriter<AppExit>,
+    mut done: Local<bool>,
+) {
+    let Some(mut run) = run else {
+        return;
+    };
+    if *done {
+        return;
+    }
+    if let Some(finished) = run.finished {
+        // Keep answering until the peer has caught up
+        for peer in &mut run.peers {
+            peer.update();
+        }
+        let delivered = run.peers.iter().all(Peer::delivered);
+        if delivered || finished.elapsed() >= LINGER {
+            *done = true;
+            exit.write(report(&run));
+        }
+        return;
+    }
+
+    // Play every tick that is due, without a tick time as many as the link
+    // allows. A peer that is done still answers, the other one may be
+    // missing its last turns
+    let due = match config.tick_time.is_zero() {
+        true => u64::MAX,
+        false => (run.started.elapsed().as_secs_f64() / config.tick_time.as_secs_f64()) as u64,
+    };
+    for peer in &mut run.peers {
+        if peer.finished(&config) || peer.played >= due {
+            peer.update();
+            continue;
+        }
+        while !peer.finished(&config) && peer.played < due && peer.advance(&config) {}
+    }
+    if run.peers.iter().all(|peer| peer.finished(&config)) {
+        run.finished = Some(Instant::now());
+    }
+}
+
+/// Prints how the run went, the outcome says whether the peers agreed.
+fn report(run: &NetTestRun) -> AppExit {
+    let first = &run.peers[0];
+    let checksums = first.checksums();
+    for (tick, checksum) in checksums.iter().enumerate() {
+        let tick = tick + 1;
+        if tick % 100 == 0 || tick == checksums.len() {
+            println!("tick {tick} checksum {checksum:016x}");
+        }
+    }
+    let sim = first.sim();
+    let scores: Vec<String> = sim.snakes.iter().map(|s| s.score.to_string()).collect();
+    println!("scores {}", scores.join(" "));
+    for peer in &run.peers {
+        let stats = peer.stats();
+        eprintln!(
+            "player {}: {} packets sent, {} received, {} stale, {} stalls, {} rollbacks",
+            peer.player(),
+            stats.sent,
+            stats.received,
+            stats.stale,
+            stats.stalls,
+            stats.rollbacks
+        );
+    }
+    eprintln!(
+        "{} ticks in {:.1}s",
+        checksums.len(),
+        run.started.elapsed().as_secs_f32()
+    );
+
+    if let [a, b] = &run.peers[..] {
+        let desync = a
+            .checksums()
+            .iter()
+            .zip(b.checksums())
+            .position(|(a, b)| a != b);
+        if let Some(tick) = desync {
+            eprintln!("Desync at tick {}", tick + 1);
+            return AppExit::error();
+        }
+        if a.checksums().len() != b.checksums().len() {
+            eprintln!("The peers stopped at different ticks");
+            return AppExit::error();
+        }
+        eprintln!("Both peers agree on every tick");
+    }
+    AppExit::Success
+}
@@ -0,0 +1,546 @@
+//! Setting up an online match on the local network. A host opens a lobby on
+//! [`LOBBY_PORT`], everyone else finds it by broadcasting a probe there, and
+//! the first to join takes the second seat. Once both players are ready the
+//! host counts down. When the count runs out the host sends the match with its
+//! seed, the guest starts on that and acknowledges it, and the host starts
+//! once it hears back. Both sides then carry on with a lockstep match over the
+//! same sockets.
+//!
+//! Every message is one JSON datagram and is sent again a few times a second
+//! while it matters, so nothing breaks when one goes missing.
+
+use crate::{
+    board::{board::Board, generator::BoardStyle},
+    mode::mode::GameMode,
+    net::{
+        lockstep::MatchSetup,
+        transport::{MAX_PACKET, UdpTransport, receive_message, send_message},
+    },
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    io,
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    time::{Duration, Instant},
+};
+
+/// Where hosts listen for probes and guests.
+pub const LOBBY_PORT: u16 = 47474;
+
+/// How long the countdown before a match runs.
+pub const COUNTDOWN: Duration = Duration::from_secs(3);
+
+/// How often the lobby state goes out again.
+const HEARTBEAT: Duration = Duration::from_millis(200);
+
+/// How often a browsing player looks for hosts.
+const PROBE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Silence after which the other side counts as gone.
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Modes the host can pick from. Zen is left out, with no way to die a match
+/// there would never end.
+pub const ONLINE_MODES: [GameMode; 4] = [
+    GameMode::Versus,
+    GameMode::Classic,
+    GameMode::WrapAround,
+    GameMode::TimeAttack,
+];
+
+/// Board sizes the host can pick from, by name.
+pub const BOARD_SIZES: [(&str, Board); 3] = [
+    (
+        "SMALL",
+        Board {
+            half_extent: IVec2::new(7, 5),
+        },
+    ),
+    (
+        "MEDIUM",
+        Board {
+            half_extent: IVec2::new(11, 8),
+        },
+    ),
+    (
+        "LARGE",
+        Board {
+            half_extent: IVec2::new(15, 11),
+        },
+    ),
+];
+
+/// What others see this player as, from `--name` or the login name.
+#[derive(Resource, Clone, PartialEq, Eq, Debug)]
+pub struct PlayerName(pub String);
+
+impl PlayerName {
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let given = args.into_iter().skip_while(|arg| arg != "--name").nth(1);
+        let name = given
+            .or_else(|| std::env::var("USER").ok())
+            .or_else(|| std::env::var("USERNAME").ok())
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| "Player".to_string());
+        // Long enough to tell players apart, short enough for one packet
+        Self(name.trim().chars().take(16).collect())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Seat {
+    pub name: String,
+    pub ready: bool,
+}
+
+/// The lobby as the host sees it, shared with the guest.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct LobbyView {
+    /// Host first, then the guest once there is one.
+    pub seats: Vec<Seat>,
+    pub setup: MatchSetup,
+    /// Time left on the countdown, `None` while it is not running.
+    pub countdown_ms: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum LobbyMessage {
+    /// Anyone hosting, please answer.
+    Probe,
+    /// Answer to a probe, `open` while the second seat is free.
+    Announce {
+        name: String,
+        open: bool,
+    },
+    /// Sent by the guest over and over while in the lobby.
+    Join {
+        name: String,
+        ready: bool,
+    },
+    View(LobbyView),
+    /// Sent by the host over and over once the countdown is over, until the
+    /// guest acknowledges it. The only message the guest starts on.
+    Start {
+        setup: MatchSetup,
+        host: String,
+    },
+    StartAck,
+    Leave,
+}
+
+fn open_socket(port: u16) -> io::Result<UdpSocket> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port))?;
+    socket.set_nonblocking(true)?;
+    socket.set_broadcast(true)?;
+    Ok(socket)
+}
+
+/// Everything needed to start the match once the countdown is over.
+pub struct LobbyStart {
+    pub transport: UdpTransport,
+    /// Snake index of this side, the host plays the first snake.
+    pub player: usize,
+    pub setup: MatchSetup,
+    /// Names by snake index.
+    pub names: [String; 2],
+}
+
+#[derive(Clone, Debug)]
+pub struct FoundHost {
+    pub address: SocketAddr,
+    pub name: String,
+    pub open: bool,
+    seen: Instant,
+}
+
+/// Looks for hosts on the local network.
+pub struct Discovery {
+    socket: UdpSocket,
+    hosts: Vec<FoundHost>,
+    last_probe: Option<Instant>,
+    buffer: Vec<u8>,
+}
+
+impl Discovery {
+    pub fn new() -> io::Result<Self> {
+        Ok(Self {
+            socket: open_socket(0)?,
+            hosts: Vec::new(),
+            last_probe: None,
+            buffer: vec![0; MAX_PACKET],
+        })
+    }
+
+    /// Hosts that answered lately, in the order they were found.
+    pub fn hosts(&self) -> &[FoundHost] {
+        &self.hosts
+    }
+
+    pub fn update(&mut self) {
+        if self
+            .last_probe
+            .is_none_or(|probe| probe.elapsed() >= PROBE_INTERVAL)
+        {
+            // Broadcasts do not come back over loopback everywhere, so a host
+            // on this machine gets asked directly as well
+            for ip in [Ipv4Addr::BROADCAST, Ipv4Addr::LOCALHOST] {
+                send_message(&self.socket, (ip, LOBBY_PORT).into(), &LobbyMessage::Probe);
+            }
+            self.last_probe = Some(Instant::now());
+        }
+
+        while let Some((message, address)) = receive_message(&self.socket, &mut self.buffer) {
+            let LobbyMessage::Announce { name, open } = message else {
+                continue;
+            };
+            let found = FoundHost {
+                address,
+                name,
+                open,
+                seen: Instant::now(),
+            };
+            match self.hosts.iter_mut().find(|host| host.address == address) {
+                Some(host) => *host = found,
+                None => self.hosts.push(found),
+            }
+        }
+        self.hosts.retain(|host| host.seen.elapsed() < TIMEOUT);
+    }
+}
+
+struct Guest {
+    address: SocketAddr,
+    seat: Seat,
+    seen: Instant,
+}
+
+/// This player's own lobby, with room for one guest.
+pub struct HostLobby {
+    socket: UdpSocket,
+    pub seat: Seat,
+    pub setup: MatchSetup,
+    guest: Option<Guest>,
+    countdown: Option<Instant>,
+    last_view: Option<Instant>,
+    /// The guest has started the match.
+    acked: bool,
+    buffer: Vec<u8>,
+}
+
+impl HostLobby {
+    /// Fails when the port is taken, by another host on this machine for one.
+    pub fn new(name: &PlayerName, setup: MatchSetup) -> io::Result<Self> {
+        Self::bind(name, setup, LOBBY_PORT)
+    }
+
+    fn bind(name: &PlayerName, setup: MatchSetup, port: u16) -> io::Result<Self> {
+        Ok(Self {
+            socket: open_socket(port)?,
+            seat: Seat {
+                name: name.0.clone(),
+                ready: false,
+            },
+            setup,
+            guest: None,
+            countdown: None,
+            last_view: None,
+            acked: false,
+            buffer: vec![0; MAX_PACKET],
+        })
+    }
+
+    pub fn view(&self) -> LobbyView {
+        let mut seats = vec![self.seat.clone()];
+        seats.extend(self.guest.as_ref().map(|guest| guest.seat.clone()));
+        LobbyView {
+            seats,
+            setup: self.setup,
+            countdown_ms: self.countdown_left().map(|left| left.as_millis() as u64),
+        }
+    }
+
+    pub fn countdown_left(&self) -> Option<Duration> {
+        self.countdown
+            .map(|end| end.saturating_duration_since(Instant::now()))
+    }
+
+    /// The countdown is over and the guest is being told to start.
+    fn starting(&self) -> bool {
+        self.guest.is_some() && self.countdown_left().is_some_and(|left| left.is_zero())
+    }
+
+    /// Changes the match, which calls off a running countdown. Too late once
+    /// the countdown is over.
+    pub fn set_setup(&mut self, setup: MatchSetup) {
+        if self.starting() {
+            return;
+        }
+        self.setup = setup;
+        self.countdown = None;
+        self.last_view = None;
+    }
+
+    pub fn set_ready(&mut self, ready: bool) {
+        if self.starting() {
+            return;
+        }
+        self.seat.ready = ready;
+        self.last_view = None;
+    }
+
+    pub fn update(&mut self) {
+        while let Ok((len, address)) = self.socket.recv_from(&mut self.buffer) {
+            let is_guest = self
+                .guest
+                .as_ref()
+                .is_some_and(|guest| guest.address == address);
+            let Ok(message) = serde_json::from_slice(&self.buffer[..len]) else {
+                // A guest that started sends turns, so its ack was lost
+                if is_guest && self.starting() {
+                    self.acked = true;
+                }
+                continue;
+            };
+            match message {
+                LobbyMessage::Probe => send_message(
+                    &self.socket,
+                    address,
+                    &LobbyMessage::Announce {
+                        name: self.seat.name.clone(),
+                        open: self.guest.is_none(),
+                    },
+                ),
+                LobbyMessage::Join { name, ready } if is_guest || self.guest.is_none() => {
+                    let seat = Seat { name, ready };
+                    if self.guest.as_ref().is_none_or(|guest| guest.seat != seat) {
+                        self.last_view = None;
+                    }
+                    self.guest = Some(Guest {
+                        address,
+                        seat,
+                        seen: Instant::now(),
+                    });
+                }
+                LobbyMessage::StartAck if is_guest && self.starting() => self.acked = true,
+                LobbyMessage::Leave if is_guest => self.guest = None,
+                _ => {}
+            }
+        }
+        if self
+            .guest
+            .as_ref()
+            .is_some_and(|guest| guest.seen.elapsed() >= TIMEOUT)
+        {
+            self.guest = None;
+        }
+
+        // The countdown runs while both are ready, and starts over otherwise.
+        // Once it is over there is no way back.
+        let ready = self.starting()
+            || self.seat.ready && self.guest.as_ref().is_some_and(|guest| guest.seat.ready);
+        match (ready, self.countdown) {
+            (true, None) => {
+                self.countdown = Some(Instant::now() + COUNTDOWN);
+                self.setup.seed = rand::random();
+                self.last_view = None;
+            }
+            (false, Some(_)) => {
+                self.countdown = None;
+                self.last_view = None;
+            }
+            _ => {}
+        }
+
+        if let Some(guest) = &self.guest
+            && self
+                .last_view
+                .is_none_or(|sent| sent.elapsed() >= HEARTBEAT)
+        {
+            let message = match self.starting() {
+                true => LobbyMessage::Start {
+                    setup: self.setup,
+                    host: self.seat.name.clone(),
+                },
+                false => LobbyMessage::View(self.view()),
+            };
+            send_message(&self.socket, guest.address, &message);
+            self.last_view = Some(Instant::now());
+        }
+    }
+
+    /// The match, once the guest has acknowledged the start.
+    pub fn start(&self) -> Option<LobbyStart> {
+        let guest = self.guest.as_ref()?;
+        if !(self.starting() && self.acked) {
+            return None;
+        }
+        let socket = self.socket.try_clone().ok()?;
+        Some(LobbyStart {
+            transport: UdpTransport::from_socket(socket, guest.address),
+            player: 0,
+            setup: self.setup,
+            names: [self.seat.name.clone(), guest.seat.name.clone()],
+        })
+    }
+
+    /// Tells the guest the lobby is closing.
+    pub fn leave(&self) {
+        if let Some(guest) = &self.guest {
+            send_message(&self.socket, guest.address, &LobbyMessage::Leave);
+        }
+    }
+}
+
+/// A seat in someone else's lobby.
+pub struct GuestLobby {
+    socket: UdpSocket,
+    pub host: SocketAddr,
+    pub seat: Seat,
+    /// The lobby as the host last sent it.
+    pub view: Option<LobbyView>,
+    countdown: Option<Instant>,
+    /// The match and the host's name, once the host said to start.
+    started: Option<(MatchSetup, String)>,
+    heard: Instant,
+    closed: bool,
+    last_join: Option<Instant>,
+    buffer: Vec<u8>,
+}
+
+impl GuestLobby {
+    pub fn new(name: &PlayerName, host: SocketAddr) -> io::Result<Self> {
+        Ok(Self {
+            socket: open_socket(0)?,
+            host,
+            seat: Seat {
+                name: name.0.clone(),
+                ready: false,
+            },
+            view: None,
+            countdown: None,
+            started: None,
+            heard: Instant::now(),
+            closed: false,
+            last_join: None,
+            buffer: vec![0; MAX_PACKET],
+        })
+    }
+
+    pub fn set_ready(&mut self, ready: bool) {
+        self.seat.ready = ready;
+        self.last_join = None;
+    }
+
+    pub fn countdown_left(&self) -> Option<Duration> {
+        self.countdown
+            .map(|end| end.saturating_duration_since(Instant::now()))
+    }
+
+    /// The host left, went quiet or never answered.
+    pub fn is_lost(&self) -> bool {
+        self.closed || self.heard.elapsed() >= TIMEOUT
+    }
+
+    pub fn update(&mut self) {
+        if self
+            .last_join
+            .is_none_or(|sent| sent.elapsed() >= HEARTBEAT)
+        {
+            let join = LobbyMessage::Join {
+                name: self.seat.name.clone(),
+                ready: self.seat.ready,
+            };
+            send_message(&self.socket, self.host, &join);
+            self.last_join = Some(Instant::now());
+        }
+
+        while let Some((message, address)) = receive_message(&self.socket, &mut self.buffer) {
+            if address != self.host {
+                continue;
+            }
+            self.heard = Instant::now();
+            match message {
+                LobbyMessage::View(view) => {
+                    // The host's count is the one that counts, the trip here
+                    // is short enough on a local network to leave out
+                    self.countdown = view
+                        .countdown_ms
+                        .map(|left| Instant::now() + Duration::from_millis(left));
+                    self.view = Some(view);
+                }
+                LobbyMessage::Start { setup, host } => {
+                    send_message(&self.socket, self.host, &LobbyMessage::StartAck);
+                    self.started = Some((setup, host));
+                }
+                LobbyMessage::Leave => self.closed = true,
+                _ => {}
+            }
+        }
+    }
+
+    /// The match, once the host said to start. The countdown in the view
+    /// only shows how long that takes.
+    pub fn start(&self) -> Option<LobbyStart> {
+        let (setup, host) = self.started.as_ref()?;
+        let socket = self.socket.try_clone().ok()?;
+        Some(LobbyStart {
+            transport: UdpTransport::from_socket(socket, self.host),
+            player: 1,
+            setup: *setup,
+            names: [host.clone(), self.seat.name.clone()],
+        })
+    }
+
+    pub fn leave(&self) {
+        send_message(&self.socket, self.host, &LobbyMessage::Leave);
+    }
+}
+
+/// Default match a new lobby offers.
+pub fn default_setup() -> MatchSetup {
+    MatchSetup {
+        seed: 0,
+        mode: GameMode::Versus,
+        style: BoardStyle::Open,
+        board: BOARD_SIZES[1].1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn host_and_guest_start_the_same_match() {
+        let mut host = HostLobby::bind(&PlayerName("host".into()), default_setup(), 0).unwrap();
+        let port = host.socket.local_addr().unwrap().port();
+        let address = (Ipv4Addr::LOCALHOST, port).into();
+        let mut guest = GuestLobby::new(&PlayerName("guest".into()), address).unwrap();
+        host.set_ready(true);
+        guest.set_ready(true);
+
+        let deadline = Instant::now() + COUNTDOWN + Duration::from_secs(5);
+        let (mut host_start, mut guest_start) = (None, None);
+        while host_start.is_none() || guest_start.is_none() {
+            assert!(Instant::now() < deadline, "the match never started");
+            host.update();
+            guest.update();
+            // Neither side may start before the count is over
+            if host.countdown_left().is_none_or(|left| !left.is_zero()) {
+                assert!(guest.start().is_none());
+            }
+            host_start = host_start.or_else(|| host.start());
+            guest_start = guest_start.or_else(|| guest.start());
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        let (host_start, guest_start) = (host_start.unwrap(), guest_start.unwrap());
+        assert_eq!((host_start.player, guest_start.player), (0, 1));
+        assert_eq!(host_start.setup, guest_start.setup);
+        assert_eq!(host_start.setup, host.setup);
+        assert_eq!(host_start.names, ["host", "guest"]);
+        assert_eq!(guest_start.names, host_start.names);
+    }
+}
@@ -0,0 +1,8 @@
+pub mod client;
+pub mod harness;
+pub mod lobby;
+pub mod lockstep;
+pub mod rollback;
+pub mod server;
+pub mod transport;
+pub mod turns;
@@ -0,0 +1,300 @@
+//! Online versus with rollback. Unlike lockstep the game never waits for the
+//! peer: a turn that has not come in yet is guessed to be no turn at all, the
+//! snake keeps going the way it last went. The game is saved before every
+//! tick, and when the real turn turns out to be different the game goes back
+//! to that tick and plays forward again with it.
+//!
+//! Only ticks played with the peer's real turn count as confirmed. Those are
+//! the same on both peers, their checksums are kept to prove it.
+
+use crate::{
+    net::{lockstep::MatchSetup, transport::Transport, turns::TurnExchange},
+    player::{
+        controller::{RemoteController, RemoteInbox, SnakeController},
+        player::Direction,
+    },
+    sim::sim::{Sim, SimState},
+};
+use std::collections::VecDeque;
+
+/// Ticks the game may run ahead of the peer's last known turn.
+pub const DEFAULT_MAX_ROLLBACK: u64 = 8;
+
+pub struct RollbackSession {
+    pub turns: TurnExchange,
+    /// Snake index of this peer, the other one plays the other snake.
+    pub player: usize,
+    pub input_delay: u64,
+    /// How far ahead of the peer the game may guess before it waits.
+    pub max_rollback: u64,
+    /// The game as far as it was played, guesses included.
+    pub sim: Sim,
+    /// Checksum of the game after every confirmed tick, to compare with the
+    /// peer.
+    pub checksums: Vec<u64>,
+    inboxes: [RemoteInbox; 2],
+    /// Next tick to play.
+    tick: u64,
+    /// Every tick before this one was played with the peer's real turn.
+    confirmed: u64,
+    /// The game before every tick from `confirmed` on.
+    snapshots: VecDeque<SimState>,
+    /// Where the peer's snake headed after every tick from `confirmed` on,
+    /// with the checksum of the game after it.
+    played: VecDeque<(Direction, u64)>,
+}
+
+impl RollbackSession {
+    /// Session for snake `player` (0 or 1) of the match `setup` describes.
+    /// Both peers have to use the same input delay.
+    pub fn new(
+        setup: &MatchSetup,
+        transport: impl Transport,
+        player: usize,
+        input_delay: u64,
+        max_rollback: u64,
+    ) -> Self {
+        let (first, first_inbox) = RemoteController::new();
+        let (second, second_inbox) = RemoteController::new();
+        Self {
+            turns: TurnExchange::new(transport, input_delay),
+            player,
+            input_delay,
+            max_rollback: max_rollback.max(1),
+            sim: setup.sim(vec![Box::new(first), Box::new(second)]),
+            checksums: Vec::new(),
+            inboxes: [first_inbox, second_inbox],
+            tick: 0,
+            confirmed: 0,
+            snapshots: VecDeque::new(),
+            played: VecDeque::new(),
+        }
+    }
+
+    /// Next tick to play.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// First tick that was not played with the peer's real turn.
+    pub fn confirmed(&self) -> u64 {
+        self.confirmed
+    }
+
+    /// Whether the peer has every turn it needs to catch up with this side.
+    pub fn delivered(&self) -> bool {
+        self.turns.delivered(self.tick)
+    }
+
+    /// Over once at most one snake is left. Until every tick is confirmed
+    /// that may still be undone.
+    pub fn is_over(&self) -> bool {
+        self.sim.snakes.iter().filter(|snake| snake.alive()).count() <= 1
+    }
+
+    /// Over for good, with every tick confirmed.
+    pub fn is_settled(&self) -> bool {
+        self.confirmed == self.tick && self.is_over()
+    }
+
+    /// Talks to the peer, puts right any tick played with a wrong guess and
+    /// plays the next tick. The local turn is asked from `local` whenever a
+    /// new one is due. Returns whether a tick was played, which only fails
+    /// once the game is over or too far ahead of the peer.
+    pub fn advance(&mut self, local: &mut dyn SnakeController) -> bool {
+        self.update();
+        if self.is_over() {
+            return false;
+        }
+        if self.tick >= self.confirmed + self.max_rollback {
+            self.turns.stats.stalls += 1;
+            return false;
+        }
+
+        let player = self.player;
+        if self.turns.local_end() <= self.tick + self.input_delay {
+            let turn = match self.sim.snakes[player].alive() {
+                true => local.decide(&self.sim.view(player)),
+                false => None,
+            };
+            self.turns.push_local(turn);
+            self.turns.update();
+        }
+        self.play(self.tick);
+        self.tick += 1;
+        true
+    }
+
+    /// Reads what the peer sent, sends whatever it is still missing and
+    /// confirms every tick its turns are in for.
+    pub fn update(&mut self) {
+        self.turns.update();
+        let remote = 1 - self.player;
+        while self.confirmed < self.tick
+            && let Some(turn) = self.turns.remote(self.confirmed)
+        {
+            let before = &self.snapshots[0].snakes()[remote];
+            if before.alive() && before.turned(turn) != self.played[0].0 {
+                self.rollback();
+                continue;
+            }
+            self.snapshots.pop_front();
+            let (_, checksum) = self.played.pop_front().unwrap();
+            self.checksums.push(checksum);
+            self.confirmed += 1;
+        }
+        self.turns.forget(self.confirmed);
+    }
+
+    /// Goes back to the first unconfirmed tick and plays up to where the game
+    /// was, with every turn known by now.
+    fn rollback(&mut self) {
+        self.turns.stats.rollbacks += 1;
+        let state = self.snapshots.pop_front().unwrap();
+        self.sim.load(&state);
+        self.snapshots.clear();
+        self.played.clear();
+
+        let end = self.tick;
+        self.tick = self.confirmed;
+        while self.tick < end && !self.is_over() {
+            self.play(self.tick);
+            self.tick += 1;
+        }
+    }
+
+    /// Plays `tick` with the own turn and the peer's turn or the guess for it.
+    fn play(&mut self, tick: u64) {
+        let local = self.turns.local(tick).flatten();
+        let remote = self.turns.remote(tick).flatten();
+        let mut turns = [remote; 2];
+        turns[self.player] = local;
+
+        self.snapshots.push_back(self.sim.save());
+        for ((inbox, turn), snake) in self.inboxes.iter().zip(turns).zip(&self.sim.snakes) {
+            // Dead snakes never pick up their turns, so they get none
+            if let Some(turn) = turn
+                && snake.alive()
+            {
+                inbox.push(turn);
+            }
+        }
+        self.sim.step();
+        let heading = self.sim.snakes[1 - self.player].direction;
+        self.played.push_back((heading, self.sim.checksum()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        board::{board::Board, generator::BoardStyle},
+        mode::mode::GameMode,
+        net::{
+            lockstep::{DEFAULT_INPUT_DELAY, LockstepMatch, LockstepSession},
+            transport::{LinkConditioner, LinkConditions, MemoryTransport},
+        },
+        player::controller::ReplayController,
+    };
+    use bevy::prelude::*;
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+    use std::time::{Duration, Instant};
+
+    const TICKS: usize = 200;
+
+    /// Nobody dies in zen, so both netcodes play every tick.
+    const SETUP: MatchSetup = MatchSetup {
+        seed: 3,
+        mode: GameMode::Zen,
+        style: BoardStyle::Open,
+        board: Board {
+            half_extent: IVec2::new(15, 11),
+        },
+    };
+
+    const CONDITIONS: LinkConditions = LinkConditions {
+        loss: 0.2,
+        latency: Duration::from_millis(5),
+        jitter: Duration::from_millis(10),
+    };
+
+    /// The same made up turns for a player whichever netcode plays them.
+    fn script(player: usize) -> ReplayController {
+        let mut rng = StdRng::seed_from_u64(player as u64);
+        let directions = [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ];
+        ReplayController::new((0..TICKS * 2).map(|_| {
+            rng.random_bool(0.3)
+                .then(|| directions[rng.random_range(0..4)])
+        }))
+    }
+
+    /// Both ends of a bad link, each with the player on it.
+    fn lossy_link() -> [(usize, LinkConditioner<MemoryTransport>); 2] {
+        let (a, b) = MemoryTransport::pair();
+        [
+            (0, LinkConditioner::new(a, CONDITIONS, 0)),
+            (1, LinkConditioner::new(b, CONDITIONS, 1)),
+        ]
+    }
+
+    fn lockstep_checksums() -> Vec<u64> {
+        let mut matches = lossy_link().map(|(player, transport)| {
+            LockstepMatch::new(
+                &SETUP,
+                LockstepSession::new(transport, player, DEFAULT_INPUT_DELAY),
+            )
+        });
+        let mut scripts = [script(0), script(1)];
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while matches.iter().any(|game| game.checksums.len() < TICKS) {
+            assert!(Instant::now() < deadline, "the lockstep match stalled");
+            for (game, script) in matches.iter_mut().zip(&mut scripts) {
+                game.advance(script);
+            }
+        }
+        let [first, second] = matches;
+        assert_eq!(first.checksums[..TICKS], second.checksums[..TICKS]);
+        first.checksums[..TICKS].to_vec()
+    }
+
+    #[test]
+    fn rollback_confirms_what_lockstep_plays() {
+        let mut sessions = lossy_link().map(|(player, transport)| {
+            RollbackSession::new(
+                &SETUP,
+                transport,
+                player,
+                DEFAULT_INPUT_DELAY,
+                DEFAULT_MAX_ROLLBACK,
+            )
+        });
+        let mut scripts = [script(0), script(1)];
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while sessions
+            .iter()
+            .any(|session| session.checksums.len() < TICKS)
+        {
+            assert!(Instant::now() < deadline, "the rollback match stalled");
+            for (session, script) in sessions.iter_mut().zip(&mut scripts) {
+                session.advance(script);
+            }
+        }
+
+        let lockstep = lockstep_checksums();
+        for session in &sessions {
+            assert_eq!(session.checksums[..TICKS], lockstep[..]);
+        }
+        // Guesses really were wrong at times and put right
+        assert!(
+            sessions
+                .iter()
+                .any(|session| session.turns.stats.rollbacks > 0)
+        );
+    }
+}
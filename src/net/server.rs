@@ -0,0 +1,804 @@
+//! Matches on a dedicated server. Only the server runs the [`Sim`], players
+//! send it their turns and draw the snapshots that come back, so nobody plays
+//! a game of their own. Seats go out in the order players say hello, a round
+//! starts once every seat is taken and the next one follows a few seconds
+//! after it ends. Run it with `snake-server`.
+//!
+//! A player who drops out keeps the seat for [`RECONNECT_GRACE`] and gets it
+//! back with the token from the welcome, nothing else does. The round waits
+//! for them meanwhile.
+//!
+//! As in the lobby, every message is one JSON datagram, and anything that has
+//! to arrive is sent again until the other side shows it did. Snapshots of
+//! long snakes do not fit one packet and go out in chunks.
+
+use crate::{
+    net::{
+        lobby::COUNTDOWN,
+        lockstep::MatchSetup,
+        transport::{Chunk, MAX_CHUNKS, MAX_PACKET, receive_message, send_message, split_message},
+    },
+    player::{
+        controller::{RemoteController, RemoteInbox, SnakeController},
+        player::Direction,
+    },
+    sim::{rules::DeathCause, sim::Sim},
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    io,
+    net::{SocketAddr, UdpSocket},
+    time::{Duration, Instant},
+};
+
+/// Where servers listen unless told otherwise.
+pub const SERVER_PORT: u16 = 47475;
+
+/// How often a snapshot goes out while nothing moves, and a client speaks up
+/// while it has nothing to say.
+pub const HEARTBEAT: Duration = Duration::from_millis(200);
+
+/// Silence after which the other side counts as gone for now.
+pub const TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How long a seat is kept for a player who dropped out.
+pub const RECONNECT_GRACE: Duration = Duration::from_secs(30);
+
+/// Longest side of a board a server plays on. A snapshot of a board packed
+/// with snakes still fits into the chunks a client takes.
+pub const MAX_SERVER_SIDE: i32 = 127;
+
+/// Time between the end of a round and the start of the next one.
+const NEXT_ROUND_AFTER: Duration = Duration::from_secs(5);
+
+/// Turns a player can have waiting, one is played per tick.
+const MAX_QUEUED: usize = 2;
+
+/// Most turns a packet may carry, a client never has more unacknowledged.
+const MAX_TURNS_PER_PACKET: usize = 16;
+
+/// Long enough to tell players apart, short enough for the status line.
+const MAX_NAME: usize = 16;
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// Asks for a seat, sent until the welcome comes. `token` asks for the
+    /// seat it came with back.
+    Hello {
+        name: String,
+        token: Option<u64>,
+    },
+    /// Turns numbered on from `from`, sent until the server acknowledges
+    /// them. Sent empty now and then to stay connected.
+    Turns {
+        from: u64,
+        turns: Vec<Direction>,
+    },
+    Bye,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// Snake `player` is yours, turns are numbered on from `ack`.
+    Welcome { player: usize, token: u64, ack: u64 },
+    /// Every seat is taken.
+    Full,
+    /// Piece of a [`Snapshot`], with the snapshot's `seq` as its id.
+    Snapshot(Chunk),
+    /// The server is shutting down.
+    Bye,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RoundPhase {
+    /// Not every seat is taken yet.
+    Waiting,
+    Countdown {
+        ms: u64,
+    },
+    Playing,
+    /// Someone dropped out and the round waits for them.
+    Paused,
+    /// `winner` by snake index, `None` for a draw.
+    Over {
+        winner: Option<usize>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct SnakeSnapshot {
+    /// Empty while the seat is free.
+    pub name: String,
+    /// Whether the player is there right now.
+    pub connected: bool,
+    pub body: VecDeque<IVec2>,
+    pub direction: Direction,
+    pub score: u32,
+    pub death: Option<DeathCause>,
+}
+
+/// The round as the server sees it, sent to every player.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Snapshot {
+    /// Counts up with every snapshot, older ones that turn up late are dropped.
+    pub seq: u64,
+    /// Counts up with every round, each has a board of its own.
+    pub round: u64,
+    pub setup: MatchSetup,
+    pub phase: RoundPhase,
+    pub tick: u64,
+    /// In snake order, which is seat order.
+    pub snakes: Vec<SnakeSnapshot>,
+    pub food: Vec<IVec2>,
+    pub time_left: Option<f32>,
+    /// Turns of the receiver before this one have arrived.
+    pub ack: u64,
+}
+
+impl Snapshot {
+    /// Whether this can be a snapshot of the round `sim` plays, with a snake
+    /// for every one in it and everything on the board. A server that sends
+    /// anything else is not listened to.
+    pub fn fits(&self, sim: &Sim) -> bool {
+        let board = sim.board();
+        let on_board = |cell: &IVec2| board.index(*cell).is_some();
+        let winner_known = match self.phase {
+            RoundPhase::Over {
+                winner: Some(winner),
+            } => winner < sim.snakes.len(),
+            _ => true,
+        };
+        self.snakes.len() == sim.snakes.len()
+            && winner_known
+            && self.food.iter().all(on_board)
+            && self
+                .snakes
+                .iter()
+                .all(|snake| !snake.body.is_empty() && snake.body.iter().all(on_board))
+    }
+
+    /// Puts the snakes and apples into `sim`, a copy of the round that is
+    /// only drawn and never stepped.
+    pub fn apply(&self, sim: &mut Sim) {
+        for (snake, shot) in sim.snakes.iter_mut().zip(&self.snakes) {
+            snake.body.clone_from(&shot.body);
+            snake.direction = shot.direction;
+            snake.score = shot.score;
+            snake.death = shot.death;
+        }
+        sim.food.clone_from(&self.food);
+        sim.ticks = self.tick;
+        sim.time_left = self.time_left;
+    }
+}
+
+/// The game of one round with a remote controller per seat, the server's
+/// copy and the one players draw alike.
+pub fn round_sim(setup: &MatchSetup) -> (Sim, Vec<RemoteInbox>) {
+    let mut controllers: Vec<Box<dyn SnakeController>> = Vec::new();
+    let mut inboxes = Vec::new();
+    for _ in 0..setup.mode.rules().players {
+        let (controller, inbox) = RemoteController::new();
+        controllers.push(Box::new(controller));
+        inboxes.push(inbox);
+    }
+    (setup.sim(controllers), inboxes)
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct ServerConfig {
+    pub bind: String,
+    /// Match of the first round, every later one counts the seed up.
+    pub setup: MatchSetup,
+    /// Time per tick.
+    pub tick_time: Duration,
+    /// Rounds before the server shuts down, `None` to go on for good.
+    pub rounds: Option<u64>,
+}
+
+/// A player holding a seat.
+struct Client {
+    name: String,
+    token: u64,
+    address: SocketAddr,
+    heard: Instant,
+    /// Turns before this one have been read.
+    ack: u64,
+    queued: VecDeque<Direction>,
+}
+
+impl Client {
+    fn connected(&self) -> bool {
+        self.heard.elapsed() < TIMEOUT
+    }
+}
+
+enum Phase {
+    Waiting,
+    Countdown(Instant),
+    Playing,
+    Over {
+        winner: Option<usize>,
+        until: Instant,
+    },
+    Closed,
+}
+
+#[derive(Resource)]
+pub struct GameServer {
+    socket: UdpSocket,
+    pub setup: MatchSetup,
+    tick_time: Duration,
+    rounds: Option<u64>,
+    /// One per snake, `None` while free.
+    seats: Vec<Option<Client>>,
+    sim: Sim,
+    inboxes: Vec<RemoteInbox>,
+    phase: Phase,
+    round: u64,
+    /// When the next tick is due while playing.
+    next_tick: Instant,
+    seq: u64,
+    last_snapshot: Option<Instant>,
+    buffer: Vec<u8>,
+}
+
+impl GameServer {
+    pub fn new(config: &ServerConfig) -> io::Result<Self> {
+        let socket = UdpSocket::bind(config.bind.as_str())?;
+        socket.set_nonblocking(true)?;
+        let (sim, inboxes) = round_sim(&config.setup);
+        Ok(Self {
+            socket,
+            setup: config.setup,
+            tick_time: config.tick_time,
+            rounds: config.rounds,
+            seats: (0..sim.snakes.len()).map(|_| None).collect(),
+            sim,
+            inboxes,
+            phase: Phase::Waiting,
+            round: 1,
+            next_tick: Instant::now(),
+            seq: 0,
+            last_snapshot: None,
+            buffer: vec![0; MAX_PACKET],
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Whether the last round is over and the players were told.
+    pub fn is_closed(&self) -> bool {
+        matches!(self.phase, Phase::Closed)
+    }
+
+    /// Reads what the players sent, plays the tick if one is due and tells
+    /// everyone how the round stands.
+    pub fn update(&mut self) {
+        if self.is_closed() {
+            return;
+        }
+        let mut changed = self.receive();
+        changed |= self.free_seats();
+        changed |= self.advance();
+        if self.is_closed() {
+            return;
+        }
+        if changed
+            || self
+                .last_snapshot
+                .is_none_or(|sent| sent.elapsed() >= HEARTBEAT)
+        {
+            self.send_snapshots();
+        }
+    }
+
+    /// Returns whether anything players see has changed.
+    fn receive(&mut self) -> bool {
+        let mut changed = false;
+        while let Some((message, address)) = receive_message(&self.socket, &mut self.buffer) {
+            let seat = self.seat_of(address);
+            if let Some(seat) = seat
+                && let Some(client) = &mut self.seats[seat]
+            {
+                changed |= !client.connected();
+                client.heard = Instant::now();
+            }
+            match (message, seat) {
+                (ClientMessage::Hello { name, token }, _) => {
+                    changed |= self.hello(address, &name, token);
+                }
+                (ClientMessage::Turns { from, turns }, Some(seat)) => {
+                    self.take_turns(seat, from, turns);
+                }
+                (ClientMessage::Bye, Some(seat)) => {
+                    if let Some(client) = self.seats[seat].take() {
+                        info!("{} left seat {seat}", client.name);
+                    }
+                    changed = true;
+                }
+                // Only players with a seat have anything else to say
+                _ => {}
+            }
+        }
+        changed
+    }
+
+    fn seat_of(&self, address: SocketAddr) -> Option<usize> {
+        self.seats.iter().position(|client| {
+            client
+                .as_ref()
+                .is_some_and(|client| client.address == address)
+        })
+    }
+
+    /// Seats `address`, or sends it away when there is no room. Returns
+    /// whether a seat changed hands.
+    fn hello(&mut self, address: SocketAddr, name: &str, token: Option<u64>) -> bool {
+        let name: String = name.trim().chars().take(MAX_NAME).collect();
+        let name = match name.is_empty() {
+            true => "Player".to_string(),
+            false => name,
+        };
+        let held = |check: &dyn Fn(&Client) -> bool| {
+            self.seats
+                .iter()
+                .position(|client| client.as_ref().is_some_and(check))
+        };
+        // A repeated hello means the welcome got lost, the token of someone
+        // who dropped out takes that seat back
+        let seat = self
+            .seat_of(address)
+            .or_else(|| token.and_then(|token| held(&|client| client.token == token)))
+            .or_else(|| self.seats.iter().position(Option::is_none));
+        let Some(seat) = seat else {
+            send_message(&self.socket, address, &ServerMessage::Full);
+            return false;
+        };
+
+        let mut changed = true;
+        let client = match &mut self.seats[seat] {
+            Some(client) if client.address == address => {
+                changed = false;
+                client
+            }
+            Some(client) => {
+                info!("{} is back on seat {seat}", client.name);
+                client.address = address;
+                client
+            }
+            free => {
+                info!("{name} took seat {seat} from {address}");
+                free.insert(Client {
+                    name,
+                    token: rand::random(),
+                    address,
+                    heard: Instant::now(),
+                    ack: 0,
+                    queued: VecDeque::new(),
+                })
+            }
+        };
+        client.heard = Instant::now();
+        let welcome = ServerMessage::Welcome {
+            player: seat,
+            token: client.token,
+            ack: client.ack,
+        };
+        send_message(&self.socket, address, &welcome);
+        changed
+    }
+
+    /// Queues the turns of `seat` that are new, in order and that the snake
+    /// could take. The rest are acknowledged all the same, so the client stops
+    /// sending them.
+    fn take_turns(&mut self, seat: usize, from: u64, turns: Vec<Direction>) {
+        if turns.len() > MAX_TURNS_PER_PACKET {
+            return;
+        }
+        let open = matches!(self.phase, Phase::Countdown(_) | Phase::Playing);
+        let snake = &self.sim.snakes[seat];
+        let Some(client) = &mut self.seats[seat] else {
+            return;
+        };
+        for (i, turn) in turns.into_iter().enumerate() {
+            // Numbers past the end are made up, the whole packet goes
+            let Some(number) = from.checked_add(i as u64) else {
+                return;
+            };
+            // Only the next turn in line counts, the rest are repeats
+            if number != client.ack {
+                continue;
+            }
+            client.ack += 1;
+            let last = client.queued.back().copied().unwrap_or(snake.direction);
+            let possible = snake.body.len() == 1 || turn != last.opposite();
+            if open && snake.alive() && client.queued.len() < MAX_QUEUED && turn != last && possible
+            {
+                client.queued.push_back(turn);
+            }
+        }
+    }
+
+    /// Gives up the seats of players gone for too long. Returns whether
+    /// there were any.
+    fn free_seats(&mut self) -> bool {
+        let mut freed = false;
+        for seat in &mut self.seats {
+            if let Some(client) = seat.take_if(|client| client.heard.elapsed() >= RECONNECT_GRACE) {
+                info!("{} is gone, the seat is free again", client.name);
+                freed = true;
+            }
+        }
+        freed
+    }
+
+    /// Whether every seat is taken by a player who is there.
+    fn full(&self) -> bool {
+        self.seats
+            .iter()
+            .all(|client| client.as_ref().is_some_and(Client::connected))
+    }
+
+    /// Whether a player who dropped out still holds a seat.
+    fn paused(&self) -> bool {
+        self.seats
+            .iter()
+            .any(|client| client.as_ref().is_some_and(|client| !client.connected()))
+    }
+
+    /// Moves the round along. Returns whether anything players see changed.
+    fn advance(&mut self) -> bool {
+        let now = Instant::now();
+        match self.phase {
+            Phase::Waiting if self.full() => {
+                self.phase = Phase::Countdown(now + COUNTDOWN);
+            }
+            Phase::Countdown(_) if !self.full() => self.phase = Phase::Waiting,
+            Phase::Countdown(start) if now >= start => {
+                info!("Round {} starts", self.round);
+                self.phase = Phase::Playing;
+                self.next_tick = now + self.tick_time;
+            }
+            Phase::Playing if self.paused() => {
+                // The clock starts over once everyone is back
+                self.next_tick = now + self.tick_time;
+                return false;
+            }
+            Phase::Playing if now >= self.next_tick => self.play(now),
+            Phase::Over { until, .. } if now >= until => self.next_round(),
+            _ => return false,
+        }
+        true
+    }
+
+    fn play(&mut self, now: Instant) {
+        // A server that fell behind does not rush to catch up
+        self.next_tick = (self.next_tick + self.tick_time).max(now);
+        for ((client, inbox), snake) in self
+            .seats
+            .iter_mut()
+            .zip(&self.inboxes)
+            .zip(&self.sim.snakes)
+        {
+            if let Some(turn) = client.as_mut().and_then(|client| client.queued.pop_front())
+                && snake.alive()
+            {
+                inbox.push(turn);
+            }
+        }
+        self.sim.step();
+
+        let alive = self.sim.snakes.iter().filter(|snake| snake.alive()).count();
+        // Alone on the board the round goes on until the snake is gone
+        if self.sim.is_over() || (self.sim.snakes.len() > 1 && alive <= 1) {
+            let winner = self.sim.leader();
+            match winner.and_then(|winner| self.seats[winner].as_ref()) {
+                Some(client) => info!("Round {} goes to {}", self.round, client.name),
+                None => info!("Round {} is a draw", self.round),
+            }
+            self.phase = Phase::Over {
+                winner,
+                until: now + NEXT_ROUND_AFTER,
+            };
+        }
+    }
+
+    fn next_round(&mut self) {
+        if self.rounds.is_some_and(|rounds| self.round >= rounds) {
+            info!("Played every round, shutting down");
+            for client in self.seats.iter().flatten() {
+                send_message(&self.socket, client.address, &ServerMessage::Bye);
+            }
+            self.phase = Phase::Closed;
+            return;
+        }
+        self.round += 1;
+        self.setup.seed = self.setup.seed.wrapping_add(1);
+        (self.sim, self.inboxes) = round_sim(&self.setup);
+        for client in self.seats.iter_mut().flatten() {
+            client.queued.clear();
+        }
+        self.phase = Phase::Waiting;
+    }
+
+    fn round_phase(&self) -> RoundPhase {
+        match self.phase {
+            Phase::Waiting | Phase::Closed => RoundPhase::Waiting,
+            Phase::Countdown(start) => RoundPhase::Countdown {
+                ms: start.saturating_duration_since(Instant::now()).as_millis() as u64,
+            },
+            Phase::Playing if self.paused() => RoundPhase::Paused,
+            Phase::Playing => RoundPhase::Playing,
+            Phase::Over { winner, .. } => RoundPhase::Over { winner },
+        }
+    }
+
+    /// The round as it stands, `ack` is filled in per client.
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            seq: self.seq,
+            round: self.round,
+            setup: self.setup,
+            phase: self.round_phase(),
+            tick: self.sim.ticks,
+            snakes: self
+                .sim
+                .snakes
+                .iter()
+                .zip(&self.seats)
+                .map(|(snake, client)| SnakeSnapshot {
+                    name: client
+                        .as_ref()
+                        .map_or_else(String::new, |client| client.name.clone()),
+                    connected: client.as_ref().is_some_and(Client::connected),
+                    body: snake.body.clone(),
+                    direction: snake.direction,
+                    score: snake.score,
+                    death: snake.death,
+                })
+                .collect(),
+            food: self.sim.food.clone(),
+            time_left: self.sim.time_left,
+            ack: 0,
+        }
+    }
+
+    fn send_snapshots(&mut self) {
+        self.seq += 1;
+        self.last_snapshot = Some(Instant::now());
+        let mut snapshot = self.snapshot();
+        // Players who dropped out get them too, they may be back any moment
+        for client in self.seats.iter().flatten() {
+            snapshot.ack = client.ack;
+            let chunks = split_message(self.seq, &snapshot);
+            if chunks.len() > MAX_CHUNKS {
+                // Clients would drop it anyway, the board is too big to serve
+                error!(
+                    "Snapshot {} takes {} chunks, more than the {MAX_CHUNKS} clients take. Not sending it",
+                    self.seq,
+                    chunks.len()
+                );
+                return;
+            }
+            for chunk in chunks {
+                let message = ServerMessage::Snapshot(chunk);
+                send_message(&self.socket, client.address, &message);
+            }
+        }
+    }
+}
+
+/// Serves the [`ServerConfig`] inserted into the app, and exits once the
+/// last round is over.
+pub struct ServerPlugin;
+
+impl Plugin for ServerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, start_server)
+            .add_systems(Update, run_server);
+    }
+}
+
+fn start_server(mut commands: Commands, config: Res<ServerConfig>, mut exit: EventWriter<AppExit>) {
+    let server = match GameServer::new(&config) {
+        Ok(server) => server,
+        Err(err) => {
+            error!("Could not open {}: {err}", config.bind);
+            exit.write(AppExit::error());
+            return;
+        }
+    };
+    if let Ok(address) = server.local_addr() {
+        info!(
+            "Serving {} for {} players on {address}",
+            config.setup.mode.label(),
+            server.seats.len()
+        );
+    }
+    commands.insert_resource(server);
+}
+
+fn run_server(server: Option<ResMut<GameServer>>, mut exit: EventWriter<AppExit>) {
+    let Some(mut server) = server else {
+        return;
+    };
+    server.update();
+    if server.is_closed() {
+        exit.write(AppExit::Success);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        board::{board::Board, generator::BoardStyle},
+        mode::mode::GameMode,
+        net::{client::ServerLink, lobby::PlayerName, transport::Reassembly},
+    };
+    use std::net::Ipv4Addr;
+
+    fn server() -> GameServer {
+        GameServer::new(&ServerConfig {
+            bind: "127.0.0.1:0".to_string(),
+            setup: MatchSetup {
+                seed: 1,
+                mode: GameMode::Versus,
+                style: BoardStyle::Open,
+                board: Board {
+                    half_extent: IVec2::new(15, 11),
+                },
+            },
+            tick_time: Duration::from_millis(100),
+            rounds: None,
+        })
+        .unwrap()
+    }
+
+    fn address(port: u16) -> SocketAddr {
+        (Ipv4Addr::LOCALHOST, port).into()
+    }
+
+    #[test]
+    fn seats_come_back_by_token_only() {
+        let mut server = server();
+        server.hello(address(1), "ada", None);
+        let token = server.seats[0].as_ref().unwrap().token;
+        // Gone quiet, but still holding the seat
+        server.seats[0].as_mut().unwrap().heard = Instant::now() - TIMEOUT;
+
+        server.hello(address(2), "ada", None);
+        assert_eq!(server.seat_of(address(2)), Some(1));
+        server.hello(address(3), "ada", Some(token));
+        assert_eq!(server.seat_of(address(3)), Some(0));
+    }
+
+    #[test]
+    fn turns_numbered_past_the_end_are_dropped() {
+        let mut server = server();
+        server.hello(address(1), "ada", None);
+        server.take_turns(0, u64::MAX, vec![Direction::Up, Direction::Down]);
+        assert_eq!(server.seats[0].as_ref().unwrap().ack, 0);
+        server.take_turns(0, 0, vec![Direction::Up]);
+        assert_eq!(server.seats[0].as_ref().unwrap().ack, 1);
+    }
+
+    #[test]
+    fn snapshots_of_long_snakes_reach_the_client() {
+        let mut server = server();
+        let port = server.local_addr().unwrap().port();
+        let mut link = ServerLink::connect(address(port), &PlayerName("ada".into())).unwrap();
+        // Far too long for one packet
+        let body: VecDeque<IVec2> = server.sim.board().cells().take(600).collect();
+        server.sim.snakes[0].body = body.clone();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while link.snapshot.is_none() {
+            assert!(Instant::now() < deadline, "no snapshot came through");
+            link.update();
+            server.update();
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        let snapshot = link.snapshot.unwrap();
+        assert_eq!(link.player, Some(0));
+        assert_eq!(snapshot.snakes[0].body, body);
+        assert!(snapshot.fits(&server.sim));
+    }
+
+    #[test]
+    fn a_packed_board_of_the_largest_size_fits_into_chunks() {
+        let mut server = GameServer::new(&ServerConfig {
+            bind: "127.0.0.1:0".to_string(),
+            setup: MatchSetup {
+                seed: 1,
+                mode: GameMode::Versus,
+                style: BoardStyle::Open,
+                board: Board {
+                    half_extent: IVec2::splat(MAX_SERVER_SIDE / 2),
+                },
+            },
+            tick_time: Duration::from_millis(100),
+            rounds: None,
+        })
+        .unwrap();
+        for seat in 0..2 {
+            server.hello(address(seat + 1), &"x".repeat(MAX_NAME), None);
+        }
+        // Every cell taken by a snake is as big as a snapshot gets
+        let cells: Vec<IVec2> = server.sim.board().cells().collect();
+        let (a, b) = cells.split_at(cells.len() / 2);
+        server.sim.snakes[0].body = a.iter().copied().collect();
+        server.sim.snakes[1].body = b.iter().copied().collect();
+        server.sim.food.clear();
+        let mut snapshot = server.snapshot();
+        snapshot.seq = u64::MAX;
+        snapshot.ack = u64::MAX;
+
+        let chunks = split_message(snapshot.seq, &snapshot);
+        assert!(chunks.len() <= MAX_CHUNKS, "{} chunks", chunks.len());
+        let mut reassembly = Reassembly::default();
+        let last = chunks.len() - 1;
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            let message = serde_json::to_vec(&ServerMessage::Snapshot(chunk)).unwrap();
+            assert!(message.len() <= MAX_PACKET);
+            let ServerMessage::Snapshot(chunk) = serde_json::from_slice(&message).unwrap() else {
+                unreachable!()
+            };
+            let done = reassembly.add::<Snapshot>(chunk);
+            assert_eq!(done.is_some(), i == last);
+            if let Some(done) = done {
+                assert_eq!(done, snapshot);
+                assert!(done.fits(&server.sim));
+            }
+        }
+    }
+
+    #[test]
+    fn snapshots_that_do_not_fit_the_round_are_caught() {
+        let server = server();
+        let good = Snapshot {
+            seq: 1,
+            round: 1,
+            setup: server.setup,
+            phase: RoundPhase::Over { winner: Some(1) },
+            tick: 0,
+            snakes: server
+                .sim
+                .snakes
+                .iter()
+                .map(|snake| SnakeSnapshot {
+                    name: "ada".to_string(),
+                    connected: true,
+                    body: snake.body.clone(),
+                    direction: snake.direction,
+                    score: 0,
+                    death: None,
+                })
+                .collect(),
+            food: server.sim.food.clone(),
+            time_left: None,
+            ack: 0,
+        };
+        assert!(good.fits(&server.sim));
+
+        let mut missing_snake = good.clone();
+        missing_snake.snakes.pop();
+        let mut unknown_winner = good.clone();
+        unknown_winner.phase = RoundPhase::Over { winner: Some(2) };
+        let mut off_board = good.clone();
+        off_board.food.push(IVec2::new(100, 0));
+        let mut no_body = good;
+        no_body.snakes[0].body.clear();
+        for bad in [missing_snake, unknown_winner, off_board, no_body] {
+            assert!(!bad.fits(&server.sim));
+        }
+    }
+}
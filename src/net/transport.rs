@@ -0,0 +1,304 @@
+//! Unreliable datagrams between two peers. Packets may be lost, late or come
+//! in out of order, so everything on top resends until it is acknowledged.
+//! Besides UDP there is an in-memory pair for peers in one process, and a
+//! [`LinkConditioner`] that makes any link worse on purpose for testing.
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::{
+    collections::VecDeque,
+    io,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Largest packet that is sent or read, small enough to get through any link
+/// without being split up on the way.
+pub const MAX_PACKET: usize = 1200;
+
+/// Room [`split_message`] leaves in a packet for the message around a chunk.
+const CHUNK_OVERHEAD: usize = 200;
+
+/// Most chunks a message may come in, anything claiming more is dropped.
+pub const MAX_CHUNKS: usize = 256;
+
+pub trait Transport: Send + Sync + 'static {
+    /// Sends one packet, with no promise that it arrives.
+    fn send(&mut self, packet: &[u8]);
+
+    /// Next packet that has come in, `None` when nothing is waiting.
+    fn recv(&mut self) -> Option<Vec<u8>>;
+}
+
+/// A UDP socket talking to a single peer. Packets from anyone else are
+/// dropped.
+pub struct UdpTransport {
+    socket: UdpSocket,
+    peer: SocketAddr,
+}
+
+impl UdpTransport {
+    pub fn bind(local: impl ToSocketAddrs, peer: impl ToSocketAddrs) -> io::Result<Self> {
+        let socket = UdpSocket::bind(local)?;
+        socket.set_nonblocking(true)?;
+        let peer = peer
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no peer address"))?;
+        Ok(Self { socket, peer })
+    }
+
+    /// Takes over a socket that is already open, e.g. the one a lobby used.
+    pub fn from_socket(socket: UdpSocket, peer: SocketAddr) -> Self {
+        Self { socket, peer }
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+}
+
+impl Transport for UdpTransport {
+    fn send(&mut self, packet: &[u8]) {
+        // A peer that is not up yet refuses packets, the next resend tries again
+        let _ = self.socket.send_to(packet, self.peer);
+    }
+
+    fn recv(&mut self) -> Option<Vec<u8>> {
+        let mut buffer = [0; MAX_PACKET];
+        loop {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((len, from)) if from == self.peer => return Some(buffer[..len].to_vec()),
+                Ok(_) => {}
+                Err(_) => return None,
+            }
+        }
+    }
+}
+
+/// Sends `message` as one JSON datagram, for sockets that talk to more than
+/// one address.
+pub fn send_message(socket: &UdpSocket, to: SocketAddr, message: &impl Serialize) {
+    if let Ok(bytes) = serde_json::to_vec(message) {
+        // Losing this one is fine, the next heartbeat repeats it
+        let _ = socket.send_to(&bytes, to);
+    }
+}
+
+/// Next datagram that reads as a `T` and who sent it, read into `buffer`,
+/// which is kept by the caller to be used again. Anything else waiting before
+/// it is dropped.
+pub fn receive_message<T: DeserializeOwned>(
+    socket: &UdpSocket,
+    buffer: &mut [u8],
+) -> Option<(T, SocketAddr)> {
+    loop {
+        let (len, from) = socket.recv_from(buffer).ok()?;
+        if let Ok(message) = serde_json::from_slice(&buffer[..len]) {
+            return Some((message, from));
+        }
+    }
+}
+
+/// Piece of a message too long for one packet, see [`split_message`].
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Chunk {
+    /// Counts up with every message, chunks of older ones are dropped.
+    pub id: u64,
+    pub index: usize,
+    pub count: usize,
+    pub text: String,
+}
+
+/// `message` as JSON in chunks, each of which fits into one packet with room
+/// to spare for whatever it is wrapped in.
+pub fn split_message(id: u64, message: &impl Serialize) -> Vec<Chunk> {
+    let Ok(json) = serde_json::to_string(message) else {
+        return Vec::new();
+    };
+    // Quotes and backslashes take twice the room once the text is in a string
+    let escaped = |c: char| match c {
+        '"' | '\\' => 2,
+        c if (c as u32) < 0x20 => 6,
+        c => c.len_utf8(),
+    };
+    let mut texts = vec![String::new()];
+    let mut size = 0;
+    for c in json.chars() {
+        if size + escaped(c) > MAX_PACKET - CHUNK_OVERHEAD {
+            texts.push(String::new());
+            size = 0;
+        }
+        size += escaped(c);
+        texts.last_mut().unwrap().push(c);
+    }
+    let count = texts.len();
+    texts
+        .into_iter()
+        .enumerate()
+        .map(|(index, text)| Chunk {
+            id,
+            index,
+            count,
+            text,
+        })
+        .collect()
+}
+
+/// Puts the chunks of a message back together, whatever order they come in.
+#[derive(Default)]
+pub struct Reassembly {
+    id: u64,
+    texts: Vec<Option<String>>,
+}
+
+impl Reassembly {
+    /// The message once `chunk` was the last one missing. Chunks of a message
+    /// older than the one in progress are dropped, a newer one replaces it.
+    pub fn add<T: DeserializeOwned>(&mut self, chunk: Chunk) -> Option<T> {
+        if chunk.count > MAX_CHUNKS || chunk.index >= chunk.count || chunk.id < self.id {
+            return None;
+        }
+        if chunk.id > self.id || self.texts.len() != chunk.count {
+            self.id = chunk.id;
+            self.texts = vec![None; chunk.count];
+        }
+        self.texts[chunk.index] = Some(chunk.text);
+        if self.texts.iter().any(Option::is_none) {
+            return None;
+        }
+        // Repeats of these chunks are dropped from now on
+        self.id = chunk.id.saturating_add(1);
+        let json: String = self.texts.drain(..).flatten().collect();
+        serde_json::from_str(&json).ok()
+    }
+}
+
+type Queue = Arc<Mutex<VecDeque<Vec<u8>>>>;
+
+/// One end of a link within the process, for peers that live side by side.
+pub struct MemoryTransport {
+    outbox: Queue,
+    inbox: Queue,
+}
+
+impl MemoryTransport {
+    /// Two ends, whatever one sends the other receives.
+    pub fn pair() -> (Self, Self) {
+        let (a, b) = (Queue::default(), Queue::default());
+        (
+            Self {
+                outbox: a.clone(),
+                inbox: b.clone(),
+            },
+            Self {
+                outbox: b,
+                inbox: a,
+            },
+        )
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn send(&mut self, packet: &[u8]) {
+        self.outbox.lock().unwrap().push_back(packet.to_vec());
+    }
+
+    fn recv(&mut self) -> Option<Vec<u8>> {
+        self.inbox.lock().unwrap().pop_front()
+    }
+}
+
+/// How bad a [`LinkConditioner`] makes the link.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct LinkConditions {
+    /// Share of packets dropped, 0 to 1.
+    pub loss: f64,
+    /// Delay added to every packet.
+    pub latency: Duration,
+    /// Up to this much more delay, picked per packet, so packets also come
+    /// in out of order.
+    pub jitter: Duration,
+}
+
+/// Drops and delays outgoing packets before they reach the real transport.
+pub struct LinkConditioner<T> {
+    inner: T,
+    pub conditions: LinkConditions,
+    /// Packets held back with the time they may go.
+    delayed: Vec<(Instant, Vec<u8>)>,
+    rng: StdRng,
+}
+
+impl<T: Transport> LinkConditioner<T> {
+    /// The seed decides which packets are lost and how late they are.
+    pub fn new(inner: T, conditions: LinkConditions, seed: u64) -> Self {
+        Self {
+            inner,
+            conditions,
+            delayed: Vec::new(),
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Sends every held back packet whose time has come.
+    fn release(&mut self) {
+        let now = Instant::now();
+        let inner = &mut self.inner;
+        self.delayed.retain(|(due, packet)| {
+            if *due > now {
+                return true;
+            }
+            inner.send(packet);
+            false
+        });
+    }
+}
+
+impl<T: Transport> Transport for LinkConditioner<T> {
+    fn send(&mut self, packet: &[u8]) {
+        let conditions = self.conditions;
+        if self.rng.random_bool(conditions.loss.clamp(0.0, 1.0)) {
+            self.release();
+            return;
+        }
+        let jitter = conditions.jitter.mul_f64(self.rng.random());
+        let due = Instant::now() + conditions.latency + jitter;
+        self.delayed.push((due, packet.to_vec()));
+        self.release();
+    }
+
+    fn recv(&mut self) -> Option<Vec<u8>> {
+        self.release();
+        self.inner.recv()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_messages_come_back_from_chunks_in_any_order() {
+        let message: Vec<(i32, String)> =
+            (0..2_000).map(|i| (i, format!("\"quoted\" {i}"))).collect();
+        let mut chunks = split_message(1, &message);
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            let packet = serde_json::to_vec(chunk).unwrap();
+            assert!(packet.len() <= MAX_PACKET - CHUNK_OVERHEAD + 100);
+        }
+
+        chunks.reverse();
+        let mut reassembly = Reassembly::default();
+        // A chunk of an older message in between changes nothing
+        let stale = split_message(0, &"old").remove(0);
+        let last = chunks.pop().unwrap();
+        for chunk in chunks {
+            assert_eq!(reassembly.add::<Vec<(i32, String)>>(chunk), None);
+            assert_eq!(reassembly.add::<Vec<(i32, String)>>(stale.clone()), None);
+        }
+        assert_eq!(reassembly.add(last), Some(message));
+    }
+}
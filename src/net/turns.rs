@@ -0,0 +1,205 @@
+//! Turns going back and forth between two peers. Each side sends its own turn
+//! for every tick and learns the other side's, in order and without gaps, no
+//! matter how many packets are lost. Every packet repeats all turns the other
+//! side has not acknowledged yet, so a lost packet costs nothing but the wait
+//! for the next one.
+
+use crate::{net::transport::Transport, player::player::Direction};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// How often unacknowledged turns go out again while nothing new comes up.
+const RESEND_INTERVAL: Duration = Duration::from_millis(30);
+
+/// What goes over the wire, as JSON.
+#[derive(Serialize, Deserialize, Debug)]
+struct TurnPacket {
+    /// Counts up with every packet, older ones that turn up late are dropped.
+    seq: u64,
+    /// Every turn of the receiver before this tick has arrived.
+    ack: u64,
+    /// Tick of the first turn in `turns`.
+    from: u64,
+    turns: Vec<Option<Direction>>,
+}
+
+/// Link health, for the status line and the end of a run.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct LinkStats {
+    pub sent: u64,
+    pub received: u64,
+    /// Packets that came in after a newer one and were dropped.
+    pub stale: u64,
+    /// Checks that found the game ready to step but still waiting on the
+    /// peer.
+    pub stalls: u64,
+    /// Ticks played again because a guessed turn was wrong.
+    pub rollbacks: u64,
+}
+
+pub struct TurnExchange {
+    transport: Box<dyn Transport>,
+    pub stats: LinkStats,
+    /// Own turns from `local_from` on.
+    local: VecDeque<Option<Direction>>,
+    local_from: u64,
+    /// Own turns before this tick have reached the peer.
+    acked: u64,
+    /// Peer turns from `remote_from` on.
+    remote: VecDeque<Option<Direction>>,
+    remote_from: u64,
+    /// Turns before this tick are not needed here any more.
+    kept_from: u64,
+    seq: u64,
+    remote_seq: u64,
+    last_sent: Option<Instant>,
+    last_heard: Option<Instant>,
+    /// A turn was added since the last packet went out.
+    dirty: bool,
+}
+
+impl TurnExchange {
+    /// Exchange over `transport`. Both sides start with `opening` ticks
+    /// without turns, so there is something to play before the first packet
+    /// arrives.
+    pub fn new(transport: impl Transport, opening: u64) -> Self {
+        let opening = VecDeque::from(vec![None; opening as usize]);
+        Self {
+            transport: Box::new(transport),
+            stats: LinkStats::default(),
+            local: opening.clone(),
+            local_from: 0,
+            acked: 0,
+            remote: opening,
+            remote_from: 0,
+            kept_from: 0,
+            seq: 0,
+            remote_seq: 0,
+            last_sent: None,
+            last_heard: None,
+            dirty: true,
+        }
+    }
+
+    /// First tick this side has no turn for yet.
+    pub fn local_end(&self) -> u64 {
+        self.local_from + self.local.len() as u64
+    }
+
+    /// First tick the peer's turn has not come in for.
+    pub fn remote_end(&self) -> u64 {
+        self.remote_from + self.remote.len() as u64
+    }
+
+    /// Own turn for `tick`, if it was given and not forgotten.
+    pub fn local(&self, tick: u64) -> Option<Option<Direction>> {
+        let i = tick.checked_sub(self.local_from)?;
+        self.local.get(i as usize).copied()
+    }
+
+    /// The peer's turn for `tick`, if it came in and was not forgotten.
+    pub fn remote(&self, tick: u64) -> Option<Option<Direction>> {
+        let i = tick.checked_sub(self.remote_from)?;
+        self.remote.get(i as usize).copied()
+    }
+
+    /// Adds this side's turn for [`TurnExchange::local_end`].
+    pub fn push_local(&mut self, turn: Option<Direction>) {
+        self.local.push_back(turn);
+        self.dirty = true;
+    }
+
+    /// Lets go of every turn before `tick`, once the peer has its own ones.
+    pub fn forget(&mut self, tick: u64) {
+        self.kept_from = self.kept_from.max(tick);
+        self.trim();
+    }
+
+    /// Whether the peer has every own turn before `tick`.
+    pub fn delivered(&self, tick: u64) -> bool {
+        self.acked >= tick
+    }
+
+    /// Time since anything came from the peer, `None` before the first packet.
+    pub fn silence(&self) -> Option<Duration> {
+        self.last_heard.map(|heard| heard.elapsed())
+    }
+
+    /// Reads what the peer sent and sends whatever it is still missing.
+    pub fn update(&mut self) {
+        self.receive();
+        let resend = self
+            .last_sent
+            .is_none_or(|sent| sent.elapsed() >= RESEND_INTERVAL);
+        if self.dirty || resend {
+            self.send();
+        }
+    }
+
+    fn receive(&mut self) {
+        while let Some(bytes) = self.transport.recv() {
+            // Late lobby messages can still turn up right after the start
+            let Ok(packet) = serde_json::from_slice::<TurnPacket>(&bytes) else {
+                debug!("Dropping a packet that is not a turn packet");
+                continue;
+            };
+            self.last_heard = Some(Instant::now());
+            if packet.seq <= self.remote_seq {
+                self.stats.stale += 1;
+                continue;
+            }
+            self.remote_seq = packet.seq;
+            self.stats.received += 1;
+
+            if packet.ack > self.acked {
+                self.acked = packet.ack;
+                self.trim();
+            }
+            // Turns already known are repeats, there are never gaps
+            for (i, turn) in packet.turns.into_iter().enumerate() {
+                if packet.from + i as u64 == self.remote_end() {
+                    self.remote.push_back(turn);
+                }
+            }
+            self.trim();
+        }
+    }
+
+    fn send(&mut self) {
+        let from = self.acked.max(self.local_from);
+        let packet = TurnPacket {
+            seq: self.seq + 1,
+            ack: self.remote_end(),
+            from,
+            turns: self
+                .local
+                .iter()
+                .skip((from - self.local_from) as usize)
+                .copied()
+                .collect(),
+        };
+        let Ok(bytes) = serde_json::to_vec(&packet) else {
+            return;
+        };
+        self.seq += 1;
+        self.transport.send(&bytes);
+        self.stats.sent += 1;
+        self.last_sent = Some(Instant::now());
+        self.dirty = false;
+    }
+
+    fn trim(&mut self) {
+        while self.local_from < self.acked.min(self.kept_from) && !self.local.is_empty() {
+            self.local.pop_front();
+            self.local_from += 1;
+        }
+        while self.remote_from < self.kept_from && !self.remote.is_empty() {
+            self.remote.pop_front();
+            self.remote_from += 1;
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! avian2d integration. The grid game answers every collision question through
+//! `Occupancy`, so physics is an optional feature; without it the collider
+//! helpers return empty bundles and nothing else needs to change.
+
+use bevy::prelude::*;
+
+#[cfg(feature = "physics")]
+use crate::player::player::GRID_SIZE;
+#[cfg(feature = "physics")]
+use avian2d::prelude::*;
+
+pub struct GamePhysicsPlugin;
+
+impl Plugin for GamePhysicsPlugin {
+    fn build(&self, _app: &mut App) {
+        #[cfg(feature = "physics")]
+        _app.add_plugins((PhysicsPlugins::default(), PhysicsDebugPlugin::default()));
+    }
+}
+
+/// Physics layers for everything that can carry a collider.
+///
+/// Interaction matrix (symmetric, a pair only collides when both sides list each other):
+///
+/// | layer    | collides with                              |
+/// |----------|--------------------------------------------|
+/// | Head     | Head, Body, Food, Wall, Obstacle, PowerUp  |
+/// | Body     | Head                                       |
+/// | Food     | Head                                       |
+/// | Wall     | Head                                       |
+/// | Obstacle | Head                                       |
+/// | PowerUp  | Head                                       |
+/// | Neck     | nothing                                    |
+///
+/// Only heads generate events against the rest of the board; bodies, walls and
+/// pickups never collide among themselves. The pieces right behind a slither
+/// head always overlap it, so they sit on `Neck` and make no contacts at all.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "physics", derive(PhysicsLayer))]
+pub enum GameLayer {
+    #[default]
+    Default,
+    Head,
+    Body,
+    Food,
+    Wall,
+    Obstacle,
+    Neck,
+    // Board pieces not spawned yet
+    #[allow(dead_code)]
+    PowerUp,
+}
+
+#[cfg(feature = "physics")]
+impl GameLayer {
+    pub fn collision_layers(self) -> CollisionLayers {
+        match self {
+            Self::Default => CollisionLayers::default(),
+            Self::Head => CollisionLayers::new(
+                Self::Head,
+                [
+                    Self::Head,
+                    Self::Body,
+                    Self::Food,
+                    Self::Wall,
+                    Self::Obstacle,
+                    Self::PowerUp,
+                ],
+            ),
+            Self::Body | Self::Food | Self::Wall | Self::Obstacle | Self::PowerUp => {
+                CollisionLayers::new(self, Self::Head)
+            }
+            Self::Neck => CollisionLayers::new(self, LayerMask::NONE),
+        }
+    }
+}
+
+/// Cell-sized collider on the given layer.
+#[cfg(feature = "physics")]
+pub fn grid_collider(layer: GameLayer) -> impl Bundle {
+    (
+        Collider::rectangle(GRID_SIZE, GRID_SIZE),
+        layer.collision_layers(),
+    )
+}
+
+#[cfg(not(feature = "physics"))]
+pub fn grid_collider(_layer: GameLayer) -> impl Bundle {}
+
+/// Collider for a snake head. Heads are the only pieces with collision events
+/// enabled, which still covers every pair in the matrix above.
+#[cfg(feature = "physics")]
+pub fn head_collider() -> impl Bundle {
+    (grid_collider(GameLayer::Head), CollisionEventsEnabled)
+}
+
+#[cfg(not(feature = "physics"))]
+pub fn head_collider() -> impl Bundle {
+    grid_collider(GameLayer::Head)
+}
@@ -0,0 +1,373 @@
+//! Snakes steered by an outside program, in whatever language it is written.
+//! Every time the snake is about to step, the board goes out as one JSON line
+//! and one line comes back:
+//!
+//! ```text
+//! > {"tick":3,"board":{"min":[-15,-11],"max":[15,11],"wrap":false},"you":{...},...}
+//! < {"tick":3,"direction":"left"}
+//! ```
+//!
+//! The reply may also be just the direction, `left`. The step waits for it up
+//! to the timeout, in the game as in headless runs, so a bot plays the same
+//! rules everywhere. A reply that is late, malformed or for an older tick
+//! keeps the snake going straight.
+
+use crate::{
+    board::occupancy::CellContent,
+    player::{
+        controller::{SnakeController, SnakeView},
+        player::Direction,
+    },
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, BufReader, Write},
+    net::TcpStream,
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Receiver, RecvTimeoutError},
+    },
+    time::{Duration, Instant},
+};
+
+/// How long a bot gets to answer when no timeout is given.
+pub const DEFAULT_BOT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Where the bot is found.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum BotTarget {
+    /// The game's own stdin and stdout, for a bot that starts the game as a
+    /// child process.
+    Stdio,
+    /// A bot listening on a TCP address, e.g. `127.0.0.1:7000`.
+    Socket(String),
+}
+
+/// Set when the game was started with `--bot`, the first player's snake is
+/// then handed to the bot.
+#[derive(Resource, Clone, Debug)]
+pub struct BotLink {
+    pub target: BotTarget,
+    pub timeout: Duration,
+    /// Lines read from stdin. There is only one stdin, so every run shares
+    /// the same reader.
+    stdin: Option<Arc<Mutex<Receiver<String>>>>,
+}
+
+impl BotLink {
+    pub fn new(target: BotTarget, timeout: Duration) -> Self {
+        let stdin = (target == BotTarget::Stdio).then(|| read_lines(BufReader::new(io::stdin())));
+        Self {
+            target,
+            timeout,
+            stdin,
+        }
+    }
+
+    /// Picks `--bot <stdio|address>` and `--bot-timeout <ms>` out of the
+    /// command line, `None` without `--bot`.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Option<Self>, String> {
+        let mut target = None;
+        let mut timeout = DEFAULT_BOT_TIMEOUT;
+        let mut args = args.into_iter();
+        while let Some(flag) = args.next() {
+            match flag.as_str() {
+                "--bot" => {
+                    let value = args.next().ok_or("--bot needs stdio or an address")?;
+                    target = Some(match value.as_str() {
+                        "stdio" => BotTarget::Stdio,
+                        _ => BotTarget::Socket(value),
+                    });
+                }
+                "--bot-timeout" => {
+                    let value = args.next().ok_or("--bot-timeout needs milliseconds")?;
+                    let millis = value
+                        .parse()
+                        .map_err(|_| format!("bad value for --bot-timeout: {value}"))?;
+                    timeout = Duration::from_millis(millis);
+                }
+                _ => {}
+            }
+        }
+        Ok(target.map(|target| Self::new(target, timeout)))
+    }
+
+    /// Controller talking to the bot, over a fresh connection for sockets.
+    pub fn connect(&self) -> io::Result<BotController> {
+        let (writer, replies): (Box<dyn Write + Send>, _) = match &self.target {
+            BotTarget::Stdio => (
+                Box::new(io::stdout()),
+                self.stdin.clone().expect("stdio links read stdin"),
+            ),
+            BotTarget::Socket(address) => {
+                let stream = TcpStream::connect(address)?;
+                stream.set_nodelay(true)?;
+                let replies = read_lines(BufReader::new(stream.try_clone()?));
+                (Box::new(stream), replies)
+            }
+        };
+        Ok(BotController {
+            writer: Mutex::new(writer),
+            replies,
+            timeout: self.timeout,
+            tick: 0,
+        })
+    }
+}
+
+/// Reads lines on a thread of their own, so waiting for a reply can time out.
+fn read_lines(reader: impl BufRead + Send + 'static) -> Arc<Mutex<Receiver<String>>> {
+    let (sender, replies) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in reader.lines() {
+            let Ok(line) = line else { break };
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    Arc::new(Mutex::new(replies))
+}
+
+/// Board state sent to the bot.
+#[derive(Serialize, Debug)]
+pub struct BotState<'a> {
+    /// Counts the steps of this snake, replies name the tick they answer.
+    pub tick: u64,
+    pub board: BotBoard,
+    pub you: BotSnake<'a>,
+    /// Cells taken by other snakes.
+    pub rivals: Vec<IVec2>,
+    pub food: &'a [IVec2],
+    /// Walls and obstacles.
+    pub walls: Vec<IVec2>,
+    /// Both ends of every portal pair.
+    pub portals: Vec<(IVec2, IVec2)>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BotBoard {
+    pub min: IVec2,
+    pub max: IVec2,
+    /// Whether edges lead to the opposite side.
+    pub wrap: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BotSnake<'a> {
+    pub direction: Direction,
+    /// Head first.
+    pub body: &'a VecDeque<IVec2>,
+    pub growing: bool,
+}
+
+impl<'a> BotState<'a> {
+    pub fn new(tick: u64, snake: &SnakeView<'a>) -> Self {
+        let board = snake.occupancy.board();
+        // Body pieces are entities of their own, so the snake is told apart
+        // from its rivals by its cells
+        let mut own = vec![false; (board.width() * board.height()) as usize];
+        for &cell in snake.body {
+            if let Some(i) = board.index(cell) {
+                own[i] = true;
+            }
+        }
+        let mut rivals = Vec::new();
+        let mut walls = Vec::new();
+        for cell in board.cells() {
+            let Some(occupant) = snake.occupancy.get(cell) else {
+                continue;
+            };
+            match occupant.content {
+                CellContent::Head | CellContent::Body
+                    if !board.index(cell).is_some_and(|i| own[i]) =>
+                {
+                    rivals.push(cell)
+                }
+                CellContent::Wall | CellContent::Obstacle => walls.push(cell),
+                _ => {}
+            }
+        }
+        Self {
+            tick,
+            board: BotBoard {
+                min: board.min(),
+                max: board.max(),
+                wrap: snake.wrap,
+            },
+            you: BotSnake {
+                direction: snake.direction,
+                body: snake.body,
+                growing: snake.growing,
+            },
+            rivals,
+            food: snake.food,
+            walls,
+            portals: snake.portals.pairs().collect(),
+        }
+    }
+}
+
+/// Reply from the bot. `tick` may be left out.
+#[derive(Deserialize, Debug)]
+pub struct BotReply {
+    pub tick: Option<u64>,
+    pub direction: Direction,
+}
+
+impl BotReply {
+    /// A JSON reply or a bare direction.
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        serde_json::from_str(line).ok().or_else(|| {
+            serde_json::from_value(serde_json::Value::String(line.to_lowercase()))
+                .ok()
+                .map(|direction| BotReply {
+                    tick: None,
+                    direction,
+                })
+        })
+    }
+}
+
+pub struct BotController {
+    writer: Mutex<Box<dyn Write + Send>>,
+    replies: Arc<Mutex<Receiver<String>>>,
+    timeout: Duration,
+    tick: u64,
+}
+
+impl BotController {
+    /// Sends the board for the current tick, false when the bot is gone.
+    fn send(&mut self, snake: &SnakeView) -> bool {
+        let state = BotState::new(self.tick, snake);
+        let writer = self.writer.get_mut().unwrap();
+        let sent = serde_json::to_writer(&mut *writer, &state)
+            .map_err(io::Error::from)
+            .and_then(|_| writer.write_all(b"\n"))
+            .and_then(|_| writer.flush());
+        if let Err(err) = &sent {
+            warn!("Could not reach the bot: {err}");
+        }
+        sent.is_ok()
+    }
+}
+
+impl SnakeController for BotController {
+    fn decide(&mut self, snake: &SnakeView) -> Option<Direction> {
+        self.tick += 1;
+        let replies = Arc::clone(&self.replies);
+        let replies = replies.lock().unwrap();
+
+        // Whatever came in after an earlier timeout is stale by now
+        while replies.try_recv().is_ok() {}
+        if !self.send(snake) {
+            return None;
+        }
+
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match replies.recv_timeout(left) {
+                Ok(line) => match BotReply::parse(&line) {
+                    Some(reply) if reply.tick.is_none_or(|tick| tick == self.tick) => {
+                        return Some(reply.direction);
+                    }
+                    Some(_) => {}
+                    None => warn!("Ignoring bot reply {line:?}"),
+                },
+                Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        board::board::Board, mode::mode::GameMode, player::controller::ReplayController,
+        sim::sim::Sim,
+    };
+
+    /// Stands in for the bot, answering every state with the given lines.
+    struct ScriptedBot {
+        answers: Vec<String>,
+        sender: mpsc::Sender<String>,
+    }
+
+    impl Write for ScriptedBot {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            for answer in &self.answers {
+                let _ = self.sender.send(answer.clone());
+            }
+            Ok(())
+        }
+    }
+
+    fn controller(answers: &[&str], timeout: Duration) -> BotController {
+        let (sender, replies) = mpsc::channel();
+        let bot = ScriptedBot {
+            answers: answers.iter().map(|answer| answer.to_string()).collect(),
+            sender,
+        };
+        BotController {
+            writer: Mutex::new(Box::new(bot)),
+            replies: Arc::new(Mutex::new(replies)),
+            timeout,
+            tick: 0,
+        }
+    }
+
+    fn sim() -> Sim {
+        let board = Board {
+            half_extent: IVec2::new(5, 5),
+        };
+        let idle = ReplayController::new(std::iter::empty());
+        Sim::new(board, None, GameMode::Zen.rules(), 1, vec![Box::new(idle)])
+    }
+
+    #[test]
+    fn parses_json_and_bare_directions() {
+        let reply = BotReply::parse(r#"{"tick":3,"direction":"left"}"#).unwrap();
+        assert_eq!((reply.tick, reply.direction), (Some(3), Direction::Left));
+        let reply = BotReply::parse(r#"{"direction":"up"}"#).unwrap();
+        assert_eq!((reply.tick, reply.direction), (None, Direction::Up));
+        let reply = BotReply::parse(" DOWN\n").unwrap();
+        assert_eq!((reply.tick, reply.direction), (None, Direction::Down));
+        assert!(BotReply::parse("sideways").is_none());
+        assert!(BotReply::parse(r#"{"tick":3}"#).is_none());
+    }
+
+    #[test]
+    fn follows_the_reply_for_this_tick() {
+        let sim = sim();
+        let mut bot = controller(
+            &[
+                r#"{"tick":7,"direction":"down"}"#,
+                r#"{"tick":1,"direction":"up"}"#,
+            ],
+            Duration::from_secs(5),
+        );
+        assert_eq!(bot.decide(&sim.view(0)), Some(Direction::Up));
+    }
+
+    #[test]
+    fn goes_straight_without_a_reply_for_this_tick() {
+        let sim = sim();
+        let timeout = Duration::from_millis(20);
+        let mut bot = controller(&[r#"{"tick":7,"direction":"down"}"#, "garbage"], timeout);
+        let start = Instant::now();
+        assert_eq!(bot.decide(&sim.view(0)), None);
+        assert!(start.elapsed() >= timeout);
+
+        let mut silent = controller(&[], timeout);
+        assert_eq!(silent.decide(&sim.view(0)), None);
+    }
+}
@@ -0,0 +1,364 @@
+//! Where a grid snake's turns come from. Every snake head carries a
+//! [`Controller`], so keyboards, gamepads, computer players, recorded runs and
+//! network peers can be mixed freely within one match.
+
+use crate::{
+    IsPaused,
+    board::{occupancy::Occupancy, portal::Portals},
+    mode::mode::GameMode,
+    player::{
+        food::Food,
+        player::{
+            Direction, GridPosition, KeyBindings, PendingGrowth, PlayerId, SnakeHead, SnakeSegment,
+            SnakeSegments, snake_movement_system,
+        },
+    },
+};
+use bevy::prelude::*;
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+/// Turns a key press can queue up before the snake takes them, so a quick
+/// double turn is not lost between two steps.
+const QUEUED_TURNS: usize = 2;
+
+/// How far a stick has to be pushed before it counts as a turn.
+const STICK_DEADZONE: f32 = 0.5;
+
+/// Devices as they are this frame.
+pub struct ControlInput<'a> {
+    pub keyboard: &'a ButtonInput<KeyCode>,
+    /// Connected gamepads, in the order they were connected.
+    pub gamepads: Vec<&'a Gamepad>,
+}
+
+/// What a controller gets to see when the snake is about to step.
+pub struct SnakeView<'a> {
+    pub entity: Entity,
+    pub direction: Direction,
+    /// Own cells, head first.
+    pub body: &'a VecDeque<IVec2>,
+    pub growing: bool,
+    pub food: &'a [IVec2],
+    pub occupancy: &'a Occupancy,
+    pub portals: &'a Portals,
+    pub wrap: bool,
+    /// Heads of the other snakes still on the board.
+    pub rival_heads: Vec<IVec2>,
+}
+
+pub trait SnakeController: Send + Sync + 'static {
+    /// Called every frame, for controllers that have to catch input as it
+    /// happens.
+    fn observe(&mut self, _input: &ControlInput) {}
+
+    /// Direction for the step the snake is about to take, `None` to keep
+    /// going the way it heads. Turning straight back is ignored.
+    fn decide(&mut self, snake: &SnakeView) -> Option<Direction>;
+}
+
+#[derive(Component)]
+pub struct Controller(pub Box<dyn SnakeController>);
+
+impl Controller {
+    pub fn new(controller: impl SnakeController) -> Self {
+        Self(Box::new(controller))
+    }
+}
+
+/// Turns from a set of keys.
+pub struct KeyboardController {
+    pub keys: KeyBindings,
+    queued: VecDeque<Direction>,
+}
+
+impl KeyboardController {
+    pub fn new(keys: KeyBindings) -> Self {
+        Self {
+            keys,
+            queued: VecDeque::new(),
+        }
+    }
+}
+
+impl SnakeController for KeyboardController {
+    fn observe(&mut self, input: &ControlInput) {
+        let keys = self.keys;
+        for (key, direction) in [
+            (keys.up, Direction::Up),
+            (keys.down, Direction::Down),
+            (keys.left, Direction::Left),
+            (keys.right, Direction::Right),
+        ] {
+            if input.keyboard.just_pressed(key) && self.queued.len() < QUEUED_TURNS {
+                self.queued.push_back(direction);
+            }
+        }
+    }
+
+    fn decide(&mut self, _snake: &SnakeView) -> Option<Direction> {
+        self.queued.pop_front()
+    }
+}
+
+/// Turns from the d-pad or left stick of one gamepad, picked by its position
+/// in [`ControlInput::gamepads`].
+pub struct GamepadController {
+    pub index: usize,
+    queued: Option<Direction>,
+    /// Direction the stick pointed last frame, a held stick only turns once.
+    stick: Option<Direction>,
+}
+
+impl GamepadController {
+    pub fn new(index: usize) -> Self {
+        Self {
+            index,
+            queued: None,
+            stick: None,
+        }
+    }
+}
+
+impl SnakeController for GamepadController {
+    fn observe(&mut self, input: &ControlInput) {
+        let Some(gamepad) = input.gamepads.get(self.index) else {
+            return;
+        };
+
+        for (button, direction) in [
+            (GamepadButton::DPadUp, Direction::Up),
+            (GamepadButton::DPadDown, Direction::Down),
+            (GamepadButton::DPadLeft, Direction::Left),
+            (GamepadButton::DPadRight, Direction::Right),
+        ] {
+            if gamepad.just_pressed(button) {
+                self.queued = Some(direction);
+            }
+        }
+
+        let stick = gamepad.left_stick();
+        let pointing = if stick.length() < STICK_DEADZONE {
+            None
+        } else if stick.x.abs() > stick.y.abs() {
+            Some(if stick.x > 0.0 {
+                Direction::Right
+            } else {
+                Direction::Left
+            })
+        } else {
+            Some(if stick.y > 0.0 {
+                Direction::Up
+            } else {
+                Direction::Down
+            })
+        };
+        if pointing.is_some() && pointing != self.stick {
+            self.queued = pointing;
+        }
+        self.stick = pointing;
+    }
+
+    fn decide(&mut self, _snake: &SnakeView) -> Option<Direction> {
+        self.queued.take()
+    }
+}
+
+/// Several controllers on one snake, the first one with a turn wins. Lets a
+/// player use the keyboard and a gamepad side by side.
+pub struct AnyOf(pub Vec<Box<dyn SnakeController>>);
+
+impl SnakeController for AnyOf {
+    fn observe(&mut self, input: &ControlInput) {
+        for controller in &mut self.0 {
+            controller.observe(input);
+        }
+    }
+
+    fn decide(&mut self, snake: &SnakeView) -> Option<Direction> {
+        // The rest keep their turns for the steps after this one
+        self.0
+            .iter_mut()
+            .find_map(|controller| controller.decide(snake))
+    }
+}
+
+/// Plays back recorded turns, one entry per step.
+pub struct ReplayController {
+    steps: VecDeque<Option<Direction>>,
+}
+
+impl ReplayController {
+    pub fn new(steps: impl IntoIterator<Item = Option<Direction>>) -> Self {
+        Self {
+            steps: steps.into_iter().collect(),
+        }
+    }
+}
+
+impl SnakeController for ReplayController {
+    fn decide(&mut self, _snake: &SnakeView) -> Option<Direction> {
+        self.steps.pop_front().flatten()
+    }
+}
+
+/// Sending end for a [`RemoteController`], handed to whatever receives the
+/// peer's input.
+#[derive(Clone, Default)]
+pub struct RemoteInbox(Arc<Mutex<VecDeque<Direction>>>);
+
+impl RemoteInbox {
+    pub fn push(&self, direction: Direction) {
+        self.0.lock().unwrap().push_back(direction);
+    }
+}
+
+/// Turns sent by a player on another machine.
+pub struct RemoteController {
+    inbox: RemoteInbox,
+}
+
+impl RemoteController {
+    pub fn new() -> (Self, RemoteInbox) {
+        let inbox = RemoteInbox::default();
+        (
+            Self {
+                inbox: inbox.clone(),
+            },
+            inbox,
+        )
+    }
+}
+
+impl SnakeController for RemoteController {
+    fn decide(&mut self, _snake: &SnakeView) -> Option<Direction> {
+        self.inbox.0.lock().unwrap().pop_front()
+    }
+}
+
+/// Keyboard plus the gamepad with the same number as the player.
+pub fn human_controller(player: PlayerId, keys: KeyBindings) -> Controller {
+    Controller::new(AnyOf(vec![
+        Box::new(KeyboardController::new(keys)),
+        Box::new(GamepadController::new(player.0)),
+    ]))
+}
+
+pub struct ControllerPlugin;
+
+impl Plugin for ControllerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            drive_snakes
+                .before(snake_movement_system)
+                .run_if(in_state(IsPaused::Running)),
+        );
+    }
+}
+
+/// Feeds every controller this frame's input and asks it for a turn when its
+/// snake steps this frame.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn drive_snakes(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<(Entity, &Gamepad)>,
+    mut head_query: Query<(
+        Entity,
+        &mut SnakeHead,
+        &mut Controller,
+        &GridPosition,
+        &SnakeSegments,
+        &PendingGrowth,
+    )>,
+    segment_query: Query<&GridPosition, (With<SnakeSegment>, Without<SnakeHead>)>,
+    food_query: Query<&GridPosition, With<Food>>,
+    occupancy: Res<Occupancy>,
+    portals: Res<Portals>,
+    mode: Res<GameMode>,
+    time: Res<Time>,
+) {
+    let mut gamepads: Vec<_> = gamepads.iter().collect();
+    gamepads.sort_by_key(|(entity, _)| *entity);
+    let input = ControlInput {
+        keyboard: &keyboard,
+        gamepads: gamepads.into_iter().map(|(_, gamepad)| gamepad).collect(),
+    };
+    let food: Vec<IVec2> = food_query.iter().map(|cell| cell.0).collect();
+    let wrap = mode.rules().wrap_edges;
+    let heads: Vec<(Entity, IVec2)> = head_query
+        .iter()
+        .map(|(entity, _, _, cell, ..)| (entity, cell.0))
+        .collect();
+
+    for (entity, mut head, mut controller, head_cell, segments, growth) in &mut head_query {
+        controller.0.observe(&input);
+
+        // Only the frame the movement timer runs out decides the next step
+        if head.next_move_timer.remaining() > time.delta() {
+            continue;
+        }
+
+        let body: VecDeque<IVec2> = std::iter::once(head_cell.0)
+            .chain(
+                segments
+                    .0
+                    .iter()
+                    .filter_map(|segment| segment_query.get(*segment).ok())
+                    .map(|cell| cell.0),
+            )
+            .collect();
+        let view = SnakeView {
+            entity,
+            direction: head.direction,
+            body: &body,
+            growing: growth.0 > 0,
+            food: &food,
+            occupancy: &occupancy,
+            portals: &portals,
+            wrap,
+            rival_heads: heads
+                .iter()
+                .filter(|(other, _)| *other != entity)
+                .map(|(_, cell)| *cell)
+                .collect(),
+        };
+
+        if let Some(direction) = controller.0.decide(&view)
+            && (body.len() == 1 || direction != head.direction.opposite())
+        {
+            head.direction = direction;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{board::board::Board, sim::sim::Sim};
+
+    #[test]
+    fn any_of_keeps_later_turns_for_later_steps() {
+        let board = Board {
+            half_extent: IVec2::new(5, 5),
+        };
+        let sim = Sim::new(
+            board,
+            None,
+            GameMode::Classic.rules(),
+            0,
+            vec![Box::new(ReplayController::new([]))],
+        );
+        let mut controller = AnyOf(vec![
+            Box::new(ReplayController::new([Some(Direction::Up), None])),
+            Box::new(ReplayController::new([Some(Direction::Left)])),
+        ]);
+
+        let view = sim.view(0);
+        assert_eq!(controller.decide(&view), Some(Direction::Up));
+        assert_eq!(controller.decide(&view), Some(Direction::Left));
+        assert_eq!(controller.decide(&view), None);
+    }
+}
@@ -1,48 +1,81 @@
-use crate::{player::player::{InGameEntity, Player}, MyAssets};
-use avian2d::prelude::*;
+use crate::{
+    MyAssets,
+    board::occupancy::{CellContent, Occupancy},
+    physics::physics::{GameLayer, grid_collider},
+    player::player::{GridPosition, InGameEntity, cell_to_world},
+};
 use bevy::prelude::*;
+use rand::{Rng, seq::IteratorRandom};
 
 #[derive(Component)]
 pub struct Food;
 
-pub fn spawn_food(commands: &mut Commands, assets: &MyAssets, window_w: f32, window_h: f32, player_query: &Query<&Transform, With<Player>>) {
-    let apple_texture = assets.apple.clone();
-
-    // Get player position
-    let player_pos = if let Ok(player_transform) = player_query.single() {
-        player_transform.translation
-    } else {
-        Vec3::ZERO // fallback if no player found
-    };
-
-    // Ensure food doesn't spawn on the border by adding a margin
-    let margin = 32.0;
-    let min_distance = 200.0;
-    
-    let mut x;
-    let mut y;
-    let mut attempts = 0;
-    const MAX_ATTEMPTS: i32 = 100;
-    
-    // Keep trying to find a position that's far enough from the player
-    loop {
-        x = rand::random::<f32>() * (window_w - 2.0 * margin) - (window_w / 2.0) + margin;
-        y = rand::random::<f32>() * (window_h - 2.0 * margin) - (window_h / 2.0) + margin;
-        
-        let food_pos = Vec3::new(x, y, 0.0);
-        let distance = player_pos.distance(food_pos);
-        
-        attempts += 1;
-        if distance >= min_distance || attempts >= MAX_ATTEMPTS {
-            break;
-        }
+/// Apples are drawn above the snakes.
+pub const FOOD_Z: f32 = 10.0;
+
+/// Cells the current level allows food on, empty when it can go anywhere.
+#[derive(Resource, Default)]
+pub struct FoodZones(pub Vec<IVec2>);
+
+impl FoodZones {
+    pub fn allows(&self, cell: IVec2) -> bool {
+        self.0.is_empty() || self.0.contains(&cell)
     }
+}
+
+/// Where the next apple goes. Prefers cells far enough from the player and
+/// falls back to any free cell. On a wrapping board a cell just across the
+/// edge is close too.
+pub fn pick_food_cell(
+    occupancy: &Occupancy,
+    zones: &FoodZones,
+    player_cell: IVec2,
+    wrap_edges: bool,
+    rng: &mut impl Rng,
+) -> Option<IVec2> {
+    let min_distance = 6;
+    let board = occupancy.board();
+    occupancy
+        .free_cells()
+        .filter(|cell| zones.allows(*cell))
+        .filter(|cell| board.distance(player_cell, *cell, wrap_edges) >= min_distance)
+        .choose(rng)
+        .or_else(|| {
+            occupancy
+                .free_cells()
+                .filter(|cell| zones.allows(*cell))
+                .choose(rng)
+        })
+}
+
+/// How an apple on `cell` looks.
+pub fn food_sprite(assets: &MyAssets, cell: IVec2) -> impl Bundle {
+    (
+        Sprite::from_image(assets.apple.clone()),
+        Transform::from_translation(cell_to_world(cell, FOOD_Z)),
+    )
+}
+
+pub fn spawn_food(
+    commands: &mut Commands,
+    assets: &MyAssets,
+    occupancy: &mut Occupancy,
+    zones: &FoodZones,
+    player_cell: IVec2,
+    wrap_edges: bool,
+) -> Option<Entity> {
+    let cell = pick_food_cell(occupancy, zones, player_cell, wrap_edges, &mut rand::rng())?;
+
+    let food = commands
+        .spawn((
+            Food,
+            InGameEntity,
+            GridPosition(cell),
+            food_sprite(assets, cell),
+            grid_collider(GameLayer::Food),
+        ))
+        .id();
+    occupancy.set(cell, food, CellContent::Food);
 
-    commands.spawn((
-        Food,
-        InGameEntity,
-        Sprite::from_image(apple_texture),
-        Transform::from_xyz(x, y, 10.0),
-        Collider::rectangle(32., 32.),
-    ));
+    Some(food)
 }
@@ -1,3 +1,8 @@
 pub mod player;
 
-pub mod food;
\ No newline at end of file
+pub mod bot;
+pub mod controller;
+pub mod food;
+
+#[cfg(feature = "physics")]
+pub mod slither;
@@ -1,15 +1,38 @@
 use crate::{
     AppState, IsPaused, MyAssets,
-    player::food::{Food, spawn_food},
+    ai::ai::{AiController, AiSnake, Opponent},
+    board::{
+        board::Board,
+        generator::BoardStyle,
+        obstacle::{ObstacleRenderAssets, spawn_obstacle, spawn_wall},
+        occupancy::{CellContent, Occupancy, Occupant},
+        portal::{Portals, spawn_portal_pair},
+    },
+    level::{
+        layout::LevelLayout,
+        loader::{PlaytestLayout, active_layout},
+    },
+    mode::mode::{FoodEaten, GameMode, MatchOutcome, RunSeed},
+    physics::physics::{GameLayer, grid_collider, head_collider},
+    player::{
+        bot::BotLink,
+        controller::{Controller, ControllerPlugin, human_controller},
+        food::{FoodZones, spawn_food},
+    },
+    sim::rules::{PlannedStep, next_cell, resolve_crashes},
 };
-use avian2d::prelude::*;
-use bevy::{input::keyboard::KeyboardInput, prelude::*, window::PrimaryWindow};
+
+#[cfg(feature = "physics")]
+use crate::player::slither::SlitherPlugin;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::{collections::VecDeque, time::Duration};
 
 // #[derive(Component, Default)]
 // pub struct Direction(DIRECTION);
 
-#[derive(Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Direction {
     #[default]
     Right,
@@ -18,25 +41,112 @@ pub enum Direction {
     Left,
 }
 
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Right,
+        Direction::Up,
+        Direction::Left,
+        Direction::Down,
+    ];
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+
+    /// One grid step in this direction.
+    pub fn delta(self) -> IVec2 {
+        match self {
+            Direction::Up => IVec2::Y,
+            Direction::Down => IVec2::NEG_Y,
+            Direction::Left => IVec2::NEG_X,
+            Direction::Right => IVec2::X,
+        }
+    }
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 #[derive(Component)]
 pub struct Player;
 
 pub const GRID_SIZE: f32 = 32.0;
 
+pub const SNAKE_Z: f32 = 5.0;
+
+/// World position of the centre of a grid cell.
+pub fn cell_to_world(cell: IVec2, z: f32) -> Vec3 {
+    (cell.as_vec2() * GRID_SIZE).extend(z)
+}
+
+/// How the snake moves, picked from the main menu.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum MovementMode {
+    /// Classic snake, one cell per tick.
+    #[default]
+    Grid,
+    /// Free-roaming head steered with the arrow keys, see `slither.rs`.
+    #[cfg(feature = "physics")]
+    Slither,
+}
+
 #[derive(Component)]
 pub struct Ground;
 
 #[derive(Component)]
 pub struct InGameEntity;
 
-// const SPEED: f32 = 140.0;
-
-#[derive(Component)]
+/// Points of one snake, kept on its head.
+#[derive(Component, Default)]
 pub struct Score(pub u32);
 
+/// HUD line showing the score of one player.
 #[derive(Component)]
-pub struct ScoreText;
+pub struct ScoreText(pub PlayerId);
+
+/// Which player a snake belongs to, `PlayerId(0)` is player one.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct PlayerId(pub usize);
+
+impl PlayerId {
+    pub fn name(self) -> String {
+        format!("Player {}", self.0 + 1)
+    }
+}
+
+/// Keys that steer one snake, see `controller.rs`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct KeyBindings {
+    pub up: KeyCode,
+    pub down: KeyCode,
+    pub left: KeyCode,
+    pub right: KeyCode,
+}
+
+impl KeyBindings {
+    pub const ARROWS: Self = Self {
+        up: KeyCode::ArrowUp,
+        down: KeyCode::ArrowDown,
+        left: KeyCode::ArrowLeft,
+        right: KeyCode::ArrowRight,
+    };
+
+    pub const WASD: Self = Self {
+        up: KeyCode::KeyW,
+        down: KeyCode::KeyS,
+        left: KeyCode::KeyA,
+        right: KeyCode::KeyD,
+    };
+
+    /// Bindings by player, player one keeps the arrow keys.
+    pub const PLAYERS: [Self; MAX_PLAYERS] = [Self::ARROWS, Self::WASD];
+}
+
+/// Snakes that can share one keyboard.
+pub const MAX_PLAYERS: usize = 2;
 
 #[derive(Component)]
 pub struct SnakeHead {
@@ -47,13 +157,62 @@ pub struct SnakeHead {
 #[derive(Component)]
 pub struct SnakeSegment;
 
+/// Grid cell a snake piece currently sits on.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GridPosition(pub IVec2);
+
+/// Body segments of one snake as a ring buffer, front is the piece right behind
+/// the head. A step only moves the back entity to the front, so cost does not
+/// grow with length.
+#[derive(Component, Default)]
+pub struct SnakeSegments(pub VecDeque<Entity>);
+
+/// Where the snake starts on the current board and which way it heads.
+#[derive(Resource, Clone, Copy, Default)]
+pub struct SpawnPoint {
+    pub cell: IVec2,
+    pub direction: Direction,
+}
+
+/// Segments a snake still has to add. Each step that consumes one spawns a new
+/// piece instead of moving the tail.
+#[derive(Component, Default)]
+pub struct PendingGrowth(pub u32);
+
+pub const SNAKE_COLOR: Color = Color::srgb_u8(65, 171, 93);
+
+pub const RIVAL_COLOR: Color = Color::srgb_u8(77, 140, 242);
+
+pub const AI_COLOR: Color = Color::srgb_u8(242, 153, 51);
+
+/// Mesh shared by every snake piece, one material per player and one for
+/// computer snakes, so segments batch together and growing a snake never adds
+/// new assets.
 #[derive(Resource)]
-pub struct SnakeSegments(pub Vec<Entity>);
+pub struct SnakeRenderAssets {
+    pub mesh: Handle<Mesh>,
+    pub players: [Handle<ColorMaterial>; MAX_PLAYERS],
+    /// Every computer snake, whichever player slot it took.
+    pub ai: Handle<ColorMaterial>,
+}
 
-#[derive(Resource, Default)]
-pub struct PositionHistory(pub VecDeque<Vec3>);
+impl SnakeRenderAssets {
+    pub fn material(&self, player: PlayerId) -> Handle<ColorMaterial> {
+        self.players[player.0 % self.players.len()].clone()
+    }
+}
 
-pub const MAX_HISTORY: usize = 1000; // Just a safety cap
+impl FromWorld for SnakeRenderAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mesh = world
+            .resource_mut::<Assets<Mesh>>()
+            .add(Rectangle::new(GRID_SIZE, GRID_SIZE));
+        let mut materials = world.resource_mut::<Assets<ColorMaterial>>();
+        let players = [SNAKE_COLOR, RIVAL_COLOR].map(|color| materials.add(color));
+        let ai = materials.add(AI_COLOR);
+        Self { mesh, players, ai }
+    }
+}
 
 pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
@@ -74,155 +233,452 @@ impl Plugin for PlayerPlugin {
         //             .run_if(in_state(IsPaused::Running)),
         //     );
 
-        // TODO: Reset resource in restart/State Change
+        #[cfg(feature = "physics")]
+        app.add_plugins(SlitherPlugin);
 
-        app.insert_resource(SnakeSegments(vec![]))
-            .insert_resource(PositionHistory::default())
+        app.add_plugins(ControllerPlugin)
+            .init_resource::<MovementMode>()
+            .init_resource::<SnakeRenderAssets>()
+            .init_resource::<ObstacleRenderAssets>()
+            .init_resource::<SpawnPoint>()
+            .init_resource::<FoodZones>()
+            .init_resource::<BoardStyle>()
             .add_systems(
                 Update,
                 transition_to_ingame.run_if(in_state(AppState::InGameLoading)),
             )
             // Setup and Exit
-            .add_systems(OnEnter(AppState::InGame), (spawn_ground, spawn_snake))
+            .add_systems(
+                OnEnter(AppState::InGame),
+                (
+                    spawn_ground,
+                    spawn_snake.run_if(resource_equals(MovementMode::Grid)),
+                    spawn_first_food,
+                )
+                    .chain(),
+            )
             .add_systems(OnExit(AppState::InGame), teardown_game_object)
             // Movement
             .add_systems(
                 Update,
-                (snake_movement_system, player_input, grow_on_key)
+                (
+                    snake_movement_system.run_if(resource_equals(MovementMode::Grid)),
+                    grow_on_key,
+                )
                     .run_if(in_state(IsPaused::Running)),
             );
     }
 }
 
-fn spawn_ground(mut commands: Commands, window_query: Single<&Window>, assets: Res<MyAssets>) {
-    let window_h = window_query.height();
-    let window_w = window_query.width();
+#[allow(clippy::too_many_arguments)]
+fn spawn_ground(
+    mut commands: Commands,
+    window_query: Single<&Window>,
+    assets: Res<MyAssets>,
+    layouts: Res<Assets<LevelLayout>>,
+    playtest: Res<PlaytestLayout>,
+    obstacle_render: Res<ObstacleRenderAssets>,
+    mode: Res<GameMode>,
+    style: Res<BoardStyle>,
+    seed: Res<RunSeed>,
+) {
+    // Campaign levels and playtests bring their own board, everything else
+    // fills the window and may get generated walls from the run seed
+    let window_board = Board::from_window(window_query.width(), window_query.height());
+    let layout = active_layout(*mode, &assets, &playtest).and_then(|handle| layouts.get(handle));
+    let generated = match layout {
+        Some(_) => None,
+        None => style.generate(window_board, seed.0),
+    };
+    let layout = layout.or(generated.as_ref());
+    let board = layout.map_or(window_board, |layout| layout.board);
     let ground_texture = assets.ground.clone();
 
-    let tiles_x = (window_w / GRID_SIZE).ceil() as i64;
-    let tiles_y = (window_h / GRID_SIZE).ceil() as i64;
-
-    let start_x = -window_w / 2.0 + GRID_SIZE / 2.0;
-    let start_y = -window_h / 2.0 + GRID_SIZE / 2.0;
-
-    for y in 0..tiles_y {
-        for x in 0..tiles_x {
-            let x_pos = start_x + x as f32 * GRID_SIZE;
-            let y_pos = start_y + y as f32 * GRID_SIZE;
+    for cell in board.cells() {
+        commands.spawn((
+            InGameEntity,
+            Sprite::from_image(ground_texture.clone()),
+            Transform::from_translation(cell_to_world(cell, 0.0)),
+            GlobalTransform::default(),
+            Ground,
+        ));
+    }
 
-            commands.spawn((
-                InGameEntity,
-                Sprite::from_image(ground_texture.clone()),
-                Transform::from_xyz(x_pos, y_pos, 0.0),
-                GlobalTransform::default(),
-                Ground,
-            ));
+    let mut occupancy = Occupancy::new(board);
+    let mut spawn = SpawnPoint::default();
+    let mut food_zones = FoodZones::default();
+    let mut portals = Portals::default();
+    if let Some(layout) = layout {
+        for &cell in &layout.walls {
+            spawn_wall(&mut commands, &obstacle_render, &mut occupancy, cell);
         }
+        for &cell in &layout.obstacles {
+            spawn_obstacle(&mut commands, &obstacle_render, &mut occupancy, cell);
+        }
+        for (id, &pair) in layout.portals.iter().enumerate() {
+            spawn_portal_pair(
+                &mut commands,
+                &assets,
+                &mut occupancy,
+                &mut portals,
+                id as u8 + 1,
+                pair,
+            );
+        }
+        spawn = SpawnPoint {
+            cell: layout.spawn,
+            direction: layout.spawn_direction,
+        };
+        food_zones.0.clone_from(&layout.food_zones);
     }
+
+    commands.insert_resource(board);
+    commands.insert_resource(occupancy);
+    commands.insert_resource(spawn);
+    commands.insert_resource(food_zones);
+    commands.insert_resource(portals);
 }
 
 fn spawn_snake(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    render: Res<SnakeRenderAssets>,
+    mut occupancy: ResMut<Occupancy>,
+    spawn: Res<SpawnPoint>,
+    mode: Res<GameMode>,
+    opponent: Res<Opponent>,
+    bot: Option<Res<BotLink>>,
 ) {
-    let shape = Rectangle::new(GRID_SIZE, GRID_SIZE);
-    let color = Color::srgb(65.0, 171.0, 93.0);
-
-    commands.spawn((
-        SnakeHead {
-            direction: Direction::Right,
-            next_move_timer: Timer::from_seconds(0.5, TimerMode::Repeating),
-        },
-        Transform::from_xyz(0.0, 0.0, 5.0),
-        GlobalTransform::default(),
-        InGameEntity,
-        Player,
-        Mesh2d(meshes.add(shape)),
-        MeshMaterial2d(materials.add(ColorMaterial::from(color))),
-        Collider::rectangle(GRID_SIZE, GRID_SIZE),
-        CollisionEventsEnabled,
-    ));
+    let rules = mode.rules();
+
+    // A computer opponent only joins the free-play modes, never a level
+    let difficulty = opponent.0.filter(|_| GameMode::ALL.contains(&*mode));
+    let snakes = rules.players + usize::from(difficulty.is_some());
+
+    for (i, spawn) in player_spawns(&occupancy, *spawn, snakes)
+        .into_iter()
+        .enumerate()
+    {
+        let player = PlayerId(i);
+        // Players steer and score, the computer snake only steers
+        let opponent = difficulty.filter(|_| i >= rules.players);
+        let (controller, computer) = match opponent {
+            Some(difficulty) => (Controller::new(AiController { difficulty }), true),
+            None => {
+                let mut controller = (human_controller(player, KeyBindings::PLAYERS[i]), false);
+                // A bot from the command line takes over the first player
+                if i == 0
+                    && let Some(bot) = &bot
+                {
+                    match bot.connect() {
+                        Ok(bot) => controller = (Controller::new(bot), true),
+                        Err(err) => error!("Could not connect to the bot, playing by hand: {err}"),
+                    }
+                }
+                controller
+            }
+        };
+        // Computer driven snakes share a colour, whichever slot they took
+        let material = if computer {
+            render.ai.clone()
+        } else {
+            render.material(player)
+        };
+        let mut head = commands.spawn((
+            SnakeHead {
+                direction: spawn.direction,
+                next_move_timer: Timer::from_seconds(rules.step_seconds, TimerMode::Repeating),
+            },
+            player,
+            SnakeSegments::default(),
+            PendingGrowth(rules.start_length),
+            GridPosition(spawn.cell),
+            snake_piece(&render, material, spawn.cell),
+            GlobalTransform::default(),
+            InGameEntity,
+            Player,
+            head_collider(),
+            controller,
+        ));
+        match opponent {
+            Some(_) => head.insert(AiSnake),
+            None => head.insert(Score::default()),
+        };
+        occupancy.set(spawn.cell, head.id(), CellContent::Head);
+    }
 }
 
-fn grow_snake(
+/// Snakes [`player_spawns`] has a starting spot for.
+pub const MAX_SNAKES: usize = 3;
+
+/// Where each snake starts. A lone snake uses the board's spawn point, more
+/// snakes start on opposite halves heading away from each other and then
+/// below the middle, on the free cells closest to those spots. There are no
+/// more than [`MAX_SNAKES`] spots.
+pub fn player_spawns(occupancy: &Occupancy, spawn: SpawnPoint, snakes: usize) -> Vec<SpawnPoint> {
+    if snakes < 2 {
+        return vec![spawn];
+    }
+
+    let quarter = occupancy.board().half_extent / 2;
+    let mut taken = Vec::new();
+    [
+        (IVec2::new(-quarter.x, 0), Direction::Up),
+        (IVec2::new(quarter.x, 0), Direction::Down),
+        (IVec2::new(0, -quarter.y), Direction::Right),
+    ]
+    .into_iter()
+    .take(snakes)
+    .map(|(wanted, direction)| {
+        let cell = occupancy
+            .free_cells()
+            .filter(|cell| !taken.contains(cell) && occupancy.is_free(*cell + direction.delta()))
+            .min_by_key(|cell| (*cell - wanted).abs().element_sum())
+            .unwrap_or(spawn.cell);
+        taken.push(cell);
+        SpawnPoint { cell, direction }
+    })
+    .collect()
+}
+
+fn spawn_first_food(
     mut commands: Commands,
-    mut segments: ResMut<SnakeSegments>,
-    history: Res<PositionHistory>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    assets: Res<MyAssets>,
+    mut occupancy: ResMut<Occupancy>,
+    food_zones: Res<FoodZones>,
+    spawn: Res<SpawnPoint>,
+    mode: Res<GameMode>,
 ) {
-    if let Some(&last_position) = history.0.get(segments.0.len()) {
-        let shape = Rectangle::new(GRID_SIZE, GRID_SIZE);
-        let color = Color::srgb(65.0, 171.0, 93.0);
+    spawn_food(
+        &mut commands,
+        &assets,
+        &mut occupancy,
+        &food_zones,
+        spawn.cell,
+        mode.rules().wrap_edges,
+    );
+}
 
-        let segment = commands
-            .spawn((
-                Mesh2d(meshes.add(shape)),
-                MeshMaterial2d(materials.add(ColorMaterial::from(color))),
-                SnakeSegment,
-                Collider::rectangle(GRID_SIZE, GRID_SIZE),
-                CollisionEventsEnabled,
-                InGameEntity,
-                Transform::from_translation(last_position),
-            ))
-            .id();
+/// How a snake piece on `cell` looks, head or body alike.
+pub fn snake_piece(
+    render: &SnakeRenderAssets,
+    material: Handle<ColorMaterial>,
+    cell: IVec2,
+) -> impl Bundle {
+    (
+        Mesh2d(render.mesh.clone()),
+        MeshMaterial2d(material),
+        Transform::from_translation(cell_to_world(cell, SNAKE_Z)),
+    )
+}
 
-        segments.0.push(segment);
-    }
+/// Body piece in the colour of its head.
+fn spawn_segment(
+    commands: &mut Commands,
+    render: &SnakeRenderAssets,
+    material: Handle<ColorMaterial>,
+    cell: IVec2,
+) -> Entity {
+    commands
+        .spawn((
+            snake_piece(render, material, cell),
+            SnakeSegment,
+            GridPosition(cell),
+            grid_collider(GameLayer::Body),
+            InGameEntity,
+        ))
+        .id()
 }
 
-fn grow_on_key(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    commands: Commands,
-    segments: ResMut<SnakeSegments>,
-    history: Res<PositionHistory>,
-    meshes: ResMut<Assets<Mesh>>,
-    materials: ResMut<Assets<ColorMaterial>>,
-) {
+fn grow_on_key(keyboard_input: Res<ButtonInput<KeyCode>>, mut query: Query<&mut PendingGrowth>) {
     if keyboard_input.just_pressed(KeyCode::Space) {
-        grow_snake(commands, segments, history, meshes, materials);
+        for mut growth in &mut query {
+            growth.0 += 1;
+        }
     }
 }
 
-fn snake_movement_system(
-    mut history: ResMut<PositionHistory>,
-    mut head_query: Query<(&mut Transform, &mut SnakeHead), Without<SnakeSegment>>,
-    mut segment_query: Query<&mut Transform, With<SnakeSegment>>,
-    segments: Res<SnakeSegments>,
+/// One grid step a snake takes this frame.
+struct Step {
+    head: Entity,
+    vacated: IVec2,
+    target: IVec2,
+    growing: bool,
+    /// Tail piece that leaves its cell during the step, `None` while growing.
+    moving_tail: Option<Entity>,
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn snake_movement_system(
+    mut commands: Commands,
+    mut head_query: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut GridPosition,
+            &mut SnakeHead,
+            &mut SnakeSegments,
+            &mut PendingGrowth,
+            &PlayerId,
+        ),
+        Without<SnakeSegment>,
+    >,
+    mut segment_query: Query<(&mut Transform, &mut GridPosition), With<SnakeSegment>>,
+    ai_query: Query<(), With<AiSnake>>,
+    material_query: Query<&MeshMaterial2d<ColorMaterial>, With<SnakeHead>>,
+    mut occupancy: ResMut<Occupancy>,
+    mut next_state: ResMut<NextState<IsPaused>>,
+    mut food_eaten: EventWriter<FoodEaten>,
+    food_zones: Res<FoodZones>,
+    portals: Res<Portals>,
+    mode: Res<GameMode>,
+    render: Res<SnakeRenderAssets>,
+    assets: Res<MyAssets>,
     time: Res<Time>,
 ) {
-    if let Ok((mut head_transform, mut head)) = head_query.single_mut() {
-        head.next_move_timer.tick(time.delta());
+    let rules = mode.rules();
 
-        if head.next_move_timer.finished() {
-            // Record current head position
-            history.0.push_front(head_transform.translation);
+    let mut steps = Vec::new();
+    for (head_entity, _, head_cell, mut head, segments, growth, _) in &mut head_query {
+        head.next_move_timer.tick(time.delta());
+        if !head.next_move_timer.finished() {
+            continue;
+        }
+        head.next_move_timer.reset();
+
+        // The cell the head leaves is where the body continues from
+        let vacated = head_cell.0;
+        let target = next_cell(
+            occupancy.board(),
+            &portals,
+            rules.wrap_edges,
+            vacated,
+            head.direction,
+        );
+
+        // Without pending growth the tail leaves its cell this step, so a head may enter it
+        let growing = growth.0 > 0;
+        steps.push(Step {
+            head: head_entity,
+            vacated,
+            target,
+            growing,
+            moving_tail: if growing {
+                None
+            } else {
+                segments.0.back().copied()
+            },
+        });
+    }
 
-            // Limit history size
-            if history.0.len() > MAX_HISTORY {
-                history.0.pop_back();
+    let planned: Vec<PlannedStep> = steps
+        .iter()
+        .map(|step| PlannedStep {
+            target: step.target,
+            moving_tail: step
+                .moving_tail
+                .and_then(|tail| segment_query.get(tail).ok())
+                .map(|(_, cell)| cell.0),
+        })
+        .collect();
+    let crashed: Vec<Entity> = resolve_crashes(&occupancy, &rules, &planned)
+        .into_iter()
+        .zip(&steps)
+        .filter(|(cause, _)| cause.is_some())
+        .map(|(_, step)| step.head)
+        .collect();
+
+    // A computer snake that crashes is taken off the board and the rest play on
+    let (wrecked, crashed): (Vec<Entity>, Vec<Entity>) = crashed
+        .into_iter()
+        .partition(|head| ai_query.contains(*head));
+    for &head in &wrecked {
+        if let Ok((_, _, head_cell, _, segments, ..)) = head_query.get(head) {
+            occupancy.remove(head_cell.0, head);
+            for &segment in &segments.0 {
+                if let Ok((_, segment_cell)) = segment_query.get(segment) {
+                    occupancy.remove(segment_cell.0, segment);
+                }
+                commands.entity(segment).despawn();
             }
+        }
+        commands.entity(head).despawn();
+    }
+    steps.retain(|step| !wrecked.contains(&step.head));
+
+    if !crashed.is_empty() {
+        // The first crash ends a match, whichever player is still standing wins it
+        if rules.players > 1 {
+            let survivors: Vec<PlayerId> = head_query
+                .iter()
+                .filter(|(entity, ..)| !crashed.contains(entity) && !ai_query.contains(*entity))
+                .map(|(.., player)| *player)
+                .collect();
+            commands.insert_resource(match survivors[..] {
+                [winner] => MatchOutcome::Winner(winner),
+                _ => MatchOutcome::Draw,
+            });
+        }
+        next_state.set(IsPaused::GameOver);
+        return;
+    }
 
-            // Move head
-            let delta = match head.direction {
-                Direction::Up => Vec3::Y * GRID_SIZE,
-                Direction::Down => -Vec3::Y * GRID_SIZE,
-                Direction::Left => -Vec3::X * GRID_SIZE,
-                Direction::Right => Vec3::X * GRID_SIZE,
-            };
-            head_transform.translation += delta;
-
-            // Move body segments
-            for (i, segment_entity) in segments.0.iter().enumerate() {
-                if let Ok(mut segment_transform) = segment_query.get_mut(*segment_entity) {
-                    if let Some(pos) = history.0.get(i) {
-                        segment_transform.translation = *pos;
-                    }
-                }
+    for step in steps {
+        let Ok((_, mut head_transform, mut head_cell, _, mut segments, mut growth, &player)) =
+            head_query.get_mut(step.head)
+        else {
+            continue;
+        };
+        let (vacated, target) = (step.vacated, step.target);
+
+        let mut ate = false;
+        if let Some(Occupant {
+            entity,
+            content: CellContent::Food,
+        }) = occupancy.get(target)
+        {
+            commands.entity(entity).despawn();
+            occupancy.clear(target);
+            growth.0 += 1;
+            food_eaten.write(FoodEaten(step.head));
+            ate = true;
+        }
+
+        if step.growing {
+            // Grow: a new piece fills the vacated cell and the tail stays put
+            growth.0 -= 1;
+            let material = material_query
+                .get(step.head)
+                .map_or_else(|_| render.material(player), |material| material.0.clone());
+            let segment = spawn_segment(&mut commands, &render, material, vacated);
+            segments.0.push_front(segment);
+            occupancy.set(vacated, segment, CellContent::Body);
+        } else if let Some(tail) = segments.0.pop_back() {
+            // Move only the tail piece into the vacated cell
+            if let Ok((mut tail_transform, mut tail_cell)) = segment_query.get_mut(tail) {
+                occupancy.remove(tail_cell.0, tail);
+                tail_cell.0 = vacated;
+                tail_transform.translation = cell_to_world(vacated, SNAKE_Z);
             }
+            segments.0.push_front(tail);
+            occupancy.set(vacated, tail, CellContent::Body);
+        } else {
+            occupancy.remove(vacated, step.head);
+        }
 
-            head.next_move_timer.reset();
+        // Move head
+        head_cell.0 = target;
+        head_transform.translation = cell_to_world(target, SNAKE_Z);
+        occupancy.set(target, step.head, CellContent::Head);
+
+        if ate {
+            spawn_food(
+                &mut commands,
+                &assets,
+                &mut occupancy,
+                &food_zones,
+                target,
+                rules.wrap_edges,
+            );
         }
     }
 }
@@ -382,100 +838,6 @@ fn teardown_game_object(mut commands: Commands, query: Query<Entity, With<InGame
     }
 }
 
-pub fn player_input(keyboard_input: Res<ButtonInput<KeyCode>>, mut query: Query<&mut SnakeHead>) {
-    for mut head in query.iter_mut() {
-        if keyboard_input.just_pressed(KeyCode::ArrowLeft) && head.direction != Direction::Right {
-            head.direction = Direction::Left;
-        } else if keyboard_input.just_pressed(KeyCode::ArrowRight)
-            && head.direction != Direction::Left
-        {
-            head.direction = Direction::Right;
-        } else if keyboard_input.just_pressed(KeyCode::ArrowUp) && head.direction != Direction::Down
-        {
-            head.direction = Direction::Up;
-        } else if keyboard_input.just_pressed(KeyCode::ArrowDown) && head.direction != Direction::Up
-        {
-            head.direction = Direction::Down;
-        }
-    }
-}
-// pub fn player_movement(
-//     time: Res<Time>,
-//     // mut query: Query<(&mut Transform, &Direction), With<Player>>,
-//     mut snake_segments: ResMut<SnakeSegments>,
-//     mut segment_query: Query<&mut Transform, With<SnakeSegment>>,
-//     direction_query: Query<&Direction, With<Player>>,
-//     window: Query<&Window>,
-//     mut next_state: ResMut<NextState<IsPaused>>,
-// ) {
-//     // if user touch the border of the window change the state to GameOver
-//     let window = window.single().unwrap();
-//     let window_h = window.resolution.height();
-//     let window_w = window.resolution.width();
-
-//     // Don't do anything if no segments or no direction
-//     if snake_segments.0.is_empty() {
-//         return;
-//     }
-
-//     let head_entity = snake_segments.0[0];
-
-//     let direction = direction_query.get(head_entity).ok();
-//     if direction.is_none() {
-//         return;
-//     }
-
-//     let direction = direction.unwrap();
-
-//     let mut positions: Vec<Vec3> = Vec::with_capacity(snake_segments.0.len());
-
-//     if let Ok(mut transform) = segment_query.get_mut(head_entity) {
-//         let mut translation = transform.translation;
-//         match direction.0 {
-//             DIRECTION::Right => translation.x += SPEED * time.delta_secs(),
-//             DIRECTION::Up => translation.y += SPEED * time.delta_secs(),
-//             DIRECTION::Down => translation.y -= SPEED * time.delta_secs(),
-//             DIRECTION::Left => translation.x -= SPEED * time.delta_secs(),
-//         }
-//         positions.push(transform.translation); // store old position
-//         transform.translation = translation;
-
-//         // Assume player size is 40x40 (from spawn_player)
-//         let half_player_size = 20.0;
-//         if transform.translation.x.abs() + half_player_size > window_w / 2.0
-//             || transform.translation.y.abs() + half_player_size > window_h / 2.0
-//         {
-//             next_state.set(IsPaused::GameOver);
-//         }
-//     }
-
-//     // Step 2: collect previous positions first to avoid mutable/immutable borrow conflict
-//     let mut prev_positions: Vec<Vec3> = Vec::with_capacity(snake_segments.0.len());
-//     for &entity in &snake_segments.0 {
-//         if let Ok(transform) = segment_query.get(entity) {
-//             let offset = match direction.0 {
-//                 DIRECTION::Right => Vec3::new(40.0, 0.0, 0.0),
-//                 DIRECTION::Left => Vec3::new(-40.0, 0.0, 0.0),
-//                 DIRECTION::Up => Vec3::new(0.0, 40.0, 0.0),
-//                 DIRECTION::Down => Vec3::new(0.0, -40.0, 0.0),
-//             };
-
-//             prev_positions.push(transform.translation + offset);
-//         } else {
-//             prev_positions.push(Vec3::ZERO);
-//         }
-//     }
-
-//     // Now update each segment's transform using mutable borrow
-//     for i in 1..snake_segments.0.len() {
-//         let curr = snake_segments.0[i];
-//         let prev_position = prev_positions[i - 1];
-//         if let Ok(mut curr_transform) = segment_query.get_mut(curr) {
-//             curr_transform.translation = prev_position;
-//         }
-//     }
-// }
-
 fn transition_to_ingame(
     mut next_state: ResMut<NextState<AppState>>,
     time: Res<Time>,
@@ -531,3 +893,78 @@ fn transition_to_ingame(
 //         }
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::time::TimeUpdateStrategy;
+
+    #[test]
+    fn growing_adds_no_render_assets() {
+        const GROWTH: u32 = 300;
+        let step = Duration::from_secs_f32(GameMode::Classic.rules().step_seconds);
+
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(step))
+            .init_resource::<Assets<Mesh>>()
+            .init_resource::<Assets<ColorMaterial>>()
+            .init_resource::<SnakeRenderAssets>()
+            .init_resource::<MyAssets>()
+            .init_resource::<NextState<IsPaused>>()
+            .init_resource::<FoodZones>()
+            .init_resource::<Portals>()
+            .insert_resource(GameMode::Classic)
+            .insert_resource(Occupancy::new(Board {
+                half_extent: IVec2::new(200, 2),
+            }))
+            .add_event::<FoodEaten>()
+            .add_systems(Update, snake_movement_system);
+        // A whole step per update
+        app.world_mut()
+            .resource_mut::<Time<Virtual>>()
+            .set_max_delta(step);
+
+        let render = app.world().resource::<SnakeRenderAssets>();
+        let (mesh, material) = (render.mesh.clone(), render.material(PlayerId(0)));
+        let start = IVec2::new(-190, 0);
+        let head = app
+            .world_mut()
+            .spawn((
+                SnakeHead {
+                    direction: Direction::Right,
+                    next_move_timer: Timer::new(step, TimerMode::Repeating),
+                },
+                PlayerId(0),
+                SnakeSegments::default(),
+                PendingGrowth(GROWTH),
+                GridPosition(start),
+                Transform::from_translation(cell_to_world(start, SNAKE_Z)),
+                Mesh2d(mesh),
+                MeshMaterial2d(material),
+            ))
+            .id();
+        let meshes = app.world().resource::<Assets<Mesh>>().len();
+        let materials = app.world().resource::<Assets<ColorMaterial>>().len();
+
+        // Every update after the clock starts is one step
+        for _ in 0..=GROWTH {
+            app.update();
+            if app.world().get::<PendingGrowth>(head).unwrap().0 == 0 {
+                break;
+            }
+        }
+
+        let snake = app.world().entity(head);
+        assert_eq!(snake.get::<PendingGrowth>().unwrap().0, 0);
+        assert_eq!(
+            snake.get::<SnakeSegments>().unwrap().0.len(),
+            GROWTH as usize
+        );
+        assert_eq!(app.world().resource::<Assets<Mesh>>().len(), meshes);
+        assert_eq!(
+            app.world().resource::<Assets<ColorMaterial>>().len(),
+            materials
+        );
+    }
+}
@@ -0,0 +1,419 @@
+use crate::{
+    AppState, IsPaused, MyAssets,
+    board::{
+        board::Board,
+        obstacle::{Obstacle, Wall},
+        occupancy::{CellContent, Occupancy},
+        portal::Portals,
+    },
+    mode::mode::{FoodEaten, GameMode},
+    physics::physics::GameLayer,
+    player::{
+        food::{Food, FoodZones, spawn_food},
+        player::{
+            GRID_SIZE, GridPosition, InGameEntity, MovementMode, PendingGrowth, Player, PlayerId,
+            SNAKE_Z, Score, SnakeRenderAssets, SpawnPoint, cell_to_world,
+        },
+    },
+};
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+/// Forward speed of the head in pixels per second.
+const SPEED: f32 = 140.0;
+
+/// How fast the head turns while a steering key is held, in radians per second.
+const TURN_RATE: f32 = 3.5;
+
+const PIECE_RADIUS: f32 = 14.0;
+
+/// Distance along the path between two consecutive pieces.
+const SEGMENT_SPACING: f32 = 18.0;
+
+/// Pieces right behind the head always touch it, so they go on the neck layer
+/// and never count as a bite.
+const NECK_SEGMENTS: usize = 3;
+
+/// Grid cell containing a world position.
+fn world_to_cell(position: Vec3) -> IVec2 {
+    (position.truncate() / GRID_SIZE).round().as_ivec2()
+}
+
+/// World-space counterpart of [`Board::wrap`], for a head that moves freely.
+fn wrap_world(board: &Board, position: Vec2) -> Vec2 {
+    let min = (board.min().as_vec2() - 0.5) * GRID_SIZE;
+    min + (position - min).rem_euclid(board_size(board))
+}
+
+/// Shortest offset between two points on a wrapping board, the world-space
+/// counterpart of [`Board::delta`].
+fn wrapped_offset(board: &Board, from: Vec2, to: Vec2) -> Vec2 {
+    let size = board_size(board);
+    (to - from + size / 2.0).rem_euclid(size) - size / 2.0
+}
+
+fn board_size(board: &Board) -> Vec2 {
+    Vec2::new(board.width() as f32, board.height() as f32) * GRID_SIZE
+}
+
+#[derive(Component)]
+pub struct SlitherHead;
+
+#[derive(Component)]
+pub struct SlitherSegment {
+    pub index: usize,
+}
+
+/// Body pieces in order, first one right behind the head.
+#[derive(Resource, Default)]
+pub struct SlitherBody(pub Vec<Entity>);
+
+/// Recent head positions, newest first. Pieces are laid out along it at a
+/// fixed arc length, so the body follows the exact path the head took.
+#[derive(Resource, Default)]
+pub struct SlitherTrail(pub VecDeque<TrailPoint>);
+
+#[derive(Clone, Copy, Default)]
+pub struct TrailPoint {
+    pub position: Vec2,
+    /// The head came out of a portal here, the gap to the older point is
+    /// crossed in no distance at all.
+    pub through_portal: bool,
+}
+
+#[derive(Resource)]
+pub struct SlitherMesh(pub Handle<Mesh>);
+
+impl FromWorld for SlitherMesh {
+    fn from_world(world: &mut World) -> Self {
+        Self(
+            world
+                .resource_mut::<Assets<Mesh>>()
+                .add(Circle::new(PIECE_RADIUS)),
+        )
+    }
+}
+
+pub struct SlitherPlugin;
+
+impl Plugin for SlitherPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SlitherBody>()
+            .init_resource::<SlitherTrail>()
+            .init_resource::<SlitherMesh>()
+            .add_systems(
+                OnEnter(AppState::InGame),
+                spawn_slither.run_if(resource_equals(MovementMode::Slither)),
+            )
+            // Kinematic bodies keep integrating while the game is paused unless physics time stops too
+            .add_systems(OnEnter(IsPaused::Running), unpause_physics)
+            .add_systems(OnExit(IsPaused::Running), pause_physics)
+            .add_systems(
+                Update,
+                (
+                    slither_steering,
+                    grow_slither,
+                    slither_follow,
+                    slither_collisions,
+                )
+                    .chain()
+                    .run_if(
+                        in_state(IsPaused::Running).and(resource_equals(MovementMode::Slither)),
+                    ),
+            );
+    }
+}
+
+fn spawn_slither(
+    mut commands: Commands,
+    render: Res<SnakeRenderAssets>,
+    mesh: Res<SlitherMesh>,
+    mut body: ResMut<SlitherBody>,
+    mut trail: ResMut<SlitherTrail>,
+    spawn: Res<SpawnPoint>,
+    mode: Res<GameMode>,
+) {
+    body.0.clear();
+    trail.0.clear();
+
+    let player = PlayerId(0);
+    commands.spawn((
+        SlitherHead,
+        Player,
+        player,
+        PendingGrowth(mode.rules().start_length),
+        Score::default(),
+        InGameEntity,
+        Mesh2d(mesh.0.clone()),
+        MeshMaterial2d(render.material(player)),
+        GridPosition(spawn.cell),
+        Transform::from_translation(cell_to_world(spawn.cell, SNAKE_Z + 1.0)).with_rotation(
+            Quat::from_rotation_z(spawn.direction.delta().as_vec2().to_angle()),
+        ),
+        RigidBody::Kinematic,
+        Collider::circle(PIECE_RADIUS),
+        GameLayer::Head.collision_layers(),
+        CollisionEventsEnabled,
+    ));
+}
+
+fn slither_steering(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut head_query: Query<
+        (&Transform, &mut LinearVelocity, &mut AngularVelocity),
+        With<SlitherHead>,
+    >,
+) {
+    if let Ok((transform, mut velocity, mut angular)) = head_query.single_mut() {
+        let mut turn = 0.0;
+        if keyboard_input.pressed(KeyCode::ArrowLeft) {
+            turn += 1.0;
+        }
+        if keyboard_input.pressed(KeyCode::ArrowRight) {
+            turn -= 1.0;
+        }
+
+        angular.0 = turn * TURN_RATE;
+        velocity.0 = (transform.rotation * Vec3::X).truncate() * SPEED;
+    }
+}
+
+fn grow_slither(
+    mut commands: Commands,
+    mut head_query: Query<(&mut PendingGrowth, &PlayerId), With<SlitherHead>>,
+    mut body: ResMut<SlitherBody>,
+    trail: Res<SlitherTrail>,
+    render: Res<SnakeRenderAssets>,
+    mesh: Res<SlitherMesh>,
+) {
+    let Ok((mut growth, &player)) = head_query.single_mut() else {
+        return;
+    };
+
+    // New pieces start on the oldest trail point and slide into place next frame
+    let start = trail.0.back().copied().unwrap_or_default().position;
+
+    while growth.0 > 0 {
+        growth.0 -= 1;
+        let index = body.0.len();
+        let layer = if index < NECK_SEGMENTS {
+            GameLayer::Neck
+        } else {
+            GameLayer::Body
+        };
+        let segment = commands
+            .spawn((
+                SlitherSegment { index },
+                InGameEntity,
+                Mesh2d(mesh.0.clone()),
+                MeshMaterial2d(render.material(player)),
+                Transform::from_translation(start.extend(SNAKE_Z)),
+                GridPosition(world_to_cell(start.extend(SNAKE_Z))),
+                Collider::circle(PIECE_RADIUS),
+                layer.collision_layers(),
+            ))
+            .id();
+        body.0.push(segment);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn slither_follow(
+    mut head_query: Query<(Entity, &Transform, &mut GridPosition), With<SlitherHead>>,
+    mut segment_query: Query<
+        (&mut Transform, &mut GridPosition),
+        (With<SlitherSegment>, Without<SlitherHead>),
+    >,
+    body: Res<SlitherBody>,
+    mut trail: ResMut<SlitherTrail>,
+    mut occupancy: ResMut<Occupancy>,
+    mode: Res<GameMode>,
+    board: Res<Board>,
+) {
+    let Ok((head_entity, head, mut head_cell)) = head_query.single_mut() else {
+        return;
+    };
+    let head_pos = head.translation.truncate();
+    let wrap = mode.rules().wrap_edges;
+
+    // Sample the path only once the head has moved, so standing still keeps it short
+    if trail
+        .0
+        .front()
+        .is_none_or(|last| last.position.distance(head_pos) >= 1.0)
+    {
+        trail.0.push_front(TrailPoint {
+            position: head_pos,
+            through_portal: false,
+        });
+    }
+
+    // Walk the trail once, dropping each piece at the next multiple of the spacing
+    let mut pieces = body.0.iter();
+    let mut next_piece = pieces.next();
+    let mut wanted = SEGMENT_SPACING;
+    let mut walked = 0.0;
+    let mut used = trail.0.len();
+
+    for (i, pair) in trail.0.iter().zip(trail.0.iter().skip(1)).enumerate() {
+        let (from, to) = (pair.0.position, pair.1.position);
+        // A head that wrapped leaves a jump in the trail, bridge it the short way
+        // round so the body follows through the edge instead of across the screen.
+        // A portal hop is not bridged, the body goes in one end and out the other.
+        let offset = if pair.0.through_portal {
+            Vec2::ZERO
+        } else if wrap {
+            wrapped_offset(&board, from, to)
+        } else {
+            to - from
+        };
+        let step = offset.length();
+
+        while let Some(&entity) = next_piece {
+            if walked + step < wanted {
+                break;
+            }
+            let t = if step > 0.0 {
+                (wanted - walked) / step
+            } else {
+                0.0
+            };
+            if let Ok((mut transform, _)) = segment_query.get_mut(entity) {
+                let mut position = from + offset * t;
+                if wrap {
+                    position = wrap_world(&board, position);
+                }
+                transform.translation = position.extend(SNAKE_Z);
+            }
+            next_piece = pieces.next();
+            wanted += SEGMENT_SPACING;
+        }
+
+        walked += step;
+        if next_piece.is_none() {
+            used = i + 2;
+            break;
+        }
+    }
+
+    // Everything past the last piece is no longer needed
+    trail.0.truncate(used);
+
+    // Pieces overlap, so take the whole snake off the grid before putting it
+    // back on the cells it covers now, head first. Food and walls keep their
+    // cells.
+    occupancy.remove(head_cell.0, head_entity);
+    for &entity in &body.0 {
+        if let Ok((_, cell)) = segment_query.get(entity) {
+            occupancy.remove(cell.0, entity);
+        }
+    }
+    head_cell.0 = world_to_cell(head.translation);
+    if occupancy.is_free(head_cell.0) {
+        occupancy.set(head_cell.0, head_entity, CellContent::Head);
+    }
+    for &entity in &body.0 {
+        if let Ok((transform, mut cell)) = segment_query.get_mut(entity) {
+            cell.0 = world_to_cell(transform.translation);
+            if occupancy.is_free(cell.0) {
+                occupancy.set(cell.0, entity, CellContent::Body);
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn slither_collisions(
+    mut commands: Commands,
+    mut collision_event_reader: EventReader<CollisionStarted>,
+    mut head_query: Query<(Entity, &mut Transform, &mut PendingGrowth), With<SlitherHead>>,
+    segment_query: Query<&SlitherSegment>,
+    food_query: Query<&GridPosition, With<Food>>,
+    blocker_query: Query<(), Or<(With<Obstacle>, With<Wall>)>>,
+    mut occupancy: ResMut<Occupancy>,
+    mut next_state: ResMut<NextState<IsPaused>>,
+    mut food_eaten: EventWriter<FoodEaten>,
+    mut trail: ResMut<SlitherTrail>,
+    food_zones: Res<FoodZones>,
+    portals: Res<Portals>,
+    mode: Res<GameMode>,
+    board: Res<Board>,
+    assets: Res<MyAssets>,
+) {
+    let Ok((head_entity, mut head, mut growth)) = head_query.single_mut() else {
+        return;
+    };
+    let rules = mode.rules();
+
+    // The border works the same as in grid mode
+    if !board.contains(world_to_cell(head.translation)) {
+        if !rules.wrap_edges {
+            next_state.set(IsPaused::GameOver);
+            return;
+        }
+        let wrapped = wrap_world(&board, head.translation.truncate());
+        head.translation = wrapped.extend(head.translation.z);
+    }
+
+    // Same as on the grid, the head comes out one cell past the partner portal
+    // and keeps its heading
+    if let Some(partner) = portals.partner(world_to_cell(head.translation)) {
+        let forward = (head.rotation * Vec3::X).truncate();
+        let mut exit = cell_to_world(partner, 0.0).truncate() + forward * GRID_SIZE;
+        if !board.contains(world_to_cell(exit.extend(0.0))) {
+            if !rules.wrap_edges {
+                next_state.set(IsPaused::GameOver);
+                return;
+            }
+            exit = wrap_world(&board, exit);
+        }
+        head.translation = exit.extend(head.translation.z);
+        trail.0.push_front(TrailPoint {
+            position: exit,
+            through_portal: true,
+        });
+    }
+    let head_cell = world_to_cell(head.translation);
+
+    for CollisionStarted(entity1, entity2) in collision_event_reader.read() {
+        let other = if *entity1 == head_entity {
+            *entity2
+        } else if *entity2 == head_entity {
+            *entity1
+        } else {
+            continue;
+        };
+
+        if blocker_query.contains(other) {
+            next_state.set(IsPaused::GameOver);
+            return;
+        } else if segment_query.contains(other) {
+            if rules.body_collision {
+                next_state.set(IsPaused::GameOver);
+                return;
+            }
+        } else if let Ok(food_cell) = food_query.get(other) {
+            commands.entity(other).despawn();
+            occupancy.clear(food_cell.0);
+            growth.0 += 1;
+            food_eaten.write(FoodEaten(head_entity));
+            spawn_food(
+                &mut commands,
+                &assets,
+                &mut occupancy,
+                &food_zones,
+                head_cell,
+                rules.wrap_edges,
+            );
+        }
+    }
+}
+
+fn pause_physics(mut time: ResMut<Time<Physics>>) {
+    time.pause();
+}
+
+fn unpause_physics(mut time: ResMut<Time<Physics>>) {
+    time.unpause();
+}
@@ -0,0 +1,213 @@
+//! Many headless games in a row with the same settings, for balancing. Runs
+//! inside a windowless app, one game per update, and prints the statistics
+//! once the last game is over.
+
+use crate::{
+    ai::ai::{AiController, Difficulty},
+    board::{board::Board, generator::BoardStyle},
+    mode::mode::{GameMode, ModeRules},
+    player::{bot::BotLink, controller::SnakeController},
+    sim::sim::Sim,
+};
+use bevy::prelude::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Death label of snakes still alive when a game hits the step limit.
+const SURVIVED: &str = "survived";
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum OutputFormat {
+    /// Summary with every game record.
+    #[default]
+    Json,
+    /// One row per snake per game.
+    Csv,
+}
+
+/// What every game of a batch is played with.
+#[derive(Resource, Clone, Debug)]
+pub struct BatchConfig {
+    pub games: u64,
+    /// Seed of the first game, the others count up from it.
+    pub first_seed: u64,
+    pub mode: GameMode,
+    /// The mode's rules with any changes being tried out.
+    pub rules: ModeRules,
+    pub style: BoardStyle,
+    pub board: Board,
+    pub snakes: usize,
+    pub difficulty: Difficulty,
+    /// Outside program playing the first snake instead of the computer.
+    pub bot: Option<BotLink>,
+    /// Steps after which a game is called off, modes without a way to die
+    /// would run forever otherwise.
+    pub max_ticks: u64,
+    pub format: OutputFormat,
+}
+
+/// How one snake did in one game.
+#[derive(Serialize, Clone, Debug)]
+pub struct GameRecord {
+    pub seed: u64,
+    pub snake: usize,
+    pub score: u32,
+    pub apples: u32,
+    pub ticks: u64,
+    pub death: &'static str,
+}
+
+impl GameRecord {
+    pub const CSV_HEADER: &'static str = "seed,snake,score,apples,ticks,death";
+
+    pub fn csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{}",
+            self.seed, self.snake, self.score, self.apples, self.ticks, self.death
+        )
+    }
+}
+
+/// Totals over every snake of every game in a batch.
+#[derive(Serialize, Debug)]
+pub struct BatchStats {
+    pub games: u64,
+    pub mode: &'static str,
+    pub style: &'static str,
+    pub difficulty: &'static str,
+    pub step_seconds: f32,
+    pub mean_score: f64,
+    pub max_score: u32,
+    pub mean_apples: f64,
+    pub mean_ticks: f64,
+    pub max_ticks: u64,
+    /// Mean time survived at the step time of the batch.
+    pub mean_seconds: f64,
+    /// Snakes per death cause.
+    pub deaths: BTreeMap<&'static str, u32>,
+    pub records: Vec<GameRecord>,
+}
+
+impl BatchStats {
+    pub fn new(config: &BatchConfig, records: Vec<GameRecord>) -> Self {
+        let count = records.len().max(1) as f64;
+        let mean_ticks = records.iter().map(|r| r.ticks as f64).sum::<f64>() / count;
+        let mut deaths = BTreeMap::new();
+        for record in &records {
+            *deaths.entry(record.death).or_default() += 1;
+        }
+        Self {
+            games: config.games,
+            mode: config.mode.label(),
+            style: config.style.label(),
+            difficulty: config.difficulty.label(),
+            step_seconds: config.rules.step_seconds,
+            mean_score: records.iter().map(|r| r.score as f64).sum::<f64>() / count,
+            max_score: records.iter().map(|r| r.score).max().unwrap_or(0),
+            mean_apples: records.iter().map(|r| r.apples as f64).sum::<f64>() / count,
+            mean_ticks,
+            max_ticks: records.iter().map(|r| r.ticks).max().unwrap_or(0),
+            mean_seconds: mean_ticks * config.rules.step_seconds as f64,
+            deaths,
+            records,
+        }
+    }
+
+    pub fn print(&self, format: OutputFormat) {
+        match format {
+            OutputFormat::Json => match serde_json::to_string_pretty(self) {
+                Ok(json) => println!("{json}"),
+                Err(err) => eprintln!("Could not write the statistics: {err}"),
+            },
+            OutputFormat::Csv => {
+                println!("{}", GameRecord::CSV_HEADER);
+                for record in &self.records {
+                    println!("{}", record.csv_row());
+                }
+            }
+        }
+    }
+}
+
+/// Plays one game to the end, or to the step limit, and reports every snake.
+pub fn play_game(config: &BatchConfig, seed: u64) -> Vec<GameRecord> {
+    let layout = config.style.generate(config.board, seed);
+    let controllers: Vec<Box<dyn SnakeController>> = (0..config.snakes)
+        .map(|i| match &config.bot {
+            Some(bot) if i == 0 => match bot.connect() {
+                Ok(bot) => Box::new(bot) as Box<dyn SnakeController>,
+                Err(err) => {
+                    eprintln!("Could not connect to the bot, the computer plays instead: {err}");
+                    Box::new(AiController {
+                        difficulty: config.difficulty,
+                    })
+                }
+            },
+            _ => Box::new(AiController {
+                difficulty: config.difficulty,
+            }),
+        })
+        .collect();
+    let mut sim = Sim::new(
+        config.board,
+        layout.as_ref(),
+        config.rules,
+        seed,
+        controllers,
+    );
+    while !sim.is_over() && sim.ticks < config.max_ticks {
+        sim.step();
+    }
+
+    sim.snakes
+        .iter()
+        .enumerate()
+        .map(|(snake, state)| GameRecord {
+            seed,
+            snake,
+            score: state.score,
+            apples: state.apples,
+            ticks: state.ticks,
+            death: state.death.map_or(SURVIVED, |cause| cause.label()),
+        })
+        .collect()
+}
+
+#[derive(Resource, Default)]
+struct BatchProgress {
+    played: u64,
+    records: Vec<GameRecord>,
+    done: bool,
+}
+
+/// Plays the [`BatchConfig`] inserted into the app and exits when done.
+pub struct BatchPlugin;
+
+impl Plugin for BatchPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BatchProgress>()
+            .add_systems(Update, play_batch);
+    }
+}
+
+fn play_batch(
+    config: Res<BatchConfig>,
+    mut progress: ResMut<BatchProgress>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if progress.done {
+        return;
+    }
+    if progress.played < config.games {
+        let seed = config.first_seed.wrapping_add(progress.played);
+        let records = play_game(&config, seed);
+        progress.records.extend(records);
+        progress.played += 1;
+        return;
+    }
+
+    let stats = BatchStats::new(&config, std::mem::take(&mut progress.records));
+    stats.print(config.format);
+    progress.done = true;
+    exit.write(AppExit::Success);
+}
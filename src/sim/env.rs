@@ -0,0 +1,398 @@
+//! Training environment in the usual reset/step shape, on top of [`Sim`] so an
+//! agent learns the real rules. The agent drives the first snake, optional
+//! computer snakes fill the other slots.
+//!
+//! ```
+//! use bevy_movment::sim::env::{Action, Env, EnvConfig};
+//!
+//! let mut env = Env::new(EnvConfig::default());
+//! let mut observation = env.reset(7);
+//! loop {
+//!     let (next, _reward, done, _info) = env.step(Action::Straight);
+//!     observation = next;
+//!     if done {
+//!         break;
+//!     }
+//! }
+//! # let _ = observation;
+//! ```
+
+use crate::{
+    ai::ai::{AiController, Difficulty},
+    board::{board::Board, generator::BoardStyle, occupancy::CellContent},
+    mode::mode::GameMode,
+    player::{
+        controller::{RemoteController, RemoteInbox, SnakeController},
+        player::Direction,
+    },
+    sim::{rules::DeathCause, sim::Sim},
+};
+use bevy::prelude::*;
+
+/// Ray directions, the four straight ones first, then the diagonals.
+const RAYS: [IVec2; 8] = [
+    IVec2::new(1, 0),
+    IVec2::new(0, 1),
+    IVec2::new(-1, 0),
+    IVec2::new(0, -1),
+    IVec2::new(1, 1),
+    IVec2::new(-1, 1),
+    IVec2::new(-1, -1),
+    IVec2::new(1, -1),
+];
+
+/// Turn relative to where the agent's snake heads, so there is no move that
+/// doubles back on itself.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    Straight,
+    Left,
+    Right,
+}
+
+impl Action {
+    pub const ALL: [Action; 3] = [Action::Straight, Action::Left, Action::Right];
+
+    /// Action for an index into [`Action::ALL`], for agents that output one.
+    pub fn from_index(index: usize) -> Option<Self> {
+        Self::ALL.get(index).copied()
+    }
+
+    /// Direction a snake heading `heading` ends up going.
+    pub fn direction(self, heading: Direction) -> Direction {
+        // Direction::ALL runs counter-clockwise
+        let i = Direction::ALL.iter().position(|d| *d == heading).unwrap();
+        match self {
+            Action::Straight => heading,
+            Action::Left => Direction::ALL[(i + 1) % 4],
+            Action::Right => Direction::ALL[(i + 3) % 4],
+        }
+    }
+}
+
+/// How the board is handed to the agent.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Encoding {
+    /// One board-sized plane per [`GridChannel`], shape `[channels, height,
+    /// width]` with row 0 at the bottom of the board.
+    #[default]
+    Grid,
+    /// Eight rays from the head, straight ones first, each giving the inverse
+    /// distance to the nearest blocker, snake piece and apple, followed by the
+    /// heading one-hot in [`Direction::ALL`] order. Shape `[28]`.
+    Rays,
+}
+
+/// Planes of the [`Encoding::Grid`] observation, in order.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GridChannel {
+    Head,
+    Body,
+    /// Heads and bodies of the other snakes.
+    Rivals,
+    Food,
+    /// Walls, obstacles and portals.
+    Blocked,
+}
+
+impl GridChannel {
+    pub const ALL: [GridChannel; 5] = [
+        GridChannel::Head,
+        GridChannel::Body,
+        GridChannel::Rivals,
+        GridChannel::Food,
+        GridChannel::Blocked,
+    ];
+}
+
+/// Reward for each thing that can happen in a step, added up.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RewardShaping {
+    pub food: f32,
+    pub death: f32,
+    /// Paid every step the agent survives, usually a small penalty so it does
+    /// not circle forever.
+    pub step: f32,
+    /// Paid per cell the head gets closer to the nearest apple, and taken
+    /// away per cell it moves off.
+    pub approach: f32,
+}
+
+impl Default for RewardShaping {
+    fn default() -> Self {
+        Self {
+            food: 1.0,
+            death: -1.0,
+            step: -0.01,
+            approach: 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EnvConfig {
+    pub board: Board,
+    pub style: BoardStyle,
+    pub mode: GameMode,
+    /// Computer snakes playing against the agent.
+    pub opponents: Vec<Difficulty>,
+    pub rewards: RewardShaping,
+    pub encoding: Encoding,
+    /// Steps after which an episode is cut off.
+    pub max_ticks: u64,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        Self {
+            board: Board {
+                half_extent: IVec2::new(7, 7),
+            },
+            style: BoardStyle::Open,
+            mode: GameMode::Classic,
+            opponents: Vec::new(),
+            rewards: RewardShaping::default(),
+            encoding: Encoding::Grid,
+            max_ticks: 5_000,
+        }
+    }
+}
+
+/// Flat tensor, `data` is row-major in `shape`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Observation {
+    pub shape: Vec<usize>,
+    pub data: Vec<f32>,
+}
+
+/// Extra detail about a step, for logging.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct StepInfo {
+    pub score: u32,
+    pub apples: u32,
+    pub ticks: u64,
+    pub death: Option<DeathCause>,
+    /// The episode hit [`EnvConfig::max_ticks`] with the agent still alive.
+    pub truncated: bool,
+}
+
+pub struct Env {
+    pub config: EnvConfig,
+    sim: Sim,
+    inbox: RemoteInbox,
+}
+
+impl Env {
+    /// Environment ready to play, already reset with seed 0.
+    pub fn new(config: EnvConfig) -> Self {
+        let (sim, inbox) = Self::start(&config, 0);
+        Self { config, sim, inbox }
+    }
+
+    /// The game being played, for rendering or extra features.
+    pub fn sim(&self) -> &Sim {
+        &self.sim
+    }
+
+    /// Starts a new episode, the seed decides the board and every apple.
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        (self.sim, self.inbox) = Self::start(&self.config, seed);
+        self.observe()
+    }
+
+    /// Plays one step with the agent's action. Once `done` is returned the
+    /// episode has to be reset.
+    pub fn step(&mut self, action: Action) -> (Observation, f32, bool, StepInfo) {
+        let rewards = self.config.rewards;
+        let before = self.food_distance();
+        let apples = self.sim.snakes[0].apples;
+
+        self.inbox
+            .push(action.direction(self.sim.snakes[0].direction));
+        self.sim.step();
+
+        let agent = &self.sim.snakes[0];
+        let mut reward = 0.0;
+        if agent.alive() {
+            reward += rewards.step;
+        } else {
+            reward += rewards.death;
+        }
+        reward += (agent.apples - apples) as f32 * rewards.food;
+        if let (Some(before), Some(after)) = (before, self.food_distance())
+            && agent.apples == apples
+        {
+            reward += (before - after) as f32 * rewards.approach;
+        }
+
+        let truncated = agent.alive() && self.sim.ticks >= self.config.max_ticks;
+        let info = StepInfo {
+            score: agent.score,
+            apples: agent.apples,
+            ticks: agent.ticks,
+            death: agent.death,
+            truncated,
+        };
+        let done = !agent.alive() || truncated;
+        (self.observe(), reward, done, info)
+    }
+
+    fn start(config: &EnvConfig, seed: u64) -> (Sim, RemoteInbox) {
+        let (agent, inbox) = RemoteController::new();
+        let mut controllers: Vec<Box<dyn SnakeController>> = vec![Box::new(agent)];
+        controllers.extend(
+            config.opponents.iter().map(|&difficulty| {
+                Box::new(AiController { difficulty }) as Box<dyn SnakeController>
+            }),
+        );
+        let layout = config.style.generate(config.board, seed);
+        let sim = Sim::new(
+            config.board,
+            layout.as_ref(),
+            config.mode.rules(),
+            seed,
+            controllers,
+        );
+        (sim, inbox)
+    }
+
+    /// Steps from the agent's head to the nearest apple, `None` without one
+    /// or once the agent is dead.
+    fn food_distance(&self) -> Option<i32> {
+        let agent = &self.sim.snakes[0];
+        if !agent.alive() {
+            return None;
+        }
+        let board = self.sim.board();
+        self.sim
+            .food
+            .iter()
+            .map(|&food| board.distance(agent.head(), food, self.sim.rules.wrap_edges))
+            .min()
+    }
+
+    pub fn observe(&self) -> Observation {
+        match self.config.encoding {
+            Encoding::Grid => self.grid(),
+            Encoding::Rays => self.rays(),
+        }
+    }
+
+    fn grid(&self) -> Observation {
+        let board = self.sim.board();
+        let plane = (board.width() * board.height()) as usize;
+        let mut data = vec![0.0; plane * GridChannel::ALL.len()];
+        let mut mark = |channel: GridChannel, cell: IVec2| {
+            if let Some(i) = board.index(cell) {
+                data[channel as usize * plane + i] = 1.0;
+            }
+        };
+
+        for cell in board.cells() {
+            if let Some(occupant) = self.sim.occupancy.get(cell)
+                && matches!(
+                    occupant.content,
+                    CellContent::Wall | CellContent::Obstacle | CellContent::Portal
+                )
+            {
+                mark(GridChannel::Blocked, cell);
+            }
+        }
+        for &cell in &self.sim.food {
+            mark(GridChannel::Food, cell);
+        }
+        for (i, snake) in self.sim.snakes.iter().enumerate() {
+            if !snake.alive() {
+                continue;
+            }
+            for (piece, &cell) in snake.body.iter().enumerate() {
+                let channel = match (i, piece) {
+                    (0, 0) => GridChannel::Head,
+                    (0, _) => GridChannel::Body,
+                    _ => GridChannel::Rivals,
+                };
+                mark(channel, cell);
+            }
+        }
+
+        Observation {
+            shape: vec![
+                GridChannel::ALL.len(),
+                board.height() as usize,
+                board.width() as usize,
+            ],
+            data,
+        }
+    }
+
+    fn rays(&self) -> Observation {
+        let board = self.sim.board();
+        let wrap = self.sim.rules.wrap_edges;
+        let agent = &self.sim.snakes[0];
+        let reach = board.width().max(board.height());
+        let mut data = Vec::with_capacity(RAYS.len() * 3 + Direction::ALL.len());
+
+        for ray in RAYS {
+            // Inverse distance to the first blocker, snake piece and apple
+            let (mut blocker, mut snake, mut food) = (0.0, 0.0, 0.0);
+            let mut cell = agent.head();
+            for distance in 1..=reach {
+                cell += ray;
+                if wrap {
+                    cell = board.wrap(cell);
+                }
+                // Round a wrapping board and back, the ray saw all there is
+                if cell == agent.head() {
+                    break;
+                }
+                let near = 1.0 / distance as f32;
+                let Some(occupant) = self.sim.occupancy.get(cell) else {
+                    if !board.contains(cell) {
+                        blocker = near;
+                        break;
+                    }
+                    continue;
+                };
+                match occupant.content {
+                    CellContent::Food if food == 0.0 => food = near,
+                    CellContent::Head | CellContent::Body if snake == 0.0 => snake = near,
+                    CellContent::Wall | CellContent::Obstacle | CellContent::Portal => {
+                        blocker = near;
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            data.extend([blocker, snake, food]);
+        }
+        data.extend(
+            Direction::ALL
+                .iter()
+                .map(|d| if *d == agent.direction { 1.0 } else { 0.0 }),
+        );
+
+        Observation {
+            shape: vec![data.len()],
+            data,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rays_do_not_see_their_own_head_round_a_wrapping_board() {
+        let mut env = Env::new(EnvConfig {
+            mode: GameMode::Zen,
+            encoding: Encoding::Rays,
+            ..default()
+        });
+        let observation = env.reset(3);
+        assert_eq!(observation.shape, vec![RAYS.len() * 3 + Direction::ALL.len()]);
+        for ray in 0..RAYS.len() {
+            assert_eq!(observation.data[ray * 3 + 1], 0.0, "ray {ray}");
+        }
+    }
+}
@@ -0,0 +1,6 @@
+pub mod batch;
+pub mod env;
+pub mod replay;
+pub mod rules;
+pub mod sim;
+pub mod tournament;
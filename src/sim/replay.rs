@@ -0,0 +1,105 @@
+//! Recorded games. The seed rebuilds the board and every apple, so the
+//! direction of each snake after every step is all it takes to play a game
+//! again.
+
+use crate::{
+    board::{board::Board, generator::BoardStyle},
+    mode::mode::GameMode,
+    player::{
+        controller::{ReplayController, SnakeController},
+        player::Direction,
+    },
+    sim::sim::Sim,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Replay {
+    pub seed: u64,
+    pub mode: GameMode,
+    pub style: BoardStyle,
+    pub board: Board,
+    /// Who played each snake, in snake order.
+    pub players: Vec<String>,
+    /// One row per step with the direction every snake went.
+    pub moves: Vec<Vec<Direction>>,
+}
+
+impl Replay {
+    pub fn new(
+        seed: u64,
+        mode: GameMode,
+        style: BoardStyle,
+        board: Board,
+        players: Vec<String>,
+    ) -> Self {
+        Self {
+            seed,
+            mode,
+            style,
+            board,
+            players,
+            moves: Vec::new(),
+        }
+    }
+
+    /// Notes down the step `sim` just took.
+    pub fn record(&mut self, sim: &Sim) {
+        self.moves
+            .push(sim.snakes.iter().map(|snake| snake.direction).collect());
+    }
+    /// A sim set up like the recorded one, with every snake playing back its
+    /// moves. Stepping it through all of them ends on the recorded game.
+    pub fn sim(&self) -> Sim {
+        let controllers: Vec<Box<dyn SnakeController>> = (0..self.players.len())
+            .map(|i| {
+                let steps = self.moves.iter().map(move |step| step.get(i).copied());
+                Box::new(ReplayController::new(steps)) as Box<dyn SnakeController>
+            })
+            .collect();
+        let layout = self.style.generate(self.board, self.seed);
+        Sim::new(
+            self.board,
+            layout.as_ref(),
+            self.mode.rules(),
+            self.seed,
+            controllers,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::ai::{AiController, Difficulty};
+    use bevy::math::IVec2;
+
+    #[test]
+    fn playing_a_replay_ends_on_the_recorded_game() {
+        let board = Board {
+            half_extent: IVec2::new(10, 7),
+        };
+        let (seed, mode, style) = (3, GameMode::Versus, BoardStyle::Rocks);
+        let players = vec!["safe".to_string(), "greedy".to_string()];
+        let controllers: Vec<Box<dyn SnakeController>> = [Difficulty::Safe, Difficulty::Greedy]
+            .into_iter()
+            .map(|difficulty| Box::new(AiController { difficulty }) as Box<dyn SnakeController>)
+            .collect();
+        let layout = style.generate(board, seed);
+        let mut sim = Sim::new(board, layout.as_ref(), mode.rules(), seed, controllers);
+        let mut replay = Replay::new(seed, mode, style, board, players);
+        while !sim.is_over() && sim.ticks < 400 {
+            sim.step();
+            replay.record(&sim);
+        }
+
+        let json = serde_json::to_string(&replay).unwrap();
+        let replay: Replay = serde_json::from_str(&json).unwrap();
+        let mut played = replay.sim();
+        for _ in &replay.moves {
+            played.step();
+        }
+        assert_eq!(played.ticks, sim.ticks);
+        assert_eq!(played.checksum(), sim.checksum());
+    }
+}
@@ -0,0 +1,218 @@
+//! Grid movement rules shared by the game and the headless simulation, so both
+//! agree on where a head goes and what kills it.
+
+use crate::{
+    board::{
+        board::Board,
+        occupancy::{CellContent, Occupancy, Occupant},
+        portal::Portals,
+    },
+    mode::mode::ModeRules,
+    player::player::Direction,
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Why a snake stopped.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeathCause {
+    Border,
+    Wall,
+    Obstacle,
+    /// Came out of a portal straight into another one.
+    Portal,
+    /// Ran into a snake body, its own or another one.
+    Body,
+    /// Met another head.
+    HeadOn,
+    /// The clock of a timed mode ran out.
+    OutOfTime,
+}
+
+impl DeathCause {
+    pub fn label(self) -> &'static str {
+        match self {
+            DeathCause::Border => "border",
+            DeathCause::Wall => "wall",
+            DeathCause::Obstacle => "obstacle",
+            DeathCause::Portal => "portal",
+            DeathCause::Body => "body",
+            DeathCause::HeadOn => "head_on",
+            DeathCause::OutOfTime => "out_of_time",
+        }
+    }
+}
+
+/// Cell a head on `cell` lands on when it moves in `direction`: one step on,
+/// folded back onto the board when edges wrap, and out of the partner portal
+/// when the step lands on one. Can be off the board.
+pub fn next_cell(
+    board: Board,
+    portals: &Portals,
+    wrap: bool,
+    cell: IVec2,
+    direction: Direction,
+) -> IVec2 {
+    let fold = |cell: IVec2| if wrap { board.wrap(cell) } else { cell };
+    let next = fold(cell + direction.delta());
+    // Stepping into a portal carries on out of its partner, the body simply
+    // follows the cells the head left behind
+    portals.exit(next, direction).map_or(next, fold)
+}
+
+/// A step one snake is about to take.
+#[derive(Clone, Copy, Debug)]
+pub struct PlannedStep {
+    pub target: IVec2,
+    /// Cell the tail leaves during the step, `None` while growing. A head may
+    /// follow straight into it.
+    pub moving_tail: Option<IVec2>,
+}
+
+/// What each of the steps taken at the same time runs into, `None` for the
+/// ones that survive. Leaving the board, hitting a wall, obstacle or portal
+/// is always deadly, snakes only as far as the mode allows.
+pub fn resolve_crashes(
+    occupancy: &Occupancy,
+    rules: &ModeRules,
+    steps: &[PlannedStep],
+) -> Vec<Option<DeathCause>> {
+    let moving_tails: Vec<IVec2> = steps.iter().filter_map(|step| step.moving_tail).collect();
+
+    steps
+        .iter()
+        .enumerate()
+        .map(|(i, step)| {
+            if !occupancy.board().contains(step.target) {
+                return Some(DeathCause::Border);
+            }
+            match occupancy.get(step.target) {
+                Some(Occupant {
+                    content: CellContent::Wall,
+                    ..
+                }) => return Some(DeathCause::Wall),
+                Some(Occupant {
+                    content: CellContent::Obstacle,
+                    ..
+                }) => return Some(DeathCause::Obstacle),
+                Some(Occupant {
+                    content: CellContent::Portal,
+                    ..
+                }) => return Some(DeathCause::Portal),
+                _ => {}
+            }
+            if !rules.body_collision {
+                return None;
+            }
+
+            // Two heads entering the same cell take each other out
+            let same_target = steps
+                .iter()
+                .enumerate()
+                .any(|(j, other)| j != i && other.target == step.target);
+            match occupancy.get(step.target) {
+                Some(Occupant {
+                    content: CellContent::Head,
+                    ..
+                }) => Some(DeathCause::HeadOn),
+                _ if same_target => Some(DeathCause::HeadOn),
+                Some(Occupant {
+                    content: CellContent::Body,
+                    ..
+                }) if !moving_tails.contains(&step.target) => Some(DeathCause::Body),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mode::mode::GameMode;
+
+    const BOARD: Board = Board {
+        half_extent: IVec2::new(3, 3),
+    };
+
+    fn step(target: IVec2) -> PlannedStep {
+        PlannedStep {
+            target,
+            moving_tail: None,
+        }
+    }
+
+    #[test]
+    fn heads_wrap_or_leave_the_board() {
+        let portals = Portals::default();
+        let edge = IVec2::new(3, 0);
+        assert_eq!(
+            next_cell(BOARD, &portals, false, edge, Direction::Right),
+            IVec2::new(4, 0)
+        );
+        assert_eq!(
+            next_cell(BOARD, &portals, true, edge, Direction::Right),
+            IVec2::new(-3, 0)
+        );
+    }
+
+    #[test]
+    fn portals_carry_heads_on_out_of_their_partner() {
+        let mut portals = Portals::default();
+        portals.link(IVec2::new(1, 0), IVec2::new(-2, 2));
+        let next = next_cell(BOARD, &portals, false, IVec2::ZERO, Direction::Right);
+        assert_eq!(next, IVec2::new(-1, 2));
+    }
+
+    #[test]
+    fn crashes_follow_the_mode() {
+        let mut occupancy = Occupancy::new(BOARD);
+        let body = IVec2::new(0, 1);
+        let tail = IVec2::new(0, -1);
+        occupancy.set(body, Entity::from_raw(1), CellContent::Body);
+        occupancy.set(tail, Entity::from_raw(1), CellContent::Body);
+        occupancy.set(IVec2::new(2, 2), Entity::PLACEHOLDER, CellContent::Wall);
+
+        let steps = [
+            step(IVec2::new(4, 0)),
+            step(IVec2::new(2, 2)),
+            step(body),
+            PlannedStep {
+                target: IVec2::new(1, 1),
+                moving_tail: Some(tail),
+            },
+            step(tail),
+        ];
+        let classic = resolve_crashes(&occupancy, &GameMode::Classic.rules(), &steps);
+        assert_eq!(
+            classic,
+            [
+                Some(DeathCause::Border),
+                Some(DeathCause::Wall),
+                Some(DeathCause::Body),
+                None,
+                // The tail moves on during the same step
+                None,
+            ]
+        );
+
+        // Only the board and scenery kill in zen
+        let zen = resolve_crashes(&occupancy, &GameMode::Zen.rules(), &steps);
+        assert_eq!(
+            zen[..3],
+            [Some(DeathCause::Border), Some(DeathCause::Wall), None]
+        );
+    }
+
+    #[test]
+    fn heads_meeting_on_one_cell_both_die() {
+        let occupancy = Occupancy::new(BOARD);
+        let steps = [step(IVec2::ZERO), step(IVec2::ZERO), step(IVec2::ONE)];
+        let crashes = resolve_crashes(&occupancy, &GameMode::Versus.rules(), &steps);
+        assert_eq!(
+            crashes,
+            [Some(DeathCause::HeadOn), Some(DeathCause::HeadOn), None]
+        );
+    }
+}
@@ -0,0 +1,531 @@
+//! The grid game without the engine: the board, the snakes and their
+//! controllers in one struct that is stepped by hand. Nothing here touches the
+//! world, so it can run behind the menu, in batch runs or for training as fast
+//! as the caller likes. The movement rules come from `rules.rs`, the same ones
+//! the game uses.
+
+use crate::{
+    board::{
+        board::Board,
+        occupancy::{CellContent, Occupancy},
+        portal::Portals,
+    },
+    level::layout::LevelLayout,
+    mode::mode::ModeRules,
+    player::{
+        controller::{SnakeController, SnakeView},
+        food::{FoodZones, pick_food_cell},
+        player::{Direction, SpawnPoint, player_spawns},
+    },
+    sim::rules::{DeathCause, PlannedStep, next_cell, resolve_crashes},
+};
+use bevy::prelude::*;
+use rand::{SeedableRng, rngs::StdRng};
+use std::collections::VecDeque;
+
+/// Stands in for walls, obstacles and portals on the occupancy grid.
+const SCENERY: Entity = Entity::PLACEHOLDER;
+
+/// Stands in for apples, only the cell content matters for them.
+const FOOD: Entity = Entity::from_raw(u32::MAX - 1);
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// FNV-1a over fixed-width little-endian fields. Neither the std hasher nor
+/// derived `Hash`, which writes `usize` lengths, give the same bytes on every
+/// platform, this does, so peers on different builds get equal checksums.
+struct Fnv(u64);
+
+impl Fnv {
+    fn bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.bytes(&[value]);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn cells<'a>(&mut self, cells: impl ExactSizeIterator<Item = &'a IVec2>) {
+        self.u64(cells.len() as u64);
+        for cell in cells {
+            self.u32(cell.x as u32);
+            self.u32(cell.y as u32);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SimSnake {
+    /// Stands in for all of the snake's pieces on the occupancy grid.
+    pub id: Entity,
+    /// Own cells, head first.
+    pub body: VecDeque<IVec2>,
+    pub direction: Direction,
+    pub growth: u32,
+    pub score: u32,
+    pub apples: u32,
+    /// Steps survived.
+    pub ticks: u64,
+    pub death: Option<DeathCause>,
+}
+
+impl SimSnake {
+    pub fn head(&self) -> IVec2 {
+        self.body[0]
+    }
+
+    pub fn alive(&self) -> bool {
+        self.death.is_none()
+    }
+
+    /// Direction the snake heads after being given `turn`. Turning straight
+    /// back is ignored once there is a body to run into.
+    pub fn turned(&self, turn: Option<Direction>) -> Direction {
+        match turn {
+            Some(direction) if self.body.len() == 1 || direction != self.direction.opposite() => {
+                direction
+            }
+            _ => self.direction,
+        }
+    }
+}
+
+/// Heads of the living snakes other than snake `i`.
+fn rival_heads(snakes: &[SimSnake], i: usize) -> Vec<IVec2> {
+    snakes
+        .iter()
+        .enumerate()
+        .filter(|(j, snake)| *j != i && snake.alive())
+        .map(|(_, snake)| snake.head())
+        .collect()
+}
+
+pub struct Sim {
+    pub occupancy: Occupancy,
+    pub portals: Portals,
+    pub food_zones: FoodZones,
+    pub rules: ModeRules,
+    pub snakes: Vec<SimSnake>,
+    pub food: Vec<IVec2>,
+    pub ticks: u64,
+    /// Seconds left for modes with a time limit.
+    pub time_left: Option<f32>,
+    rng: StdRng,
+    /// One per snake, in the same order.
+    controllers: Vec<Box<dyn SnakeController>>,
+}
+
+/// Everything in a [`Sim`] that changes while playing, to go back to later.
+#[derive(Clone)]
+pub struct SimState {
+    occupancy: Occupancy,
+    snakes: Vec<SimSnake>,
+    food: Vec<IVec2>,
+    ticks: u64,
+    time_left: Option<f32>,
+    rng: StdRng,
+}
+
+impl SimState {
+    pub fn snakes(&self) -> &[SimSnake] {
+        &self.snakes
+    }
+}
+
+impl Sim {
+    /// Fresh game on `layout`, or on an empty `board` without one. There is a
+    /// snake per controller, in player order, and the seed decides where every
+    /// apple lands.
+    pub fn new(
+        board: Board,
+        layout: Option<&LevelLayout>,
+        rules: ModeRules,
+        seed: u64,
+        controllers: Vec<Box<dyn SnakeController>>,
+    ) -> Self {
+        let board = layout.map_or(board, |layout| layout.board);
+        let mut occupancy = Occupancy::new(board);
+        let mut portals = Portals::default();
+        let mut food_zones = FoodZones::default();
+        let mut spawn = SpawnPoint::default();
+        if let Some(layout) = layout {
+            for &cell in &layout.walls {
+                occupancy.set(cell, SCENERY, CellContent::Wall);
+            }
+            for &cell in &layout.obstacles {
+                occupancy.set(cell, SCENERY, CellContent::Obstacle);
+            }
+            for &(a, b) in &layout.portals {
+                occupancy.set(a, SCENERY, CellContent::Portal);
+                occupancy.set(b, SCENERY, CellContent::Portal);
+                portals.link(a, b);
+            }
+            spawn = SpawnPoint {
+                cell: layout.spawn,
+                direction: layout.spawn_direction,
+            };
+            food_zones.0.clone_from(&layout.food_zones);
+        }
+
+        let snakes = player_spawns(&occupancy, spawn, controllers.len())
+            .into_iter()
+            .enumerate()
+            .map(|(i, spawn)| {
+                let id = Entity::from_raw(i as u32);
+                occupancy.set(spawn.cell, id, CellContent::Head);
+                SimSnake {
+                    id,
+                    body: VecDeque::from([spawn.cell]),
+                    direction: spawn.direction,
+                    growth: rules.start_length,
+                    score: 0,
+                    apples: 0,
+                    ticks: 0,
+                    death: None,
+                }
+            })
+            .collect();
+
+        let mut sim = Self {
+            occupancy,
+            portals,
+            food_zones,
+            rules,
+            snakes,
+            food: Vec::new(),
+            ticks: 0,
+            time_left: rules.time_limit,
+            rng: StdRng::seed_from_u64(seed),
+            controllers,
+        };
+        sim.spawn_food(spawn.cell);
+        sim
+    }
+
+    pub fn board(&self) -> Board {
+        self.occupancy.board()
+    }
+
+    /// Over once no snake is left.
+    pub fn is_over(&self) -> bool {
+        self.snakes.iter().all(|snake| !snake.alive())
+    }
+
+    /// Snake ahead right now: the last one alive, or with none or several left
+    /// the one with the most points. `None` on a tie.
+    pub fn leader(&self) -> Option<usize> {
+        let mut alive = (0..self.snakes.len()).filter(|&i| self.snakes[i].alive());
+        if let (Some(last), None) = (alive.next(), alive.next()) {
+            return Some(last);
+        }
+        let best = self.snakes.iter().map(|snake| snake.score).max()?;
+        let mut leaders = (0..self.snakes.len()).filter(|&i| self.snakes[i].score == best);
+        match (leaders.next(), leaders.next()) {
+            (Some(leader), None) => Some(leader),
+            _ => None,
+        }
+    }
+
+    /// What the controller of snake `i` would see right now.
+    pub fn view(&self, i: usize) -> SnakeView<'_> {
+        let snake = &self.snakes[i];
+        SnakeView {
+            entity: snake.id,
+            direction: snake.direction,
+            body: &snake.body,
+            growing: snake.growth > 0,
+            food: &self.food,
+            occupancy: &self.occupancy,
+            portals: &self.portals,
+            wrap: self.rules.wrap_edges,
+            rival_heads: rival_heads(&self.snakes, i),
+        }
+    }
+
+    /// Copy of the game as it is now.
+    pub fn save(&self) -> SimState {
+        SimState {
+            occupancy: self.occupancy.clone(),
+            snakes: self.snakes.clone(),
+            food: self.food.clone(),
+            ticks: self.ticks,
+            time_left: self.time_left,
+            rng: self.rng.clone(),
+        }
+    }
+
+    /// Puts the game back the way it was saved. Controllers keep whatever
+    /// they remember.
+    pub fn load(&mut self, state: &SimState) {
+        self.occupancy.clone_from(&state.occupancy);
+        self.snakes.clone_from(&state.snakes);
+        self.food.clone_from(&state.food);
+        self.ticks = state.ticks;
+        self.time_left = state.time_left;
+        self.rng.clone_from(&state.rng);
+    }
+
+    /// Hash of everything that changes while playing. Two copies of a game fed
+    /// the same turns agree on it, so peers compare it to catch a desync.
+    pub fn checksum(&self) -> u64 {
+        let mut hasher = Fnv(FNV_OFFSET);
+        hasher.u64(self.ticks);
+        match self.time_left {
+            Some(time) => {
+                hasher.u8(1);
+                hasher.u32(time.to_bits());
+            }
+            None => hasher.u8(0),
+        }
+        hasher.cells(self.food.iter());
+        for snake in &self.snakes {
+            hasher.cells(snake.body.iter());
+            hasher.u8(snake.direction as u8);
+            hasher.u32(snake.growth);
+            hasher.u32(snake.score);
+            // 0 for alive, so causes are shifted up by one
+            hasher.u8(snake.death.map_or(0, |cause| cause as u8 + 1));
+        }
+        hasher.0
+    }
+
+    /// Moves every living snake one cell, the way one movement tick of the
+    /// game does. Nothing happens once the game is over.
+    pub fn step(&mut self) {
+        if self.is_over() {
+            return;
+        }
+        self.ticks += 1;
+        self.steer();
+
+        let alive: Vec<usize> = (0..self.snakes.len())
+            .filter(|&i| self.snakes[i].alive())
+            .collect();
+        let steps: Vec<PlannedStep> = alive
+            .iter()
+            .map(|&i| {
+                let snake = &self.snakes[i];
+                PlannedStep {
+                    target: next_cell(
+                        self.board(),
+                        &self.portals,
+                        self.rules.wrap_edges,
+                        snake.head(),
+                        snake.direction,
+                    ),
+                    moving_tail: (snake.growth == 0 && snake.body.len() > 1)
+                        .then(|| *snake.body.back().unwrap()),
+                }
+            })
+            .collect();
+        let crashes = resolve_crashes(&self.occupancy, &self.rules, &steps);
+
+        // Crashed snakes leave the board before anyone moves into their cells
+        for (&i, cause) in alive.iter().zip(&crashes) {
+            if let Some(cause) = cause {
+                self.kill(i, *cause);
+            }
+        }
+        for ((&i, step), cause) in alive.iter().zip(&steps).zip(&crashes) {
+            if cause.is_none() {
+                self.advance(i, step.target);
+            }
+        }
+
+        if let Some(time_left) = &mut self.time_left {
+            *time_left -= self.rules.step_seconds;
+            if *time_left <= 0.0 {
+                for i in 0..self.snakes.len() {
+                    if self.snakes[i].alive() {
+                        self.kill(i, DeathCause::OutOfTime);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Asks every living snake's controller for its turn.
+    fn steer(&mut self) {
+        let Sim {
+            snakes,
+            occupancy,
+            portals,
+            food,
+            rules,
+            controllers,
+            ..
+        } = self;
+        for (i, controller) in controllers.iter_mut().enumerate() {
+            if !snakes[i].alive() {
+                continue;
+            }
+            let rival_heads = rival_heads(snakes, i);
+            let snake = &mut snakes[i];
+            let view = SnakeView {
+                entity: snake.id,
+                direction: snake.direction,
+                body: &snake.body,
+                growing: snake.growth > 0,
+                food: food.as_slice(),
+                occupancy: &*occupancy,
+                portals: &*portals,
+                wrap: rules.wrap_edges,
+                rival_heads,
+            };
+            let turn = controller.decide(&view);
+            snake.direction = snake.turned(turn);
+        }
+    }
+
+    fn advance(&mut self, i: usize, target: IVec2) {
+        let snake = &mut self.snakes[i];
+        let vacated = snake.head();
+        let growing = snake.growth > 0;
+        snake.ticks += 1;
+
+        let ate = self.food.contains(&target);
+        if ate {
+            self.occupancy.clear(target);
+            self.food.retain(|cell| *cell != target);
+            snake.growth += 1;
+            snake.apples += 1;
+            snake.score += self.rules.points_per_food;
+            if let Some(time_left) = &mut self.time_left {
+                *time_left += self.rules.bonus_seconds;
+            }
+        }
+
+        // The tail stays put while growing, otherwise it follows the head
+        snake.body.push_front(target);
+        if growing {
+            snake.growth -= 1;
+        } else if let Some(tail) = snake.body.pop_back() {
+            self.occupancy.remove(tail, snake.id);
+        }
+        if snake.body.len() > 1 {
+            self.occupancy.set(vacated, snake.id, CellContent::Body);
+        }
+        self.occupancy.set(target, snake.id, CellContent::Head);
+
+        if ate {
+            self.spawn_food(target);
+        }
+    }
+
+    fn kill(&mut self, i: usize, cause: DeathCause) {
+        let snake = &mut self.snakes[i];
+        snake.death = Some(cause);
+        for &cell in &snake.body {
+            self.occupancy.remove(cell, snake.id);
+        }
+    }
+
+    fn spawn_food(&mut self, player_cell: IVec2) {
+        if let Some(cell) = pick_food_cell(
+            &self.occupancy,
+            &self.food_zones,
+            player_cell,
+            self.rules.wrap_edges,
+            &mut self.rng,
+        ) {
+            self.occupancy.set(cell, FOOD, CellContent::Food);
+            self.food.push(cell);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ai::ai::{AiController, Difficulty},
+        mode::mode::GameMode,
+        player::controller::ReplayController,
+    };
+
+    const BOARD: Board = Board {
+        half_extent: IVec2::new(10, 7),
+    };
+
+    fn ai_game(mode: GameMode, seed: u64) -> Sim {
+        let controllers = (0..mode.rules().players)
+            .map(|_| {
+                Box::new(AiController {
+                    difficulty: Difficulty::Safe,
+                }) as Box<dyn SnakeController>
+            })
+            .collect();
+        Sim::new(BOARD, None, mode.rules(), seed, controllers)
+    }
+
+    #[test]
+    fn the_same_seed_plays_the_same_game() {
+        let (mut a, mut b) = (ai_game(GameMode::Versus, 5), ai_game(GameMode::Versus, 5));
+        for _ in 0..300 {
+            a.step();
+            b.step();
+            assert_eq!(a.checksum(), b.checksum());
+        }
+        assert_ne!(
+            ai_game(GameMode::Versus, 5).food,
+            ai_game(GameMode::Versus, 6).food
+        );
+    }
+
+    #[test]
+    fn loading_a_save_goes_back_in_time() {
+        let mut sim = ai_game(GameMode::Classic, 1);
+        for _ in 0..20 {
+            sim.step();
+        }
+        let (state, checksum) = (sim.save(), sim.checksum());
+        for _ in 0..20 {
+            sim.step();
+        }
+        assert_ne!(sim.checksum(), checksum);
+        sim.load(&state);
+        assert_eq!(sim.checksum(), checksum);
+    }
+
+    #[test]
+    fn going_straight_runs_into_the_border() {
+        let controllers: Vec<Box<dyn SnakeController>> = vec![Box::new(ReplayController::new([]))];
+        let mut sim = Sim::new(BOARD, None, GameMode::Classic.rules(), 0, controllers);
+        let room = BOARD.width() as usize;
+        for _ in 0..room {
+            sim.step();
+        }
+        assert!(sim.is_over());
+        assert_eq!(sim.snakes[0].death, Some(DeathCause::Border));
+        // Nothing moves once it is over
+        let checksum = sim.checksum();
+        sim.step();
+        assert_eq!(sim.checksum(), checksum);
+    }
+
+    #[test]
+    fn apples_make_snakes_grow_and_score() {
+        let mut sim = ai_game(GameMode::Zen, 2);
+        for _ in 0..500 {
+            sim.step();
+        }
+        let snake = &sim.snakes[0];
+        assert!(snake.alive());
+        assert!(snake.score > 0);
+        assert_eq!(
+            snake.body.len() as u32,
+            1 + GameMode::Zen.rules().start_length + snake.apples - snake.growth
+        );
+    }
+}
@@ -0,0 +1,455 @@
+//! Round-robin between controllers. Every pair meets on the same boards over a
+//! range of seeds, once from each side, and Elo ratings are updated after
+//! every match. The table and replays of the most notable matches end up in
+//! an output directory.
+
+use crate::{
+    ai::ai::{AiController, Difficulty},
+    board::{board::Board, generator::BoardStyle},
+    mode::mode::GameMode,
+    player::{
+        bot::BotLink,
+        controller::{ReplayController, SnakeController},
+    },
+    sim::{replay::Replay, sim::Sim},
+};
+use bevy::prelude::*;
+use std::{fs, io, path::PathBuf};
+
+/// Rating every entrant starts on.
+const START_RATING: f64 = 1500.0;
+
+/// How far a single match moves a rating.
+const K_FACTOR: f64 = 24.0;
+
+/// Something that can play a snake.
+#[derive(Clone, Debug)]
+pub enum EntrantKind {
+    Ai(Difficulty),
+    Bot(BotLink),
+}
+
+#[derive(Clone, Debug)]
+pub struct Entrant {
+    pub name: String,
+    pub kind: EntrantKind,
+}
+
+impl Entrant {
+    fn controller(&self) -> Box<dyn SnakeController> {
+        match &self.kind {
+            EntrantKind::Ai(difficulty) => Box::new(AiController {
+                difficulty: *difficulty,
+            }),
+            EntrantKind::Bot(bot) => match bot.connect() {
+                Ok(bot) => Box::new(bot),
+                // A bot that is not there goes straight until it crashes
+                Err(err) => {
+                    eprintln!("Could not connect to {}: {err}", self.name);
+                    Box::new(ReplayController::new([]))
+                }
+            },
+        }
+    }
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct TournamentConfig {
+    pub entrants: Vec<Entrant>,
+    /// Seeds per pairing, each played once from either side.
+    pub seeds: u64,
+    pub first_seed: u64,
+    pub mode: GameMode,
+    pub style: BoardStyle,
+    pub board: Board,
+    /// Steps after which a match goes to the higher score.
+    pub max_ticks: u64,
+    pub out: PathBuf,
+}
+
+impl TournamentConfig {
+    /// Every match as the two entrants in snake order and the seed.
+    pub fn fixtures(&self) -> Vec<(usize, usize, u64)> {
+        let count = self.entrants.len();
+        let mut fixtures = Vec::new();
+        for a in 0..count {
+            for b in a + 1..count {
+                for seed in 0..self.seeds {
+                    let seed = self.first_seed.wrapping_add(seed);
+                    fixtures.push((a, b, seed));
+                    fixtures.push((b, a, seed));
+                }
+            }
+        }
+        fixtures
+    }
+}
+
+/// How a match ended for the two snakes in it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MatchResult {
+    /// Snake index of the winner.
+    Win(usize),
+    Draw,
+}
+
+#[derive(Clone)]
+pub struct MatchRecord {
+    pub players: [usize; 2],
+    pub result: MatchResult,
+    pub scores: [u32; 2],
+    pub ticks: u64,
+    pub replay: Replay,
+}
+
+/// Plays one match until a single snake is left, both are gone or the step
+/// limit is reached. A match that runs out of steps goes to the higher score.
+pub fn play_match(config: &TournamentConfig, players: [usize; 2], seed: u64) -> MatchRecord {
+    let [a, b] = players.map(|i| &config.entrants[i]);
+    let mut replay = Replay::new(
+        seed,
+        config.mode,
+        config.style,
+        config.board,
+        vec![a.name.clone(), b.name.clone()],
+    );
+    let layout = config.style.generate(config.board, seed);
+    let mut sim = Sim::new(
+        config.board,
+        layout.as_ref(),
+        config.mode.rules(),
+        seed,
+        vec![a.controller(), b.controller()],
+    );
+
+    let alive = |sim: &Sim| sim.snakes.iter().filter(|snake| snake.alive()).count();
+    while alive(&sim) > 1 && sim.ticks < config.max_ticks {
+        sim.step();
+        replay.record(&sim);
+    }
+
+    let scores = [sim.snakes[0].score, sim.snakes[1].score];
+    let result = match (sim.snakes[0].alive(), sim.snakes[1].alive()) {
+        (true, false) => MatchResult::Win(0),
+        (false, true) => MatchResult::Win(1),
+        _ if scores[0] > scores[1] => MatchResult::Win(0),
+        _ if scores[1] > scores[0] => MatchResult::Win(1),
+        _ => MatchResult::Draw,
+    };
+    MatchRecord {
+        players,
+        result,
+        scores,
+        ticks: sim.ticks,
+        replay,
+    }
+}
+
+/// Elo points the first snake gains from a match, the second loses as many.
+fn rating_change(ratings: [f64; 2], result: MatchResult) -> f64 {
+    let expected = 1.0 / (1.0 + 10f64.powf((ratings[1] - ratings[0]) / 400.0));
+    let outcome = match result {
+        MatchResult::Win(0) => 1.0,
+        MatchResult::Win(_) => 0.0,
+        MatchResult::Draw => 0.5,
+    };
+    K_FACTOR * (outcome - expected)
+}
+
+#[derive(Clone, Debug)]
+pub struct Standing {
+    pub rating: f64,
+    pub played: u32,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    pub total_score: u64,
+}
+
+impl Default for Standing {
+    fn default() -> Self {
+        Self {
+            rating: START_RATING,
+            played: 0,
+            wins: 0,
+            draws: 0,
+            losses: 0,
+            total_score: 0,
+        }
+    }
+}
+
+/// Notable matches, kept with their replays.
+#[derive(Default)]
+pub struct Highlights {
+    pub longest: Option<MatchRecord>,
+    pub highest_score: Option<MatchRecord>,
+    /// Win against the biggest rating gap, with that gap.
+    pub upset: Option<(f64, MatchRecord)>,
+}
+
+#[derive(Resource)]
+pub struct Tournament {
+    pub standings: Vec<Standing>,
+    pub highlights: Highlights,
+    fixtures: Vec<(usize, usize, u64)>,
+    next: usize,
+}
+
+impl Tournament {
+    pub fn new(config: &TournamentConfig) -> Self {
+        Self {
+            standings: vec![Standing::default(); config.entrants.len()],
+            highlights: Highlights::default(),
+            fixtures: config.fixtures(),
+            next: 0,
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.next >= self.fixtures.len()
+    }
+
+    /// Plays the next fixture and books its result.
+    pub fn play_next(&mut self, config: &TournamentConfig) {
+        let Some(&(a, b, seed)) = self.fixtures.get(self.next) else {
+            return;
+        };
+        self.next += 1;
+        let record = play_match(config, [a, b], seed);
+
+        let ratings = [self.standings[a].rating, self.standings[b].rating];
+        let change = rating_change(ratings, record.result);
+        for (slot, (player, sign)) in [(a, 1.0), (b, -1.0)].into_iter().enumerate() {
+            let standing = &mut self.standings[player];
+            standing.rating += sign * change;
+            standing.played += 1;
+            standing.total_score += record.scores[slot] as u64;
+            match record.result {
+                MatchResult::Win(winner) if winner == slot => standing.wins += 1,
+                MatchResult::Win(_) => standing.losses += 1,
+                MatchResult::Draw => standing.draws += 1,
+            }
+        }
+
+        let upset_gap = match record.result {
+            MatchResult::Win(winner) => ratings[1 - winner] - ratings[winner],
+            MatchResult::Draw => 0.0,
+        };
+        self.keep_highlight(record, upset_gap);
+    }
+
+    fn keep_highlight(&mut self, record: MatchRecord, upset_gap: f64) {
+        let highlights = &mut self.highlights;
+        let total = |record: &MatchRecord| record.scores[0] + record.scores[1];
+        if upset_gap > 0.0
+            && highlights
+                .upset
+                .as_ref()
+                .is_none_or(|(gap, _)| upset_gap > *gap)
+        {
+            highlights.upset = Some((upset_gap, record.clone()));
+        }
+        if highlights
+            .highest_score
+            .as_ref()
+            .is_none_or(|best| total(&record) > total(best))
+        {
+            highlights.highest_score = Some(record.clone());
+        }
+        if highlights
+            .longest
+            .as_ref()
+            .is_none_or(|longest| record.ticks > longest.ticks)
+        {
+            highlights.longest = Some(record);
+        }
+    }
+
+    /// Entrant indices from best to worst rating.
+    pub fn ranking(&self) -> Vec<usize> {
+        let mut ranking: Vec<usize> = (0..self.standings.len()).collect();
+        ranking.sort_by(|a, b| {
+            self.standings[*b]
+                .rating
+                .total_cmp(&self.standings[*a].rating)
+        });
+        ranking
+    }
+
+    /// The results as CSV, one line per entrant in ranking order.
+    pub fn table(&self, config: &TournamentConfig) -> String {
+        let mut table = String::from("rank,name,rating,played,wins,draws,losses,mean_score\n");
+        for (rank, i) in self.ranking().into_iter().enumerate() {
+            let standing = &self.standings[i];
+            table += &format!(
+                "{},{},{:.0},{},{},{},{},{:.1}\n",
+                rank + 1,
+                config.entrants[i].name,
+                standing.rating,
+                standing.played,
+                standing.wins,
+                standing.draws,
+                standing.losses,
+                standing.total_score as f64 / standing.played.max(1) as f64,
+            );
+        }
+        table
+    }
+
+    /// Writes `results.csv` and one replay per highlight into the output
+    /// directory.
+    pub fn write(&self, config: &TournamentConfig) -> io::Result<()> {
+        let replays = config.out.join("replays");
+        fs::create_dir_all(&replays)?;
+        fs::write(config.out.join("results.csv"), self.table(config))?;
+
+        let highlights = &self.highlights;
+        for (name, record) in [
+            ("longest", highlights.longest.as_ref()),
+            ("highest_score", highlights.highest_score.as_ref()),
+            ("upset", highlights.upset.as_ref().map(|(_, record)| record)),
+        ] {
+            if let Some(record) = record {
+                let json = serde_json::to_string(&record.replay).map_err(io::Error::from)?;
+                fs::write(replays.join(format!("{name}.json")), json)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Plays the [`TournamentConfig`] inserted into the app, one match per
+/// update, and exits once the results are written.
+pub struct TournamentPlugin;
+
+impl Plugin for TournamentPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, start_tournament)
+            .add_systems(Update, play_tournament);
+    }
+}
+
+fn start_tournament(mut commands: Commands, config: Res<TournamentConfig>) {
+    commands.insert_resource(Tournament::new(&config));
+}
+
+fn play_tournament(
+    config: Res<TournamentConfig>,
+    mut tournament: ResMut<Tournament>,
+    mut exit: EventWriter<AppExit>,
+    mut done: Local<bool>,
+) {
+    if *done {
+        return;
+    }
+    if !tournament.is_over() {
+        tournament.play_next(&config);
+        return;
+    }
+
+    *done = true;
+    print!("{}", tournament.table(&config));
+    match tournament.write(&config) {
+        Ok(()) => {
+            eprintln!("Results written to {}", config.out.display());
+            exit.write(AppExit::Success);
+        }
+        Err(err) => {
+            eprintln!("Could not write the results: {err}");
+            exit.write(AppExit::error());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(entrants: usize) -> TournamentConfig {
+        TournamentConfig {
+            entrants: (0..entrants)
+                .map(|i| Entrant {
+                    name: format!("ai{i}"),
+                    kind: EntrantKind::Ai(Difficulty::Safe),
+                })
+                .collect(),
+            seeds: 2,
+            first_seed: 10,
+            mode: GameMode::Versus,
+            style: BoardStyle::Open,
+            board: Board {
+                half_extent: IVec2::new(10, 7),
+            },
+            max_ticks: 200,
+            out: PathBuf::new(),
+        }
+    }
+
+    fn record(
+        players: [usize; 2],
+        result: MatchResult,
+        scores: [u32; 2],
+        ticks: u64,
+    ) -> MatchRecord {
+        let config = config(0);
+        MatchRecord {
+            players,
+            result,
+            scores,
+            ticks,
+            replay: Replay::new(0, config.mode, config.style, config.board, Vec::new()),
+        }
+    }
+
+    #[test]
+    fn elo_moves_by_how_surprising_the_result_is() {
+        let even = [START_RATING; 2];
+        assert_eq!(rating_change(even, MatchResult::Draw), 0.0);
+        assert_eq!(rating_change(even, MatchResult::Win(0)), K_FACTOR / 2.0);
+        assert_eq!(rating_change(even, MatchResult::Win(1)), -K_FACTOR / 2.0);
+
+        // 400 points apart the favourite is expected to score 10 out of 11
+        let apart = [START_RATING + 400.0, START_RATING];
+        let favourite_wins = rating_change(apart, MatchResult::Win(0));
+        let underdog_wins = rating_change(apart, MatchResult::Win(1));
+        assert!((favourite_wins - K_FACTOR / 11.0).abs() < 1e-9);
+        assert!((underdog_wins + K_FACTOR * 10.0 / 11.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn every_pair_meets_on_every_seed_from_both_sides() {
+        let fixtures = config(3).fixtures();
+        assert_eq!(fixtures.len(), 3 * 2 * 2);
+        for (a, b) in [(0, 1), (0, 2), (1, 2)] {
+            for seed in [10, 11] {
+                assert!(fixtures.contains(&(a, b, seed)));
+                assert!(fixtures.contains(&(b, a, seed)));
+            }
+        }
+        assert!(config(1).fixtures().is_empty());
+    }
+
+    #[test]
+    fn keeps_the_most_notable_matches() {
+        let mut tournament = Tournament::new(&config(3));
+        tournament.keep_highlight(record([0, 1], MatchResult::Win(0), [3, 1], 50), 0.0);
+        tournament.keep_highlight(record([1, 2], MatchResult::Win(1), [9, 8], 30), 40.0);
+        tournament.keep_highlight(record([2, 0], MatchResult::Draw, [2, 2], 80), 0.0);
+        tournament.keep_highlight(record([0, 2], MatchResult::Win(1), [0, 1], 10), 25.0);
+
+        let highlights = &tournament.highlights;
+        assert_eq!(highlights.longest.as_ref().unwrap().players, [2, 0]);
+        assert_eq!(highlights.highest_score.as_ref().unwrap().players, [1, 2]);
+        let (gap, upset) = highlights.upset.as_ref().unwrap();
+        assert_eq!((*gap, upset.players), (40.0, [1, 2]));
+    }
+
+    #[test]
+    fn a_draw_gives_no_upset() {
+        let mut tournament = Tournament::new(&config(2));
+        tournament.keep_highlight(record([0, 1], MatchResult::Draw, [1, 1], 5), 0.0);
+        assert!(tournament.highlights.upset.is_none());
+        assert!(tournament.highlights.longest.is_some());
+    }
+}
@@ -10,7 +10,9 @@ impl Plugin for CustomWindowPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(AppState::BootingApp), set_window_size_for_boot)
             .add_systems(OnEnter(AppState::MainMenu), set_window_size_for_main_menu)
-            .add_systems(OnEnter(AppState::InGameLoading), full_size_screen);
+            .add_systems(OnEnter(AppState::InGameLoading), full_size_screen)
+            .add_systems(OnEnter(AppState::Online), full_size_screen)
+            .add_systems(OnEnter(AppState::ServerMatch), full_size_screen);
         // .add_systems(OnEnter(AppState::InGame), full_size_screen);
     }
 }